@@ -1,8 +1,19 @@
+use std::sync::Arc;
+
+use crate::common::book::OpeningBook;
+use crate::common::clock::{Clock, ClockState};
 use crate::common::game_modes::{GameMode, GameType};
-use crate::common::logic::ChessPosition;
+use crate::common::logic::{ChessPosition, PlayerType};
+use crate::common::move_history::ChessMoveVector;
+use crate::common::protocol::{Encoding, DEFAULT_ADDRESS};
+use crate::common::time_control::TimeControl;
+use crate::common::variant::Variant;
+use crate::engine_adapter::Analysis;
 use crate::images::PieceImages;
-use crate::{client::GameClient, game_components, login_components};
-use eframe::glow::NONE;
+use crate::{
+    client::{GameClient, GameListing},
+    game_components, login_components,
+};
 use egui::{Color32, Pos2};
 use egui_extras::install_image_loaders;
 
@@ -15,8 +26,48 @@ const LIGHT: Color32 = Color32::from_rgb(255, 213, 153);
 pub struct ChessGame {
     page: Page,
     pub game_type: Option<GameType>,
+    /// Local , VsComputer or Online , picked on the login page
+    pub game_mode: GameMode,
+    pub computer_color: PlayerType,
+    pub computer_strength: u8,
+    /// path of a UCI engine program , used as the opponent and for analysis
+    pub engine_path: String,
+    #[serde(skip)]
+    pub analysis: Option<Analysis>,
+    #[serde(skip)]
+    pub analysis_error: Option<String>,
+    /// path of a polyglot `.bin` opening book
+    pub book_path: String,
+    #[serde(skip)]
+    pub book: Option<Arc<OpeningBook>>,
+    #[serde(skip)]
+    pub book_error: Option<String>,
+    /// pgn TimeControl value used instead of the time of the game type when given
+    pub time_control_string: String,
     pub local_fen_string: String,
+    pub local_pgn_string: String,
+    /// start from a chess960 position , or play the fen with chess960 castling
+    pub chess960: bool,
+    /// number of the chess960 start position , a random one when empty
+    pub chess960_position: String,
+    /// rules of the next local game
+    pub variant: Variant,
+    /// start position of an Online game created by joining it
     pub online_fen_string: String,
+    /// host and port of a `rusty_chess_server`
+    pub server_address: String,
+    pub online_game_id: String,
+    pub online_user_id: String,
+    /// JSON messages instead of binary ones , readable when debugging
+    pub online_json: bool,
+    /// chat message being typed in an Online game
+    pub chat_input: String,
+    /// why the server could not be reached
+    #[serde(skip)]
+    pub online_error: Option<String>,
+    /// the games going on at the server , once asked for
+    #[serde(skip)]
+    pub game_listing: Option<GameListing>,
 
     pub client: GameClient,
     #[serde(skip)]
@@ -27,6 +78,13 @@ pub struct ChessGame {
 
     pub mouse_pos: Option<Pos2>,
     pub pos_held: Option<ChessPosition>,
+    /// pawn move waiting for the player to pick a promotion piece
+    pub pending_promotion: Option<ChessMoveVector>,
+    /// ply shown on the board when looking at an earlier position , None shows the latest
+    pub viewed_ply: Option<usize>,
+    /// move typed into the side panel in SAN
+    pub move_input: String,
+    pub move_input_error: bool,
     pub tile_width: f32,
     pub window_open: bool,
     pub result_text: String,
@@ -35,19 +93,117 @@ impl PartialEq for ChessGame {
     fn eq(&self, other: &Self) -> bool {
         self.game_type == other.game_type
             && self.local_fen_string == other.local_fen_string
+            && self.local_pgn_string == other.local_pgn_string
             && self.online_fen_string == other.online_fen_string
     }
 }
 impl ChessGame {
     pub fn start_local_game(&mut self) {
         if let Some(game_type) = self.game_type {
-            self.client = GameClient::new_local(game_type);
+            self.client = match self.game_mode {
+                GameMode::VsComputer => GameClient::new_vs_computer(
+                    game_type,
+                    self.computer_color,
+                    self.computer_strength,
+                    Some(self.engine_path.trim().to_string()).filter(|path| !path.is_empty()),
+                ),
+                _ => GameClient::new_local(game_type),
+            };
+            if let Some(time_control) = TimeControl::parse(&self.time_control_string) {
+                self.client.clock = Clock::new(time_control);
+            }
+            if let Some(computer) = &mut self.client.computer {
+                computer.book = self.book.clone();
+            }
+            self.pending_promotion = None;
+            self.viewed_ply = None;
             self.page = Page::InGame(LoginInfo {
                 game_id: "local".to_string(),
                 user_id: "local".to_string(),
             });
         }
     }
+    /// joins the game on the server , the game is created when no one has joined it yet
+    pub fn start_online_game(&mut self) {
+        let Some(game_type) = self.game_type else {
+            return;
+        };
+        let time_control = TimeControl::parse(&self.time_control_string)
+            .unwrap_or_else(|| game_type.time_control());
+        let fen = Some(self.online_fen_string.trim().to_string()).filter(|fen| !fen.is_empty());
+        match GameClient::new_online(
+            self.server_address.trim(),
+            self.online_game_id.trim(),
+            self.online_user_id.trim(),
+            &time_control,
+            self.variant,
+            fen,
+            self.online_encoding(),
+        ) {
+            Ok(client) => {
+                self.client = client;
+                self.online_error = None;
+                self.pending_promotion = None;
+                self.viewed_ply = None;
+                self.page = Page::InGame(LoginInfo {
+                    game_id: self.online_game_id.trim().to_string(),
+                    user_id: self.online_user_id.trim().to_string(),
+                });
+            }
+            Err(error) => self.online_error = Some(error.to_string()),
+        }
+    }
+    /// watches the game on the server
+    pub fn start_spectating(&mut self, game_id: &str) {
+        match GameClient::new_spectator(self.server_address.trim(), game_id, self.online_encoding())
+        {
+            Ok(client) => {
+                self.client = client;
+                self.online_error = None;
+                self.game_listing = None;
+                self.pending_promotion = None;
+                self.viewed_ply = None;
+                self.page = Page::InGame(LoginInfo {
+                    game_id: game_id.to_string(),
+                    user_id: String::default(),
+                });
+            }
+            Err(error) => self.online_error = Some(error.to_string()),
+        }
+    }
+    /// asks the server for the games going on
+    pub fn list_games(&mut self) {
+        match GameListing::request(self.server_address.trim(), self.online_encoding()) {
+            Ok(game_listing) => {
+                self.game_listing = Some(game_listing);
+                self.online_error = None;
+            }
+            Err(error) => self.online_error = Some(error.to_string()),
+        }
+    }
+    fn online_encoding(&self) -> Encoding {
+        if self.online_json {
+            Encoding::Json
+        } else {
+            Encoding::Binary
+        }
+    }
+    /// reads the book at `book_path` , the computer of a running game plays from it too
+    pub fn load_book(&mut self) {
+        match OpeningBook::open(self.book_path.trim()) {
+            Ok(book) => {
+                self.book = Some(Arc::new(book));
+                self.book_error = None;
+            }
+            Err(error) => {
+                self.book = None;
+                self.book_error = Some(error.to_string());
+            }
+        }
+        if let Some(computer) = &mut self.client.computer {
+            computer.book = self.book.clone();
+        }
+    }
 }
 #[derive(serde::Deserialize, serde::Serialize)]
 enum Page {
@@ -66,13 +222,38 @@ impl Default for ChessGame {
             mouse_pos: None,
             page: Page::Login,
             game_type: None,
+            game_mode: GameMode::Local,
+            computer_color: PlayerType::Black,
+            computer_strength: 3,
+            engine_path: String::default(),
+            analysis: None,
+            analysis_error: None,
+            book_path: String::default(),
+            book: None,
+            book_error: None,
+            time_control_string: String::default(),
             local_fen_string: String::default(),
+            local_pgn_string: String::default(),
+            chess960: false,
+            chess960_position: String::default(),
+            variant: Variant::Standard,
             online_fen_string: String::default(),
+            server_address: DEFAULT_ADDRESS.to_string(),
+            online_game_id: String::default(),
+            online_user_id: String::default(),
+            online_json: false,
+            chat_input: String::default(),
+            online_error: None,
+            game_listing: None,
             client: GameClient::default(),
             images: PieceImages::default(),
             color_black: DARK,
             color_white: LIGHT,
             pos_held: None,
+            pending_promotion: None,
+            viewed_ply: None,
+            move_input: String::default(),
+            move_input_error: false,
             tile_width: 100.0,
             window_open: false,
             result_text: String::default(),
@@ -88,7 +269,14 @@ impl ChessGame {
         // Load previous app state (if any).
         // Note that you must enable the `persistence` feature for this to work.
         if let Some(storage) = cc.storage {
-            return eframe::get_value(storage, eframe::APP_KEY).unwrap_or_default();
+            let mut chess_game: Self =
+                eframe::get_value(storage, eframe::APP_KEY).unwrap_or_default();
+            // no time passes for the players while the app is closed
+            chess_game.client.clock.pause();
+            if !chess_game.book_path.trim().is_empty() {
+                chess_game.load_book();
+            }
+            return chess_game;
         }
         log::info!("the app is starting for the first time");
         Default::default()
@@ -97,28 +285,29 @@ impl ChessGame {
 
 impl eframe::App for ChessGame {
     fn save(&mut self, storage: &mut dyn eframe::Storage) {
+        // the clock is saved paused , no time passes for the players while the app is closed
+        let running = self.client.clock.state == ClockState::Running;
+        self.client.clock.pause();
         eframe::set_value(storage, eframe::APP_KEY, self);
+        if running {
+            self.client.clock.resume();
+        }
     }
 
     fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
+        // nor while it is minimized or in a hidden tab , Online clocks are the server's
+        if self.client.game_mode != Some(GameMode::Online) {
+            let away = ctx.input(|i| !i.focused || i.viewport().minimized == Some(true));
+            self.client.clock.set_away(away);
+        }
         if self.window_open {
             egui::Window::new("Modal Window")
                 .open(&mut self.window_open)
                 .show(ctx, |ui| {
-                    ui.label(&self.result_text);
+                    ui.heading(&self.result_text);
                 });
         }
 
-        if self
-            .client
-            .game_mode
-            .is_some_and(|game_mode| game_mode == GameMode::Local)
-        {
-            self.client
-                .clock
-                .update_time(self.client.game_state.active_color);
-        }
-
         egui::TopBottomPanel::top("top_menu_panel").show(ctx, |ui| {
             // The top panel is often a good place for a menu bar:
             egui::menu::bar(ui, |ui| {
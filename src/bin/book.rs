@@ -0,0 +1,46 @@
+#![warn(clippy::all, rust_2018_idioms)]
+//! builds a polyglot opening book from pgn games , `rusty_chess_book games.pgn book.bin [plies]`
+
+use rusty_chess::common::book::OpeningBook;
+use rusty_chess::common::pgn;
+
+/// plies of each game that go into the book unless asked otherwise
+const DEFAULT_PLIES: usize = 16;
+
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let (pgn_path, book_path) = match (args.first(), args.get(1)) {
+        (Some(pgn_path), Some(book_path)) => (pgn_path, book_path),
+        _ => {
+            eprintln!("usage : rusty_chess_book <games.pgn> <book.bin> [plies]");
+            std::process::exit(2);
+        }
+    };
+    let max_plies = match args.get(2).map(|plies| plies.parse::<usize>()) {
+        None => DEFAULT_PLIES,
+        Some(Ok(plies)) => plies,
+        Some(Err(_)) => {
+            eprintln!("plies has to be a number");
+            std::process::exit(2);
+        }
+    };
+    let text = match std::fs::read_to_string(pgn_path) {
+        Ok(text) => text,
+        Err(error) => {
+            eprintln!("can not read {} : {}", pgn_path, error);
+            std::process::exit(1);
+        }
+    };
+    let games = pgn::parse_pgn(&text);
+    let book = OpeningBook::from_games(&games, max_plies);
+    if let Err(error) = book.save(book_path) {
+        eprintln!("can not write {} : {}", book_path, error);
+        std::process::exit(1);
+    }
+    println!(
+        "{} games , {} book entries written to {}",
+        games.len(),
+        book.entries().len(),
+        book_path
+    );
+}
@@ -0,0 +1,43 @@
+#![warn(clippy::all, rust_2018_idioms)]
+//! the game server for Online games ,
+//! `rusty_chess_server [address] [abandon seconds] [spectator delay seconds]` , a player away
+//! for the abandon seconds loses the game , spectators see moves after the delay
+
+use std::net::TcpListener;
+use std::time::Duration;
+
+use rusty_chess::common::protocol::DEFAULT_ADDRESS;
+use rusty_chess::server::{self, ServerConfig};
+
+fn main() {
+    let mut args = std::env::args().skip(1);
+    let address = args.next().unwrap_or_else(|| DEFAULT_ADDRESS.to_string());
+    let mut config = ServerConfig::default();
+    for (setting, name) in [
+        (&mut config.abandon_timeout, "abandon seconds"),
+        (&mut config.spectator_delay, "spectator delay seconds"),
+    ] {
+        let Some(seconds) = args.next() else {
+            break;
+        };
+        match seconds.parse() {
+            Ok(seconds) => *setting = Duration::from_secs(seconds),
+            Err(_) => {
+                eprintln!("{} must be a whole number , not {}", name, seconds);
+                std::process::exit(1);
+            }
+        }
+    }
+    let listener = match TcpListener::bind(&address) {
+        Ok(listener) => listener,
+        Err(error) => {
+            eprintln!("can not listen on {} : {}", address, error);
+            std::process::exit(1);
+        }
+    };
+    println!("serving games on {}", address);
+    if let Err(error) = server::serve(listener, config) {
+        eprintln!("server stopped : {}", error);
+        std::process::exit(1);
+    }
+}
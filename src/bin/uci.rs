@@ -0,0 +1,147 @@
+#![warn(clippy::all, rust_2018_idioms)]
+//! the rules core and engine speaking UCI over stdin and stdout , for chess guis and tournaments
+
+use std::io::{BufRead, Write};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+use rusty_chess::common::engine;
+use rusty_chess::common::logic::GameState;
+use rusty_chess::common::transposition::{TranspositionTable, DEFAULT_SIZE_MB};
+use rusty_chess::common::uci::{self, UciCommand};
+use rusty_chess::common::variant::{Variant, VARIANTS};
+
+/// a search running on its own thread , it prints its own info and bestmove lines
+struct RunningSearch {
+    stop: Arc<AtomicBool>,
+    handle: JoinHandle<()>,
+}
+
+impl RunningSearch {
+    fn finish(self) {
+        self.stop.store(true, Ordering::Relaxed);
+        let _ = self.handle.join();
+    }
+}
+
+fn send(line: &str) {
+    let mut stdout = std::io::stdout().lock();
+    let _ = writeln!(stdout, "{}", line);
+    let _ = stdout.flush();
+}
+
+fn main() {
+    let mut game_state = GameState::default();
+    let mut search: Option<RunningSearch> = None;
+    // time kept back from every move for the gui and the pipe
+    let mut move_overhead = Duration::from_millis(30);
+    let mut chess960 = false;
+    let mut variant = Variant::Standard;
+    // kept from one search to the next , only the searching thread uses it
+    let table = Arc::new(Mutex::new(TranspositionTable::default()));
+
+    for line in std::io::stdin().lock().lines() {
+        let Ok(line) = line else {
+            break;
+        };
+        let Some(command) = uci::parse_command(&line) else {
+            continue;
+        };
+        match command {
+            UciCommand::Uci => {
+                send(&format!(
+                    "id name rusty chess {}",
+                    env!("CARGO_PKG_VERSION")
+                ));
+                send("id author the rusty chess authors");
+                send("option name Move Overhead type spin default 30 min 0 max 5000");
+                send(&format!(
+                    "option name Hash type spin default {} min 1 max 1024",
+                    DEFAULT_SIZE_MB
+                ));
+                send("option name UCI_Chess960 type check default false");
+                let variant_names: Vec<String> = VARIANTS
+                    .iter()
+                    .map(|variant| format!("var {}", variant.uci_name()))
+                    .collect();
+                send(&format!(
+                    "option name UCI_Variant type combo default chess {}",
+                    variant_names.join(" ")
+                ));
+                send("uciok");
+            }
+            UciCommand::IsReady => send("readyok"),
+            UciCommand::SetOption { name, value } => {
+                if name.eq_ignore_ascii_case("UCI_Chess960") {
+                    chess960 = value.is_some_and(|value| value.eq_ignore_ascii_case("true"));
+                    continue;
+                }
+                if name.eq_ignore_ascii_case("UCI_Variant") {
+                    variant = VARIANTS
+                        .into_iter()
+                        .find(|known| value.as_deref() == Some(known.uci_name()))
+                        .unwrap_or_default();
+                    continue;
+                }
+                let number = value.and_then(|value| value.parse().ok());
+                if name.eq_ignore_ascii_case("Move Overhead") {
+                    if let Some(millis) = number {
+                        move_overhead = Duration::from_millis(millis);
+                    }
+                } else if name.eq_ignore_ascii_case("Hash") {
+                    if let Some(size_mb) = number {
+                        finish(&mut search);
+                        table
+                            .lock()
+                            .unwrap()
+                            .resize(size_mb.clamp(1, 1024) as usize);
+                    }
+                }
+            }
+            UciCommand::UciNewGame => {
+                finish(&mut search);
+                game_state = GameState::default();
+                table.lock().unwrap().clear();
+            }
+            UciCommand::Position { fen, moves } => {
+                match uci::setup_position(fen.as_deref(), &moves, variant, chess960) {
+                    Some(new_state) => game_state = new_state,
+                    None => send("info string illegal position or move"),
+                }
+            }
+            UciCommand::Go(params) => {
+                finish(&mut search);
+                let limits = params.limits(game_state.active_color, move_overhead);
+                let stop = Arc::new(AtomicBool::new(false));
+                let thread_stop = stop.clone();
+                let game_state = game_state.search_copy();
+                let table = table.clone();
+                let handle = std::thread::spawn(move || {
+                    let start = Instant::now();
+                    let mut table = table.lock().unwrap();
+                    let result = engine::search_with_progress(
+                        &game_state,
+                        limits,
+                        &thread_stop,
+                        &mut table,
+                        |result| send(&uci::info_line(result, start.elapsed())),
+                    );
+                    send(&uci::best_move_line(result.best_move));
+                });
+                search = Some(RunningSearch { stop, handle });
+            }
+            UciCommand::Stop => finish(&mut search),
+            UciCommand::Quit => break,
+            UciCommand::Debug(_) | UciCommand::PonderHit => {}
+        }
+    }
+    finish(&mut search);
+}
+
+fn finish(search: &mut Option<RunningSearch>) {
+    if let Some(search) = search.take() {
+        search.finish();
+    }
+}
@@ -1,28 +1,293 @@
+use std::io;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{mpsc, Arc, Mutex};
+use std::time::{Duration, SystemTime};
+
 use crate::common::{
-    clock::Clock,
+    bitboard::Rng,
+    book::OpeningBook,
+    clock::{Clock, ClockState},
+    engine::{self, SearchLimits},
+    fen::FenError,
     game_modes::{GameMode, GameType},
-    logic::{ChessPosition, GameState},
+    logic::{ChessPosition, GameState, MoveResult, PlayerType},
+    move_history::ChessMove,
+    pgn::{self, PgnGame},
+    protocol::{Encoding, GameSummary, Message, PROTOCOL_VERSION},
+    time_control::TimeControl,
+    transposition::TranspositionTable,
+    uci::{self, EngineMessage},
+    variant::Variant,
 };
-#[derive(serde::Deserialize, serde::Serialize)]
+use crate::engine_adapter::{ExternalEngine, StartingEngine};
+use transport::Transport;
+
+pub mod transport;
+
+/// centipawns the computer has to be behind before it takes a draw offer
+const DRAW_ACCEPT_MARGIN: i32 = 150;
+/// how long an Online game waits between attempts to reach the server again
+const RECONNECT_INTERVAL: Duration = Duration::from_secs(2);
+
+#[derive(serde::Deserialize, serde::Serialize, Default)]
 pub struct GameClient {
     pub game_state: GameState,
     pub game_type: Option<GameType>,
     has_started: bool,
     pub clock: Clock,
+    /// plies of the game the clock has been switched for
+    #[serde(default)]
+    clock_ply: usize,
     pub game_mode: Option<GameMode>,
+    /// the engine playing one side , only in VsComputer games
+    pub computer: Option<ComputerPlayer>,
+    /// the connection to the server , only in Online games , kept so a restarted app can
+    /// reconnect
+    #[serde(default)]
+    pub online: Option<OnlineGame>,
+}
+
+/// the engine side of a VsComputer game , searches run on their own thread
+#[derive(serde::Deserialize, serde::Serialize)]
+pub struct ComputerPlayer {
+    pub color: PlayerType,
+    /// 1 to engine::MAX_STRENGTH
+    pub strength: u8,
+    /// UCI engine program playing instead of the built in engine
+    #[serde(default)]
+    pub engine_path: Option<String>,
+    /// why the external engine could not be used , the built in one plays then
+    #[serde(skip)]
+    pub engine_error: Option<String>,
+    #[serde(skip)]
+    search: Option<RunningSearch>,
+    /// shared by the searches of the game , one at a time
+    #[serde(skip)]
+    table: Arc<Mutex<TranspositionTable>>,
+    /// the external engine until it has answered its handshake
+    #[serde(skip)]
+    starting: Option<StartingEngine>,
+    #[serde(skip)]
+    external: Option<ExternalEngine>,
+    /// ply the external engine is searching
+    #[serde(skip)]
+    external_ply: Option<usize>,
+    /// ply at which the computer last turned a draw offer down
+    #[serde(skip)]
+    pub declined_draw_ply: Option<usize>,
+    /// opening moves are taken from here while the game is in book
+    #[serde(skip)]
+    pub book: Option<Arc<OpeningBook>>,
+}
+
+/// the player's side of an Online game , the server has the say over moves and clocks
+#[derive(serde::Deserialize, serde::Serialize)]
+pub struct OnlineGame {
+    /// of the server , see `transport::connect`
+    pub address: String,
+    pub encoding: Encoding,
+    pub game_id: String,
+    pub user_id: String,
+    /// None until the server has given the player a seat
+    pub color: Option<PlayerType>,
+    /// None until the opponent has joined
+    pub opponent: Option<String>,
+    /// what the server refused last , or why the connection was lost
+    #[serde(skip)]
+    pub error: Option<String>,
+    /// the protocol version the server agreed to , servers of version 1 take no draw offers ,
+    /// resigning or chat
+    pub version: u32,
+    /// sender and text of the chat messages , oldest first
+    pub chat: Vec<(String, String)>,
+    /// watching the game instead of playing it , `color` stays None
+    #[serde(default)]
+    pub spectating: bool,
+    /// user ids of white and black , as spectators are told them
+    #[serde(default)]
+    pub players: [Option<String>; 2],
+    /// how far behind the game spectators are shown it
+    #[serde(default)]
+    pub spectator_delay: Duration,
+    /// the secret the seat is taken back with after the connection was lost , sent by the
+    /// server in `Message::Game`
+    #[serde(default)]
+    seat_token: Option<String>,
+    /// None while the connection is lost , it is made again every `RECONNECT_INTERVAL`
+    #[serde(skip)]
+    transport: Option<Box<dyn Transport>>,
+    /// whether the server has sent the game since connecting , moves wait until then
+    #[serde(skip)]
+    synced: bool,
+    #[serde(skip)]
+    reconnect_at: Option<SystemTime>,
+    /// plies the server has been told about
+    #[serde(skip)]
+    sent_ply: usize,
 }
 
-impl Default for GameClient {
-    fn default() -> Self {
+/// the games going on at a server , asked for to pick one to watch
+pub struct GameListing {
+    transport: Box<dyn Transport>,
+    /// None until the server has answered
+    pub games: Option<Vec<GameSummary>>,
+    pub error: Option<String>,
+}
+
+struct RunningSearch {
+    receiver: mpsc::Receiver<Option<ChessMove>>,
+    stop: Arc<AtomicBool>,
+    /// ply the search was started at , its move is thrown away if the game moved on
+    ply: usize,
+}
+
+impl Drop for RunningSearch {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+    }
+}
+
+impl ComputerPlayer {
+    pub fn new(color: PlayerType, strength: u8, engine_path: Option<String>) -> Self {
         Self {
-            game_state: GameState::default(),
-            game_type: None,
-            has_started: false,
-            clock: Clock::default(),
-            game_mode: None,
+            color,
+            strength,
+            engine_path,
+            engine_error: None,
+            search: None,
+            table: Arc::default(),
+            starting: None,
+            external: None,
+            declined_draw_ply: None,
+            external_ply: None,
+            book: None,
+        }
+    }
+    pub fn is_thinking(&self) -> bool {
+        self.search.is_some() || self.starting.is_some() || self.external_ply.is_some()
+    }
+    /// starts a search on the position if none is running , and hands out its move once found
+    fn poll(&mut self, game_state: &GameState) -> Option<ChessMove> {
+        if let Some(book_move) = self.book_move(game_state) {
+            return Some(book_move);
+        }
+        if self.engine_path.is_some() && self.engine_error.is_none() {
+            return self.poll_external(game_state);
+        }
+        let ply = game_state.move_history.len();
+        if self.search.as_ref().is_some_and(|search| search.ply != ply) {
+            self.search = None;
+        }
+        match &self.search {
+            Some(search) => match search.receiver.try_recv() {
+                Ok(chess_move) => {
+                    self.search = None;
+                    chess_move
+                }
+                Err(mpsc::TryRecvError::Empty) => None,
+                Err(mpsc::TryRecvError::Disconnected) => {
+                    self.search = None;
+                    None
+                }
+            },
+            None => {
+                let (sender, receiver) = mpsc::channel();
+                let stop = Arc::new(AtomicBool::new(false));
+                let limits = SearchLimits::from_strength(self.strength);
+                let thread_stop = stop.clone();
+                let game_state = game_state.search_copy();
+                let table = self.table.clone();
+                let run_search = move || {
+                    let mut table = table.lock().unwrap();
+                    let result = engine::search(&game_state, limits, &thread_stop, &mut table);
+                    // the receiver is gone if the game was closed meanwhile
+                    let _ = sender.send(result.best_move);
+                };
+                // there are no threads on the web , the search blocks the frame there
+                #[cfg(not(target_arch = "wasm32"))]
+                std::thread::spawn(run_search);
+                #[cfg(target_arch = "wasm32")]
+                run_search();
+                self.search = Some(RunningSearch {
+                    receiver,
+                    stop,
+                    ply,
+                });
+                None
+            }
+        }
+    }
+    /// a weighted random move from the book , different from game to game
+    fn book_move(&self, game_state: &GameState) -> Option<ChessMove> {
+        // books are built from standard games
+        if game_state.variant() != Variant::Standard {
+            return None;
+        }
+        let book = self.book.as_ref()?;
+        let seed = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .ok()?
+            .as_nanos() as u64;
+        // the seed must not be 0
+        book.pick_move(game_state, &mut Rng(seed | 1))
+    }
+    /// same as poll for an external engine , the engine is started on first use , once it
+    /// fails the built in engine plays
+    fn poll_external(&mut self, game_state: &GameState) -> Option<ChessMove> {
+        if self.external.is_none() {
+            let path = self.engine_path.as_deref()?;
+            let starting = self
+                .starting
+                .get_or_insert_with(|| ExternalEngine::start(path));
+            match starting.poll() {
+                Ok(Some(engine)) => {
+                    self.starting = None;
+                    self.external = Some(engine);
+                }
+                Ok(None) => return None,
+                Err(error) => {
+                    self.starting = None;
+                    self.engine_error = Some(error.to_string());
+                    return None;
+                }
+            }
+        }
+        let engine = self.external.as_mut()?;
+        let ply = game_state.move_history.len();
+        if self.external_ply.is_none() {
+            let limits = SearchLimits::from_strength(self.strength);
+            let go = format!("go movetime {}", limits.time_limit.as_millis());
+            if let Err(error) = engine.go(game_state, &go) {
+                self.engine_error = Some(error.to_string());
+                return None;
+            }
+            self.external_ply = Some(ply);
+        }
+        loop {
+            let message = match engine.try_recv() {
+                Ok(Some(message)) => message,
+                Ok(None) => return None,
+                Err(error) => {
+                    self.engine_error = Some(error.to_string());
+                    self.external = None;
+                    self.external_ply = None;
+                    return None;
+                }
+            };
+            if let EngineMessage::BestMove { best_move, .. } = message {
+                let searched_ply = self.external_ply.take();
+                if searched_ply == Some(ply) {
+                    let chess_move = uci::move_from_uci(game_state, &best_move);
+                    if chess_move.is_none() {
+                        self.engine_error = Some(format!("the engine played {}", best_move));
+                    }
+                    return chess_move;
+                }
+            }
         }
     }
 }
+
 impl GameClient {
     pub fn new_local(game_type: GameType) -> Self {
         GameClient {
@@ -30,13 +295,126 @@ impl GameClient {
             game_type: Some(game_type),
             has_started: true,
             clock: Clock::from_game_type(game_type),
+            clock_ply: 0,
             game_mode: Some(GameMode::Local),
+            computer: None,
+            online: None,
+        }
+    }
+    /// `engine_path` picks an external UCI engine instead of the built in one
+    pub fn new_vs_computer(
+        game_type: GameType,
+        computer_color: PlayerType,
+        strength: u8,
+        engine_path: Option<String>,
+    ) -> Self {
+        GameClient {
+            game_mode: Some(GameMode::VsComputer),
+            computer: Some(ComputerPlayer::new(computer_color, strength, engine_path)),
+            ..Self::new_local(game_type)
         }
     }
-    pub fn update_fen(&mut self, fen: &str) -> Option<()> {
-        self.game_state = GameState::from_fen(fen)?;
+    /// plays the position with the rules of the variant , with `chess960` castling works from
+    /// wherever the king and rooks start
+    pub fn update_fen(
+        &mut self,
+        fen: &str,
+        variant: Variant,
+        chess960: bool,
+    ) -> Result<(), FenError> {
+        self.game_state = GameState::from_fen_variant(fen, variant, chess960)?;
+        self.clock_ply = 0;
+        Ok(())
+    }
+    /// connects to the server at `address` and joins the game , which is created with the time
+    /// control , variant and start position when no one has joined it yet
+    pub fn new_online(
+        address: &str,
+        game_id: &str,
+        user_id: &str,
+        time_control: &TimeControl,
+        variant: Variant,
+        fen: Option<String>,
+        encoding: Encoding,
+    ) -> io::Result<Self> {
+        let mut online = OnlineGame::connect(address, game_id, user_id, encoding)?;
+        online.send(&Message::Join {
+            game_id: game_id.to_string(),
+            user_id: user_id.to_string(),
+            time_control: time_control.to_string(),
+            variant,
+            fen,
+            version: PROTOCOL_VERSION,
+            seat_token: None,
+        })?;
+        Ok(GameClient {
+            game_state: GameState::from_fen_variant(variant.start_fen(), variant, false)
+                .unwrap_or_default(),
+            game_type: None,
+            has_started: true,
+            clock: Clock::new(time_control.clone()),
+            clock_ply: 0,
+            game_mode: Some(GameMode::Online),
+            computer: None,
+            online: Some(online),
+        })
+    }
+    /// connects to the server at `address` and watches the game , the board can not be played
+    /// on
+    pub fn new_spectator(address: &str, game_id: &str, encoding: Encoding) -> io::Result<Self> {
+        let mut online = OnlineGame::connect(address, game_id, "", encoding)?;
+        online.spectating = true;
+        online.send(&Message::Watch {
+            game_id: game_id.to_string(),
+            version: PROTOCOL_VERSION,
+        })?;
+        Ok(GameClient {
+            game_state: GameState::default(),
+            game_type: None,
+            has_started: true,
+            clock: Clock::new(GameType::Blitz.time_control()),
+            clock_ply: 0,
+            game_mode: Some(GameMode::Online),
+            computer: None,
+            online: Some(online),
+        })
+    }
+    /// replays the first game of the pgn text
+    pub fn load_pgn(&mut self, pgn_text: &str) -> Option<()> {
+        self.game_state = pgn::parse_pgn(pgn_text).first()?.replay()?;
+        // the moves of the pgn were played on some other clock
+        self.clock_ply = self.game_state.move_history.len();
         Some(())
     }
+    pub fn to_pgn(&self) -> String {
+        let time_control = self.game_type.map(|_| &self.clock.time_control);
+        PgnGame::from_game_state(&self.game_state, time_control).to_pgn()
+    }
+    /// runs the clock of the player to move and switches it once they have moved , called every
+    /// frame , Some when a flag falls
+    pub fn update_clock(&mut self) -> Option<MoveResult> {
+        let ply = self.game_state.move_history.len();
+        if ply > self.clock_ply {
+            // the time since the last frame went to the player who moved
+            let mover = self.game_state.active_color.opponent();
+            self.clock.switch(mover);
+            if self.clock.state == ClockState::Flagged(mover) {
+                self.clock_ply = ply;
+                return self.game_state.flag(mover);
+            }
+        }
+        // undone moves are not given back
+        self.clock_ply = ply;
+        if self.game_state.is_game_over() {
+            self.clock.pause();
+            return None;
+        }
+        let active_color = self.game_state.active_color;
+        match self.clock.update_time(active_color) {
+            Some(()) => None,
+            None => self.game_state.flag(active_color),
+        }
+    }
     pub fn possible_move_ends(&self, pos: &ChessPosition) -> Vec<ChessPosition> {
         self.game_state
             .generate_legal_moves_for_pos(pos)
@@ -44,4 +422,344 @@ impl GameClient {
             .map(|m| m.move_vector.to_pos)
             .collect()
     }
+    /// whether the player at the screen may move now , never once the game is over
+    pub fn is_human_turn(&self) -> bool {
+        let active_color = self.game_state.active_color;
+        !self
+            .computer
+            .as_ref()
+            .is_some_and(|computer| computer.color == active_color)
+            && self.online.as_ref().map_or(true, |online| {
+                online.color == Some(active_color)
+                    && online.opponent.is_some()
+                    && online.is_connected()
+            })
+            && !self.game_state.is_game_over()
+            && self.clock.state != ClockState::Paused
+    }
+    /// the side the board is shown from
+    pub fn perspective(&self, game_state: &GameState) -> PlayerType {
+        if let Some(online) = &self.online {
+            // spectators see the board from white's side
+            return online.color.unwrap_or(PlayerType::White);
+        }
+        match &self.computer {
+            Some(computer) => computer.color.opponent(),
+            None => game_state.active_color,
+        }
+    }
+    /// lets the computer move when it is its turn , called every frame
+    pub fn update_computer(&mut self) -> Option<MoveResult> {
+        let computer = self.computer.as_mut()?;
+        if computer.color != self.game_state.active_color
+            || self.game_state.is_game_over()
+            || self.clock.state == ClockState::Paused
+        {
+            return None;
+        }
+        // the computer takes a draw when it is worse off
+        if self.game_state.draw_offer == Some(computer.color.opponent()) {
+            if engine::evaluate(&self.game_state) < -DRAW_ACCEPT_MARGIN {
+                return self.game_state.accept_draw();
+            }
+            self.game_state.decline_draw();
+            computer.declined_draw_ply = Some(self.game_state.move_history.len());
+        }
+        let chess_move = computer.poll(&self.game_state)?;
+        Some(self.game_state.do_move(&chess_move))
+    }
+    /// sends the moves made at the screen and takes in what the server says , called every
+    /// frame , Some when a move of the opponent or the server ends the game
+    pub fn update_online(&mut self) -> Option<MoveResult> {
+        let online = self.online.as_mut()?;
+        if !self.game_state.is_game_over() {
+            online.reconnect_if_lost();
+        }
+        let entries = self.game_state.move_history.entries();
+        while online.is_connected() && online.sent_ply < entries.len() {
+            let ply = online.sent_ply;
+            let message = Message::Move {
+                ply,
+                uci: entries[ply].chess_move.to_string(),
+            };
+            if online.send(&message).is_err() {
+                break;
+            }
+            online.sent_ply += 1;
+        }
+        let mut move_result = None;
+        while let Some(message) = online.try_recv() {
+            // players and spectators are sent the whole game in different messages
+            let whole_game = match message {
+                Message::Game {
+                    color,
+                    opponent,
+                    time_control,
+                    variant,
+                    start_fen,
+                    moves,
+                    clock,
+                    game_end,
+                    version,
+                    seat_token,
+                } => {
+                    online.color = Some(color);
+                    online.opponent = opponent;
+                    online.version = version;
+                    online.seat_token = seat_token;
+                    Some((time_control, variant, start_fen, moves, clock, game_end))
+                }
+                Message::Watching {
+                    white,
+                    black,
+                    time_control,
+                    variant,
+                    start_fen,
+                    moves,
+                    clock,
+                    game_end,
+                    delay_ms,
+                    version,
+                } => {
+                    online.players = [white, black];
+                    online.spectator_delay = Duration::from_millis(delay_ms);
+                    online.version = version;
+                    Some((time_control, variant, start_fen, moves, clock, game_end))
+                }
+                // the player's own moves come back as well , they are already on the board
+                Message::Move { ply, uci } if ply == self.game_state.move_history.len() => {
+                    match uci::move_from_uci(&self.game_state, &uci) {
+                        Some(chess_move) => {
+                            move_result = Some(self.game_state.do_move(&chess_move));
+                            online.sent_ply = ply + 1;
+                        }
+                        None => online.error = Some(format!("the server played {}", uci)),
+                    }
+                    None
+                }
+                Message::Clock(clock) => {
+                    clock.apply(&mut self.clock);
+                    None
+                }
+                Message::GameOver(game_end) => {
+                    move_result = self.game_state.end_game(game_end).or(move_result);
+                    None
+                }
+                Message::Error(error) => {
+                    // a seat refused on reconnecting , e.g. before the server noticed the
+                    // connection was lost , is asked for again after `RECONNECT_INTERVAL`
+                    if !online.synced && online.color.is_some() {
+                        online.transport = None;
+                    }
+                    online.error = Some(error);
+                    None
+                }
+                Message::DrawOffer => {
+                    if let Some(color) = online.color {
+                        self.game_state.offer_draw(color.opponent());
+                    }
+                    None
+                }
+                Message::DrawDecline => {
+                    self.game_state.decline_draw();
+                    None
+                }
+                Message::Chat { from, text } => {
+                    online.chat.push((from, text));
+                    None
+                }
+                Message::Move { .. }
+                | Message::Join { .. }
+                | Message::Resign
+                | Message::Reconnect { .. }
+                | Message::Watch { .. }
+                | Message::ListGames { .. }
+                | Message::GameList(_) => None,
+            };
+            let Some((time_control, variant, start_fen, moves, clock, game_end)) = whole_game
+            else {
+                continue;
+            };
+            online.synced = true;
+            match replay_moves(&start_fen, variant, &moves) {
+                Some(game_state) => self.game_state = game_state,
+                None => online.error = Some("the server sent a broken game".to_string()),
+            }
+            if let Some(time_control) = TimeControl::parse(&time_control) {
+                self.clock = Clock::new(time_control);
+            }
+            clock.apply(&mut self.clock);
+            if let Some(game_end) = game_end {
+                move_result = self.game_state.end_game(game_end).or(move_result);
+            }
+            online.sent_ply = self.game_state.move_history.len();
+        }
+        // the clock only shows the time , the server tells when a flag falls
+        if !self.game_state.is_game_over() {
+            let _ = self.clock.update_time(self.game_state.active_color);
+        }
+        move_result
+    }
+    /// offers a draw in an Online game , or takes the standing offer of the opponent , the
+    /// server ends the game then
+    pub fn offer_draw_online(&mut self) {
+        let Some(online) = &mut self.online else {
+            return;
+        };
+        if let Some(color) = online.color {
+            if self.game_state.draw_offer.is_none() {
+                self.game_state.offer_draw(color);
+            }
+        }
+        let _ = online.send(&Message::DrawOffer);
+    }
+    pub fn decline_draw_online(&mut self) {
+        if let Some(online) = &mut self.online {
+            self.game_state.decline_draw();
+            let _ = online.send(&Message::DrawDecline);
+        }
+    }
+    pub fn resign_online(&mut self) {
+        if let Some(online) = &mut self.online {
+            let _ = online.send(&Message::Resign);
+        }
+    }
+    /// the server passes the text on to both players
+    pub fn send_chat(&mut self, text: &str) {
+        if let Some(online) = &mut self.online {
+            let _ = online.send(&Message::Chat {
+                from: online.user_id.clone(),
+                text: text.to_string(),
+            });
+        }
+    }
+}
+
+/// the game from its start position with the moves done , None if one of them is not legal
+fn replay_moves(start_fen: &str, variant: Variant, moves: &[String]) -> Option<GameState> {
+    let mut game_state = GameState::from_fen_variant(start_fen, variant, false).ok()?;
+    for uci_move in moves {
+        let chess_move = uci::move_from_uci(&game_state, uci_move)?;
+        game_state.do_move(&chess_move);
+    }
+    Some(game_state)
+}
+
+impl GameListing {
+    pub fn request(address: &str, encoding: Encoding) -> io::Result<Self> {
+        let mut transport = transport::connect(address, encoding)?;
+        transport.send(&Message::ListGames {
+            version: PROTOCOL_VERSION,
+        })?;
+        Ok(Self {
+            transport,
+            games: None,
+            error: None,
+        })
+    }
+    /// takes the answer in once it has come , called every frame
+    pub fn update(&mut self) {
+        if self.games.is_some() || self.error.is_some() {
+            return;
+        }
+        match self.transport.try_recv() {
+            Ok(Some(Message::GameList(games))) => self.games = Some(games),
+            Ok(Some(Message::Error(error))) => self.error = Some(error),
+            Ok(_) => {}
+            Err(error) => self.error = Some(error.to_string()),
+        }
+    }
+}
+
+impl OnlineGame {
+    /// `address` is `host:port` or a `ws://` url , see `transport::connect`
+    fn connect(
+        address: &str,
+        game_id: &str,
+        user_id: &str,
+        encoding: Encoding,
+    ) -> io::Result<Self> {
+        Ok(Self {
+            address: address.to_string(),
+            encoding,
+            game_id: game_id.to_string(),
+            user_id: user_id.to_string(),
+            color: None,
+            opponent: None,
+            error: None,
+            version: 1,
+            chat: Vec::new(),
+            spectating: false,
+            players: [None, None],
+            spectator_delay: Duration::ZERO,
+            seat_token: None,
+            transport: Some(transport::connect(address, encoding)?),
+            synced: false,
+            reconnect_at: None,
+            sent_ply: 0,
+        })
+    }
+    /// connected and told the game by the server
+    pub fn is_connected(&self) -> bool {
+        self.transport.is_some() && self.synced
+    }
+    /// errors are kept in `error` too , a lost connection is made again on the next update
+    pub fn send(&mut self, message: &Message) -> io::Result<()> {
+        let result = match &mut self.transport {
+            Some(transport) => transport.send(message),
+            None => Err(io::Error::new(
+                io::ErrorKind::NotConnected,
+                "not connected to the server",
+            )),
+        };
+        if let Err(error) = &result {
+            self.lose_connection(error);
+        }
+        result
+    }
+    /// the next message of the server , if one has come in
+    fn try_recv(&mut self) -> Option<Message> {
+        match self.transport.as_mut()?.try_recv() {
+            Ok(message) => message,
+            Err(error) => {
+                self.lose_connection(&error);
+                None
+            }
+        }
+    }
+    fn lose_connection(&mut self, error: &io::Error) {
+        self.transport = None;
+        self.synced = false;
+        self.error = Some(error.to_string());
+    }
+    /// takes the seat in the game again once the connection was lost , or the app restarted ,
+    /// the server answers with the whole game
+    fn reconnect_if_lost(&mut self) {
+        let now = SystemTime::now();
+        if self.transport.is_some() || self.reconnect_at.is_some_and(|at| now < at) {
+            return;
+        }
+        self.reconnect_at = Some(now + RECONNECT_INTERVAL);
+        match transport::connect(&self.address, self.encoding) {
+            Ok(transport) => {
+                self.transport = Some(transport);
+                self.error = None;
+                let message = if self.spectating {
+                    Message::Watch {
+                        game_id: self.game_id.clone(),
+                        version: PROTOCOL_VERSION,
+                    }
+                } else {
+                    Message::Reconnect {
+                        game_id: self.game_id.clone(),
+                        user_id: self.user_id.clone(),
+                        version: PROTOCOL_VERSION,
+                        seat_token: self.seat_token.clone(),
+                    }
+                };
+                let _ = self.send(&message);
+            }
+            Err(error) => self.error = Some(format!("can not reconnect : {}", error)),
+        }
+    }
 }
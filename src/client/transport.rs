@@ -0,0 +1,315 @@
+//! how an Online game reaches the server , the messages are the same over every transport
+//!
+//! native builds speak either plain TCP (`host:port`) or WebSocket (`ws://host:port/`) , the
+//! browser build only has WebSocket , the server takes both on the same port , every transport
+//! sends binary or JSON messages
+use std::io;
+
+use crate::common::protocol::{Encoding, Message};
+
+pub trait Transport {
+    fn send(&mut self, message: &Message) -> io::Result<()>;
+    /// a message that has come in , if any , an error once the connection is gone
+    fn try_recv(&mut self) -> io::Result<Option<Message>>;
+}
+
+/// `ws://` addresses are WebSocket , others plain TCP except in the browser
+pub fn connect(address: &str, encoding: Encoding) -> io::Result<Box<dyn Transport>> {
+    #[cfg(not(target_arch = "wasm32"))]
+    return native::connect(address, encoding);
+    #[cfg(target_arch = "wasm32")]
+    return browser::connect(address, encoding);
+}
+
+/// host and port plus the path of a `ws://` address
+pub fn parse_ws_address(address: &str) -> Option<(&str, &str)> {
+    let rest = address.trim().strip_prefix("ws://")?;
+    match rest.find('/') {
+        Some(slash) => Some((&rest[..slash], &rest[slash..])),
+        None => Some((rest, "/")),
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+pub use browser::BrowserTransport;
+#[cfg(not(target_arch = "wasm32"))]
+pub use native::SocketTransport;
+
+fn closed() -> io::Error {
+    io::Error::new(
+        io::ErrorKind::ConnectionAborted,
+        "the server closed the connection",
+    )
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+mod native {
+    use std::io::{self, BufReader, Write};
+    use std::net::{Shutdown, TcpStream};
+    use std::sync::{mpsc, Arc, Mutex};
+    use std::time::{SystemTime, UNIX_EPOCH};
+
+    use super::{closed, parse_ws_address, Transport};
+    use crate::common::bitboard::Rng;
+    use crate::common::protocol::{self, Encoding, Framing, Message};
+    use crate::websocket;
+
+    /// a TCP connection , what the server sends is read on a thread of its own , which
+    /// writes the pongs of a WebSocket too
+    pub struct SocketTransport {
+        stream: Arc<Mutex<TcpStream>>,
+        encoding: Encoding,
+        /// masks the frames sent , None for plain TCP
+        websocket_rng: Option<Rng>,
+        messages: mpsc::Receiver<Message>,
+    }
+
+    pub fn connect(address: &str, encoding: Encoding) -> io::Result<Box<dyn Transport>> {
+        let transport = match parse_ws_address(address) {
+            Some((host, path)) => SocketTransport::websocket(host, path, encoding)?,
+            None => SocketTransport::tcp(address.trim(), encoding)?,
+        };
+        Ok(Box::new(transport))
+    }
+
+    impl SocketTransport {
+        /// JSON lines or binary frames
+        pub fn tcp(address: &str, encoding: Encoding) -> io::Result<Self> {
+            let stream = TcpStream::connect(address)?;
+            let reader = BufReader::new(stream.try_clone()?);
+            Ok(Self {
+                stream: Arc::new(Mutex::new(stream)),
+                encoding,
+                websocket_rng: None,
+                messages: read_on_thread(reader, None),
+            })
+        }
+        /// one message per text or binary frame
+        pub fn websocket(host: &str, path: &str, encoding: Encoding) -> io::Result<Self> {
+            let mut stream = TcpStream::connect(host)?;
+            let mut reader = BufReader::new(stream.try_clone()?);
+            let seed = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(1, |time| time.as_nanos() as u64);
+            // the seed must not be 0
+            let mut rng = Rng(seed | 1);
+            let mut key = [0u8; 16];
+            key[..8].copy_from_slice(&rng.next_u64().to_le_bytes());
+            key[8..].copy_from_slice(&rng.next_u64().to_le_bytes());
+            websocket::client_handshake(&mut reader, &mut stream, host, path, key)?;
+            let stream = Arc::new(Mutex::new(stream));
+            // the reading thread masks its pongs with numbers of its own
+            let pong_rng = Rng(rng.next_u64() | 1);
+            Ok(Self {
+                stream: stream.clone(),
+                encoding,
+                websocket_rng: Some(rng),
+                messages: read_on_thread(reader, Some((stream, pong_rng))),
+            })
+        }
+    }
+
+    fn next_mask(rng: &mut Rng) -> [u8; 4] {
+        (rng.next_u64() as u32).to_le_bytes()
+    }
+
+    /// passes the messages the server sends on until the connection ends , the ones too new
+    /// to be known are skipped , a WebSocket answers pings on the stream given
+    fn read_on_thread(
+        mut reader: BufReader<TcpStream>,
+        mut websocket: Option<(Arc<Mutex<TcpStream>>, Rng)>,
+    ) -> mpsc::Receiver<Message> {
+        let (sender, messages) = mpsc::channel();
+        std::thread::spawn(move || {
+            let is_websocket = websocket.is_some();
+            let mut pong = |payload: &[u8]| match &mut websocket {
+                Some((stream, rng)) => {
+                    let mask = next_mask(rng);
+                    websocket::write_pong(&mut *stream.lock().unwrap(), payload, Some(mask))
+                }
+                None => Ok(()),
+            };
+            let mut framing = match is_websocket {
+                true => Framing::WebSocket {
+                    role: websocket::Role::Client,
+                    pong: &mut pong,
+                },
+                false => Framing::Plain,
+            };
+            while let Ok(Some((encoding, bytes))) =
+                protocol::read_message(&mut reader, &mut framing)
+            {
+                if let Some(message) = Message::decode(&bytes, encoding) {
+                    if sender.send(message).is_err() {
+                        break;
+                    }
+                }
+            }
+        });
+        messages
+    }
+
+    impl Transport for SocketTransport {
+        fn send(&mut self, message: &Message) -> io::Result<()> {
+            let mut bytes = message.encode(self.encoding);
+            let mut stream = self.stream.lock().unwrap();
+            if let Some(rng) = &mut self.websocket_rng {
+                let mask = next_mask(rng);
+                return match self.encoding {
+                    Encoding::Json => {
+                        websocket::write_text(&mut *stream, &message.to_json(), Some(mask))
+                    }
+                    Encoding::Binary => websocket::write_binary(&mut *stream, &bytes, Some(mask)),
+                };
+            }
+            if self.encoding == Encoding::Json {
+                bytes.push(b'\n');
+            }
+            stream.write_all(&bytes)?;
+            stream.flush()
+        }
+        fn try_recv(&mut self) -> io::Result<Option<Message>> {
+            match self.messages.try_recv() {
+                Ok(message) => Ok(Some(message)),
+                Err(mpsc::TryRecvError::Empty) => Ok(None),
+                Err(mpsc::TryRecvError::Disconnected) => Err(closed()),
+            }
+        }
+    }
+
+    impl Drop for SocketTransport {
+        fn drop(&mut self) {
+            let Ok(mut stream) = self.stream.lock() else {
+                return;
+            };
+            if let Some(rng) = &mut self.websocket_rng {
+                let _ = websocket::write_close(&mut *stream, Some(next_mask(rng)));
+            }
+            // ends the reading thread too
+            let _ = stream.shutdown(Shutdown::Both);
+        }
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+mod browser {
+    use std::cell::RefCell;
+    use std::collections::VecDeque;
+    use std::io;
+    use std::rc::Rc;
+
+    use js_sys::{ArrayBuffer, Uint8Array};
+    use wasm_bindgen::closure::Closure;
+    use wasm_bindgen::JsCast;
+    use web_sys::{BinaryType, MessageEvent, WebSocket};
+
+    use super::{closed, Transport};
+    use crate::common::protocol::{Encoding, Message};
+
+    #[derive(Default)]
+    struct Shared {
+        incoming: VecDeque<Message>,
+        /// sent once the socket is open
+        outgoing: Vec<Message>,
+        closed: bool,
+    }
+
+    /// the WebSocket of the browser , its events fill the queue between frames
+    pub struct BrowserTransport {
+        socket: WebSocket,
+        encoding: Encoding,
+        shared: Rc<RefCell<Shared>>,
+        // the callbacks live as long as the socket
+        _on_open: Closure<dyn FnMut()>,
+        _on_message: Closure<dyn FnMut(MessageEvent)>,
+        _on_close: Closure<dyn FnMut()>,
+    }
+
+    fn js_error(error: wasm_bindgen::JsValue) -> io::Error {
+        io::Error::new(io::ErrorKind::Other, format!("{:?}", error))
+    }
+
+    fn send_on(socket: &WebSocket, message: &Message, encoding: Encoding) -> io::Result<()> {
+        match encoding {
+            Encoding::Json => socket.send_with_str(&message.to_json()),
+            Encoding::Binary => socket.send_with_u8_array(&message.to_binary()),
+        }
+        .map_err(js_error)
+    }
+
+    pub fn connect(address: &str, encoding: Encoding) -> io::Result<Box<dyn Transport>> {
+        let address = address.trim();
+        let url = if address.starts_with("ws://") || address.starts_with("wss://") {
+            address.to_string()
+        } else {
+            format!("ws://{}/", address)
+        };
+        let socket = WebSocket::new(&url).map_err(js_error)?;
+        // binary frames come in as array buffers instead of blobs
+        socket.set_binary_type(BinaryType::Arraybuffer);
+        let shared = Rc::new(RefCell::new(Shared::default()));
+
+        let open_socket = socket.clone();
+        let open_shared = shared.clone();
+        let on_open = Closure::<dyn FnMut()>::new(move || {
+            for message in open_shared.borrow_mut().outgoing.drain(..) {
+                let _ = send_on(&open_socket, &message, encoding);
+            }
+        });
+        let message_shared = shared.clone();
+        let on_message = Closure::<dyn FnMut(MessageEvent)>::new(move |event: MessageEvent| {
+            let data = event.data();
+            let message = match data.as_string() {
+                Some(text) => Message::from_json(&text),
+                None => data
+                    .dyn_into::<ArrayBuffer>()
+                    .ok()
+                    .and_then(|buffer| Message::from_binary(&Uint8Array::new(&buffer).to_vec())),
+            };
+            if let Some(message) = message {
+                message_shared.borrow_mut().incoming.push_back(message);
+            }
+        });
+        let close_shared = shared.clone();
+        let on_close = Closure::<dyn FnMut()>::new(move || close_shared.borrow_mut().closed = true);
+        socket.set_onopen(Some(on_open.as_ref().unchecked_ref()));
+        socket.set_onmessage(Some(on_message.as_ref().unchecked_ref()));
+        socket.set_onclose(Some(on_close.as_ref().unchecked_ref()));
+        Ok(Box::new(BrowserTransport {
+            socket,
+            encoding,
+            shared,
+            _on_open: on_open,
+            _on_message: on_message,
+            _on_close: on_close,
+        }))
+    }
+
+    impl Transport for BrowserTransport {
+        fn send(&mut self, message: &Message) -> io::Result<()> {
+            match self.socket.ready_state() {
+                WebSocket::CONNECTING => self.shared.borrow_mut().outgoing.push(message.clone()),
+                WebSocket::OPEN => send_on(&self.socket, message, self.encoding)?,
+                _ => return Err(closed()),
+            }
+            Ok(())
+        }
+        fn try_recv(&mut self) -> io::Result<Option<Message>> {
+            let mut shared = self.shared.borrow_mut();
+            match shared.incoming.pop_front() {
+                Some(message) => Ok(Some(message)),
+                None if shared.closed => Err(closed()),
+                None => Ok(None),
+            }
+        }
+    }
+
+    impl Drop for BrowserTransport {
+        fn drop(&mut self) {
+            self.socket.set_onopen(None);
+            self.socket.set_onmessage(None);
+            self.socket.set_onclose(None);
+            let _ = self.socket.close();
+        }
+    }
+}
@@ -0,0 +1,683 @@
+//! a compact binary form of serde types , as small as the type allows and not self describing
+//!
+//! integers are LEB128 varints , signed ones zigzagged first , floats are little endian ,
+//! strings , byte strings , sequences and maps start with their length , options with a 0 or 1
+//! byte and enums with the index of their variant , structs and tuples are their fields in
+//! order without names
+//!
+//! when the input runs out in the middle of a struct the fields left take their serde default ,
+//! so structs written before a field was added at their end can still be read
+use std::fmt;
+
+use serde::de::{self, DeserializeOwned, DeserializeSeed, IntoDeserializer, Visitor};
+use serde::ser::{self, Serialize};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BinaryError(String);
+impl fmt::Display for BinaryError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+impl std::error::Error for BinaryError {}
+impl ser::Error for BinaryError {
+    fn custom<T: fmt::Display>(msg: T) -> Self {
+        BinaryError(msg.to_string())
+    }
+}
+impl de::Error for BinaryError {
+    fn custom<T: fmt::Display>(msg: T) -> Self {
+        BinaryError(msg.to_string())
+    }
+}
+
+pub fn to_bytes<T: Serialize + ?Sized>(value: &T) -> Result<Vec<u8>, BinaryError> {
+    let mut serializer = Serializer { out: Vec::new() };
+    value.serialize(&mut serializer)?;
+    Ok(serializer.out)
+}
+/// bytes after the value are left alone
+pub fn from_bytes<T: DeserializeOwned>(bytes: &[u8]) -> Result<T, BinaryError> {
+    T::deserialize(&mut Deserializer { input: bytes })
+}
+
+pub fn write_varint(out: &mut Vec<u8>, mut n: u64) {
+    while n >= 0x80 {
+        out.push(n as u8 | 0x80);
+        n >>= 7;
+    }
+    out.push(n as u8);
+}
+/// the varint at the start of the bytes and the bytes after it , None for varints beyond a u64
+pub fn read_varint(bytes: &[u8]) -> Option<(u64, &[u8])> {
+    let mut n = 0u64;
+    for (i, byte) in bytes.iter().enumerate().take(10) {
+        // the tenth byte only holds the last bit of a u64
+        if i == 9 && *byte > 1 {
+            return None;
+        }
+        n |= ((byte & 0x7f) as u64) << (7 * i);
+        if byte & 0x80 == 0 {
+            return Some((n, &bytes[i + 1..]));
+        }
+    }
+    None
+}
+
+fn zigzag(n: i64) -> u64 {
+    ((n << 1) ^ (n >> 63)) as u64
+}
+fn unzigzag(n: u64) -> i64 {
+    (n >> 1) as i64 ^ -((n & 1) as i64)
+}
+
+struct Serializer {
+    out: Vec<u8>,
+}
+impl Serializer {
+    fn write_len(&mut self, len: usize) {
+        write_varint(&mut self.out, len as u64);
+    }
+}
+
+impl<'a> ser::Serializer for &'a mut Serializer {
+    type Ok = ();
+    type Error = BinaryError;
+    type SerializeSeq = Self;
+    type SerializeTuple = Self;
+    type SerializeTupleStruct = Self;
+    type SerializeTupleVariant = Self;
+    type SerializeMap = Self;
+    type SerializeStruct = Self;
+    type SerializeStructVariant = Self;
+
+    fn serialize_bool(self, v: bool) -> Result<(), BinaryError> {
+        self.out.push(v as u8);
+        Ok(())
+    }
+    fn serialize_i8(self, v: i8) -> Result<(), BinaryError> {
+        self.serialize_i64(v.into())
+    }
+    fn serialize_i16(self, v: i16) -> Result<(), BinaryError> {
+        self.serialize_i64(v.into())
+    }
+    fn serialize_i32(self, v: i32) -> Result<(), BinaryError> {
+        self.serialize_i64(v.into())
+    }
+    fn serialize_i64(self, v: i64) -> Result<(), BinaryError> {
+        write_varint(&mut self.out, zigzag(v));
+        Ok(())
+    }
+    fn serialize_u8(self, v: u8) -> Result<(), BinaryError> {
+        self.serialize_u64(v.into())
+    }
+    fn serialize_u16(self, v: u16) -> Result<(), BinaryError> {
+        self.serialize_u64(v.into())
+    }
+    fn serialize_u32(self, v: u32) -> Result<(), BinaryError> {
+        self.serialize_u64(v.into())
+    }
+    fn serialize_u64(self, v: u64) -> Result<(), BinaryError> {
+        write_varint(&mut self.out, v);
+        Ok(())
+    }
+    fn serialize_f32(self, v: f32) -> Result<(), BinaryError> {
+        self.out.extend_from_slice(&v.to_le_bytes());
+        Ok(())
+    }
+    fn serialize_f64(self, v: f64) -> Result<(), BinaryError> {
+        self.out.extend_from_slice(&v.to_le_bytes());
+        Ok(())
+    }
+    fn serialize_char(self, v: char) -> Result<(), BinaryError> {
+        self.serialize_u64(v as u64)
+    }
+    fn serialize_str(self, v: &str) -> Result<(), BinaryError> {
+        self.serialize_bytes(v.as_bytes())
+    }
+    fn serialize_bytes(self, v: &[u8]) -> Result<(), BinaryError> {
+        self.write_len(v.len());
+        self.out.extend_from_slice(v);
+        Ok(())
+    }
+    fn serialize_none(self) -> Result<(), BinaryError> {
+        self.out.push(0);
+        Ok(())
+    }
+    fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> Result<(), BinaryError> {
+        self.out.push(1);
+        value.serialize(self)
+    }
+    fn serialize_unit(self) -> Result<(), BinaryError> {
+        Ok(())
+    }
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<(), BinaryError> {
+        Ok(())
+    }
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        index: u32,
+        _variant: &'static str,
+    ) -> Result<(), BinaryError> {
+        self.serialize_u32(index)
+    }
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> Result<(), BinaryError> {
+        value.serialize(self)
+    }
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        index: u32,
+        _variant: &'static str,
+        value: &T,
+    ) -> Result<(), BinaryError> {
+        self.write_len(index as usize);
+        value.serialize(self)
+    }
+    fn serialize_seq(self, len: Option<usize>) -> Result<Self, BinaryError> {
+        let len = len.ok_or_else(|| BinaryError("sequences need a known length".to_string()))?;
+        self.write_len(len);
+        Ok(self)
+    }
+    fn serialize_tuple(self, _len: usize) -> Result<Self, BinaryError> {
+        Ok(self)
+    }
+    fn serialize_tuple_struct(self, _name: &'static str, _len: usize) -> Result<Self, BinaryError> {
+        Ok(self)
+    }
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self, BinaryError> {
+        self.write_len(index as usize);
+        Ok(self)
+    }
+    fn serialize_map(self, len: Option<usize>) -> Result<Self, BinaryError> {
+        let len = len.ok_or_else(|| BinaryError("maps need a known length".to_string()))?;
+        self.write_len(len);
+        Ok(self)
+    }
+    fn serialize_struct(self, _name: &'static str, _len: usize) -> Result<Self, BinaryError> {
+        Ok(self)
+    }
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self, BinaryError> {
+        self.write_len(index as usize);
+        Ok(self)
+    }
+}
+
+impl ser::SerializeSeq for &mut Serializer {
+    type Ok = ();
+    type Error = BinaryError;
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), BinaryError> {
+        value.serialize(&mut **self)
+    }
+    fn end(self) -> Result<(), BinaryError> {
+        Ok(())
+    }
+}
+impl ser::SerializeTuple for &mut Serializer {
+    type Ok = ();
+    type Error = BinaryError;
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), BinaryError> {
+        value.serialize(&mut **self)
+    }
+    fn end(self) -> Result<(), BinaryError> {
+        Ok(())
+    }
+}
+impl ser::SerializeTupleStruct for &mut Serializer {
+    type Ok = ();
+    type Error = BinaryError;
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), BinaryError> {
+        value.serialize(&mut **self)
+    }
+    fn end(self) -> Result<(), BinaryError> {
+        Ok(())
+    }
+}
+impl ser::SerializeTupleVariant for &mut Serializer {
+    type Ok = ();
+    type Error = BinaryError;
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), BinaryError> {
+        value.serialize(&mut **self)
+    }
+    fn end(self) -> Result<(), BinaryError> {
+        Ok(())
+    }
+}
+impl ser::SerializeMap for &mut Serializer {
+    type Ok = ();
+    type Error = BinaryError;
+    fn serialize_key<T: Serialize + ?Sized>(&mut self, key: &T) -> Result<(), BinaryError> {
+        key.serialize(&mut **self)
+    }
+    fn serialize_value<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), BinaryError> {
+        value.serialize(&mut **self)
+    }
+    fn end(self) -> Result<(), BinaryError> {
+        Ok(())
+    }
+}
+impl ser::SerializeStruct for &mut Serializer {
+    type Ok = ();
+    type Error = BinaryError;
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        _key: &'static str,
+        value: &T,
+    ) -> Result<(), BinaryError> {
+        value.serialize(&mut **self)
+    }
+    fn end(self) -> Result<(), BinaryError> {
+        Ok(())
+    }
+}
+impl ser::SerializeStructVariant for &mut Serializer {
+    type Ok = ();
+    type Error = BinaryError;
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        _key: &'static str,
+        value: &T,
+    ) -> Result<(), BinaryError> {
+        value.serialize(&mut **self)
+    }
+    fn end(self) -> Result<(), BinaryError> {
+        Ok(())
+    }
+}
+
+struct Deserializer<'de> {
+    input: &'de [u8],
+}
+
+fn ended() -> BinaryError {
+    BinaryError("unexpected end of input".to_string())
+}
+
+impl<'de> Deserializer<'de> {
+    fn varint(&mut self) -> Result<u64, BinaryError> {
+        let (n, rest) = read_varint(self.input).ok_or_else(ended)?;
+        self.input = rest;
+        Ok(n)
+    }
+    fn byte(&mut self) -> Result<u8, BinaryError> {
+        let (&byte, rest) = self.input.split_first().ok_or_else(ended)?;
+        self.input = rest;
+        Ok(byte)
+    }
+    fn take(&mut self, len: usize) -> Result<&'de [u8], BinaryError> {
+        if self.input.len() < len {
+            return Err(ended());
+        }
+        let (taken, rest) = self.input.split_at(len);
+        self.input = rest;
+        Ok(taken)
+    }
+    fn len(&mut self) -> Result<usize, BinaryError> {
+        let len = self.varint()? as usize;
+        // every item takes at least a byte , longer lengths can only be broken input
+        if len > self.input.len() {
+            return Err(ended());
+        }
+        Ok(len)
+    }
+    fn signed(&mut self) -> Result<i64, BinaryError> {
+        Ok(unzigzag(self.varint()?))
+    }
+}
+
+impl<'de, 'a> de::Deserializer<'de> for &'a mut Deserializer<'de> {
+    type Error = BinaryError;
+
+    fn deserialize_any<V: Visitor<'de>>(self, _visitor: V) -> Result<V::Value, BinaryError> {
+        Err(BinaryError(
+            "the binary form needs to know the type".to_string(),
+        ))
+    }
+    fn deserialize_bool<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, BinaryError> {
+        match self.byte()? {
+            0 => visitor.visit_bool(false),
+            1 => visitor.visit_bool(true),
+            _ => Err(BinaryError("bad bool".to_string())),
+        }
+    }
+    fn deserialize_i8<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, BinaryError> {
+        visitor.visit_i64(self.signed()?)
+    }
+    fn deserialize_i16<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, BinaryError> {
+        visitor.visit_i64(self.signed()?)
+    }
+    fn deserialize_i32<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, BinaryError> {
+        visitor.visit_i64(self.signed()?)
+    }
+    fn deserialize_i64<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, BinaryError> {
+        visitor.visit_i64(self.signed()?)
+    }
+    fn deserialize_u8<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, BinaryError> {
+        visitor.visit_u64(self.varint()?)
+    }
+    fn deserialize_u16<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, BinaryError> {
+        visitor.visit_u64(self.varint()?)
+    }
+    fn deserialize_u32<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, BinaryError> {
+        visitor.visit_u64(self.varint()?)
+    }
+    fn deserialize_u64<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, BinaryError> {
+        visitor.visit_u64(self.varint()?)
+    }
+    fn deserialize_f32<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, BinaryError> {
+        let bytes = self.take(4)?;
+        visitor.visit_f32(f32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    }
+    fn deserialize_f64<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, BinaryError> {
+        let mut bytes = [0u8; 8];
+        bytes.copy_from_slice(self.take(8)?);
+        visitor.visit_f64(f64::from_le_bytes(bytes))
+    }
+    fn deserialize_char<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, BinaryError> {
+        let code = u32::try_from(self.varint()?).ok();
+        let c = code
+            .and_then(char::from_u32)
+            .ok_or_else(|| BinaryError("bad char".to_string()))?;
+        visitor.visit_char(c)
+    }
+    fn deserialize_str<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, BinaryError> {
+        let len = self.len()?;
+        let text = std::str::from_utf8(self.take(len)?)
+            .map_err(|_| BinaryError("string is not utf-8".to_string()))?;
+        visitor.visit_borrowed_str(text)
+    }
+    fn deserialize_string<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, BinaryError> {
+        self.deserialize_str(visitor)
+    }
+    fn deserialize_bytes<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, BinaryError> {
+        let len = self.len()?;
+        visitor.visit_borrowed_bytes(self.take(len)?)
+    }
+    fn deserialize_byte_buf<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, BinaryError> {
+        self.deserialize_bytes(visitor)
+    }
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, BinaryError> {
+        match self.byte()? {
+            0 => visitor.visit_none(),
+            1 => visitor.visit_some(self),
+            _ => Err(BinaryError("bad option".to_string())),
+        }
+    }
+    fn deserialize_unit<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, BinaryError> {
+        visitor.visit_unit()
+    }
+    fn deserialize_unit_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, BinaryError> {
+        visitor.visit_unit()
+    }
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, BinaryError> {
+        visitor.visit_newtype_struct(self)
+    }
+    fn deserialize_seq<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, BinaryError> {
+        let len = self.len()?;
+        visitor.visit_seq(Fields {
+            de: self,
+            left: len,
+            may_end: false,
+        })
+    }
+    fn deserialize_tuple<V: Visitor<'de>>(
+        self,
+        len: usize,
+        visitor: V,
+    ) -> Result<V::Value, BinaryError> {
+        visitor.visit_seq(Fields {
+            de: self,
+            left: len,
+            may_end: false,
+        })
+    }
+    fn deserialize_tuple_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        len: usize,
+        visitor: V,
+    ) -> Result<V::Value, BinaryError> {
+        self.deserialize_tuple(len, visitor)
+    }
+    fn deserialize_map<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, BinaryError> {
+        let len = self.len()?;
+        visitor.visit_map(Fields {
+            de: self,
+            left: len,
+            may_end: false,
+        })
+    }
+    fn deserialize_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, BinaryError> {
+        visitor.visit_seq(Fields {
+            de: self,
+            left: fields.len(),
+            may_end: true,
+        })
+    }
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, BinaryError> {
+        visitor.visit_enum(self)
+    }
+    fn deserialize_identifier<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, BinaryError> {
+        visitor.visit_u64(self.varint()?)
+    }
+    fn deserialize_ignored_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, BinaryError> {
+        self.deserialize_any(visitor)
+    }
+    fn is_human_readable(&self) -> bool {
+        false
+    }
+}
+
+/// the items of a sequence , map , tuple or struct
+struct Fields<'a, 'de> {
+    de: &'a mut Deserializer<'de>,
+    left: usize,
+    /// a struct may end early , its last fields were added after it was written
+    may_end: bool,
+}
+impl<'de> de::SeqAccess<'de> for Fields<'_, 'de> {
+    type Error = BinaryError;
+    fn next_element_seed<T: DeserializeSeed<'de>>(
+        &mut self,
+        seed: T,
+    ) -> Result<Option<T::Value>, BinaryError> {
+        if self.left == 0 || (self.may_end && self.de.input.is_empty()) {
+            return Ok(None);
+        }
+        self.left -= 1;
+        seed.deserialize(&mut *self.de).map(Some)
+    }
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.left)
+    }
+}
+impl<'de> de::MapAccess<'de> for Fields<'_, 'de> {
+    type Error = BinaryError;
+    fn next_key_seed<K: DeserializeSeed<'de>>(
+        &mut self,
+        seed: K,
+    ) -> Result<Option<K::Value>, BinaryError> {
+        if self.left == 0 {
+            return Ok(None);
+        }
+        self.left -= 1;
+        seed.deserialize(&mut *self.de).map(Some)
+    }
+    fn next_value_seed<V: DeserializeSeed<'de>>(
+        &mut self,
+        seed: V,
+    ) -> Result<V::Value, BinaryError> {
+        seed.deserialize(&mut *self.de)
+    }
+}
+impl<'de> de::EnumAccess<'de> for &mut Deserializer<'de> {
+    type Error = BinaryError;
+    type Variant = Self;
+    fn variant_seed<S: DeserializeSeed<'de>>(
+        self,
+        seed: S,
+    ) -> Result<(S::Value, Self), BinaryError> {
+        let index = u32::try_from(self.varint()?)
+            .map_err(|_| BinaryError("bad variant index".to_string()))?;
+        let variant = seed.deserialize(index.into_deserializer())?;
+        Ok((variant, self))
+    }
+}
+impl<'de> de::VariantAccess<'de> for &mut Deserializer<'de> {
+    type Error = BinaryError;
+    fn unit_variant(self) -> Result<(), BinaryError> {
+        Ok(())
+    }
+    fn newtype_variant_seed<S: DeserializeSeed<'de>>(
+        self,
+        seed: S,
+    ) -> Result<S::Value, BinaryError> {
+        seed.deserialize(self)
+    }
+    fn tuple_variant<V: Visitor<'de>>(
+        self,
+        len: usize,
+        visitor: V,
+    ) -> Result<V::Value, BinaryError> {
+        de::Deserializer::deserialize_tuple(self, len, visitor)
+    }
+    fn struct_variant<V: Visitor<'de>>(
+        self,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, BinaryError> {
+        de::Deserializer::deserialize_struct(self, "", fields, visitor)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::logic::{GameEnd, PlayerType, WinReason};
+    use serde::{Deserialize, Serialize};
+    use std::collections::BTreeMap;
+    use std::time::Duration;
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    enum Example {
+        Unit,
+        Newtype(Option<i32>),
+        Tuple(i64, String, char, f64),
+        Struct {
+            names: Vec<String>,
+            times: BTreeMap<String, Duration>,
+            end: GameEnd,
+        },
+    }
+
+    #[test]
+    fn values_round_trip() {
+        let examples = [
+            Example::Unit,
+            Example::Newtype(None),
+            Example::Newtype(Some(-70_000)),
+            Example::Tuple(i64::MIN, "♞ moves".to_string(), 'é', 0.5),
+            Example::Struct {
+                names: vec!["alice".to_string(), String::new()],
+                times: BTreeMap::from([("white".to_string(), Duration::from_millis(1500))]),
+                end: GameEnd::Win {
+                    winner: PlayerType::Black,
+                    reason: WinReason::Resignation,
+                },
+            },
+        ];
+        for example in examples {
+            let bytes = to_bytes(&example).unwrap();
+            assert_eq!(from_bytes::<Example>(&bytes).unwrap(), example);
+        }
+        // the variant index and a small number take a byte each
+        assert_eq!(to_bytes(&Example::Newtype(Some(3))).unwrap(), [1, 1, 6]);
+    }
+
+    #[test]
+    fn varints_take_seven_bits_a_byte() {
+        for n in [0, 1, 127, 128, 300, u32::MAX as u64, u64::MAX] {
+            let mut out = Vec::new();
+            write_varint(&mut out, n);
+            assert_eq!(read_varint(&out), Some((n, &[][..])));
+        }
+        let mut out = Vec::new();
+        write_varint(&mut out, 300);
+        assert_eq!(out, [0xac, 0x02]);
+        for n in [0, -1, 1, i64::MIN, i64::MAX] {
+            assert_eq!(unzigzag(zigzag(n)), n);
+        }
+    }
+
+    #[test]
+    fn broken_input_is_an_error() {
+        let bytes = to_bytes(&Example::Tuple(1, "text".to_string(), 'a', 1.)).unwrap();
+        for len in 0..bytes.len() {
+            assert!(from_bytes::<Example>(&bytes[..len]).is_err());
+        }
+        // a length far beyond the input
+        assert!(from_bytes::<Vec<u8>>(&[0xff, 0xff, 0x03]).is_err());
+    }
+
+    #[test]
+    fn malicious_input_is_an_error() {
+        // lengths as large as a varint goes , for strings , sequences and their items
+        let mut huge = Vec::new();
+        write_varint(&mut huge, u64::MAX);
+        assert!(from_bytes::<String>(&huge).is_err());
+        assert!(from_bytes::<Vec<String>>(&huge).is_err());
+        assert!(from_bytes::<BTreeMap<String, u8>>(&huge).is_err());
+        assert!(from_bytes::<Vec<Vec<u8>>>(&[[1].as_slice(), &huge].concat()).is_err());
+        // varints running on or beyond a u64
+        assert_eq!(read_varint(&[0x80; 11]), None);
+        assert_eq!(
+            read_varint(&[0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x02]),
+            None
+        );
+        assert!(from_bytes::<u64>(&[0x80; 11]).is_err());
+        // variants that do not exist
+        assert!(from_bytes::<Example>(&[4]).is_err());
+        assert!(from_bytes::<Example>(&[0xff, 0xff, 0xff, 0xff, 0x0f]).is_err());
+        assert!(from_bytes::<GameEnd>(&[0, 0, 100]).is_err());
+        // bytes that are no value of the type
+        assert!(from_bytes::<bool>(&[2]).is_err());
+        assert!(from_bytes::<Option<u8>>(&[2, 0]).is_err());
+        assert!(from_bytes::<String>(&[2, 0xc3, 0x28]).is_err());
+        assert!(from_bytes::<char>(&[0x80, 0xb0, 0x03]).is_err());
+    }
+}
@@ -0,0 +1,337 @@
+use std::sync::OnceLock;
+
+use crate::common::logic::{
+    BoardTable, ChessPosition, Piece, PieceType, PlayerType, KING_OFFSETS, KNIGHT_OFFSETS,
+};
+
+/// one bit per square , bit `rank * 8 + file` in board coordinates (file 0 is the h file)
+pub type BitBoard = u64;
+
+const ROOK_DIRECTIONS: [(i32, i32); 4] = [(1, 0), (0, -1), (-1, 0), (0, 1)];
+const BISHOP_DIRECTIONS: [(i32, i32); 4] = [(1, 1), (1, -1), (-1, -1), (-1, 1)];
+
+pub fn square_of(pos: &ChessPosition) -> u32 {
+    (pos.rank * 8 + pos.file) as u32
+}
+
+pub fn pos_of(square: u32) -> ChessPosition {
+    ChessPosition {
+        file: (square % 8) as i32,
+        rank: (square / 8) as i32,
+    }
+}
+
+/// the squares set in the bitboard , lowest first
+pub fn squares(mut bitboard: BitBoard) -> impl Iterator<Item = u32> {
+    std::iter::from_fn(move || {
+        if bitboard == 0 {
+            return None;
+        }
+        let square = bitboard.trailing_zeros();
+        bitboard &= bitboard - 1;
+        Some(square)
+    })
+}
+
+/// where the pieces of every kind and color are , kept next to the square table of the board
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct BitBoards {
+    /// indexed by player then piece type
+    pieces: [[BitBoard; 6]; 2],
+    players: [BitBoard; 2],
+}
+
+impl BitBoards {
+    pub fn from_table(table: &BoardTable) -> Self {
+        let mut bitboards = Self::default();
+        for pos in ChessPosition::iter() {
+            if let Some(piece) = table[pos.rank as usize][pos.file as usize] {
+                bitboards.set(square_of(&pos), piece);
+            }
+        }
+        bitboards
+    }
+    pub fn set(&mut self, square: u32, piece: Piece) {
+        self.pieces[piece.player_kind as usize][piece.piece_kind as usize] |= 1 << square;
+        self.players[piece.player_kind as usize] |= 1 << square;
+    }
+    pub fn remove(&mut self, square: u32, piece: Piece) {
+        self.pieces[piece.player_kind as usize][piece.piece_kind as usize] &= !(1 << square);
+        self.players[piece.player_kind as usize] &= !(1 << square);
+    }
+    pub fn pieces(&self, piece_type: PieceType, player_kind: PlayerType) -> BitBoard {
+        self.pieces[player_kind as usize][piece_type as usize]
+    }
+    pub fn player(&self, player_kind: PlayerType) -> BitBoard {
+        self.players[player_kind as usize]
+    }
+    pub fn occupied(&self) -> BitBoard {
+        self.players[0] | self.players[1]
+    }
+    /// pieces of the given player attacking the square , sliding pieces are stopped by `occupied`
+    /// which does not have to match the board , e.g. to look through a king that is moving away
+    pub fn attackers(&self, square: u32, by_player: PlayerType, occupied: BitBoard) -> BitBoard {
+        let queens = self.pieces(PieceType::Queen, by_player);
+        let rooks = self.pieces(PieceType::Rook, by_player) | queens;
+        let bishops = self.pieces(PieceType::Bishop, by_player) | queens;
+        // a pawn attacks the square if a pawn of the other color on it would attack the pawn
+        (pawn_attacks(by_player.opponent(), square) & self.pieces(PieceType::Pawn, by_player))
+            | (knight_attacks(square) & self.pieces(PieceType::Knight, by_player))
+            | (king_attacks(square) & self.pieces(PieceType::King, by_player))
+            | (rook_attacks(square, occupied) & rooks)
+            | (bishop_attacks(square, occupied) & bishops)
+    }
+}
+
+pub fn knight_attacks(square: u32) -> BitBoard {
+    tables().knight[square as usize]
+}
+
+pub fn king_attacks(square: u32) -> BitBoard {
+    tables().king[square as usize]
+}
+
+/// squares a pawn of the given player attacks from the square
+pub fn pawn_attacks(player_kind: PlayerType, square: u32) -> BitBoard {
+    tables().pawn[player_kind as usize][square as usize]
+}
+
+pub fn rook_attacks(square: u32, occupied: BitBoard) -> BitBoard {
+    let tables = tables();
+    tables.sliding[tables.rook_magics[square as usize].index(occupied)]
+}
+
+pub fn bishop_attacks(square: u32, occupied: BitBoard) -> BitBoard {
+    let tables = tables();
+    tables.sliding[tables.bishop_magics[square as usize].index(occupied)]
+}
+
+pub fn queen_attacks(square: u32, occupied: BitBoard) -> BitBoard {
+    rook_attacks(square, occupied) | bishop_attacks(square, occupied)
+}
+
+/// squares strictly between the two , empty if they are not on the same line
+pub fn between(from: u32, to: u32) -> BitBoard {
+    tables().between[from as usize * 64 + to as usize]
+}
+
+/// maps every occupancy of the squares a slider can be blocked on to its slot in the attack table
+struct Magic {
+    mask: BitBoard,
+    magic: u64,
+    shift: u32,
+    offset: usize,
+}
+
+impl Magic {
+    fn index(&self, occupied: BitBoard) -> usize {
+        self.offset + ((occupied & self.mask).wrapping_mul(self.magic) >> self.shift) as usize
+    }
+}
+
+struct AttackTables {
+    knight: [BitBoard; 64],
+    king: [BitBoard; 64],
+    pawn: [[BitBoard; 64]; 2],
+    rook_magics: Vec<Magic>,
+    bishop_magics: Vec<Magic>,
+    /// attacks of every rook and bishop occupancy , found through the magics
+    sliding: Vec<BitBoard>,
+    between: Vec<BitBoard>,
+}
+
+/// builds the tables now rather than on the first move generated , which takes a while
+pub fn build_tables() {
+    tables();
+}
+
+fn tables() -> &'static AttackTables {
+    static TABLES: OnceLock<AttackTables> = OnceLock::new();
+    TABLES.get_or_init(AttackTables::new)
+}
+
+impl AttackTables {
+    fn new() -> Self {
+        let step_attacks = |square: u32, offsets: &[(i32, i32)]| {
+            offsets
+                .iter()
+                .filter_map(|offset| pos_of(square).add_offset(offset))
+                .fold(0, |attacks, pos| attacks | 1 << square_of(&pos))
+        };
+        let mut tables = Self {
+            knight: [0; 64],
+            king: [0; 64],
+            pawn: [[0; 64]; 2],
+            rook_magics: Vec::with_capacity(64),
+            bishop_magics: Vec::with_capacity(64),
+            sliding: vec![],
+            between: vec![0; 64 * 64],
+        };
+        for square in 0..64 {
+            tables.knight[square as usize] = step_attacks(square, &KNIGHT_OFFSETS);
+            tables.king[square as usize] = step_attacks(square, &KING_OFFSETS);
+            tables.pawn[PlayerType::White as usize][square as usize] =
+                step_attacks(square, &[(-1, 1), (1, 1)]);
+            tables.pawn[PlayerType::Black as usize][square as usize] =
+                step_attacks(square, &[(-1, -1), (1, -1)]);
+            for offset in KING_OFFSETS {
+                let mut ray = 0;
+                let mut pos = pos_of(square);
+                while let Some(next_pos) = pos.add_offset(&offset) {
+                    tables.between[square as usize * 64 + square_of(&next_pos) as usize] = ray;
+                    ray |= 1 << square_of(&next_pos);
+                    pos = next_pos;
+                }
+            }
+        }
+        // the magics are searched for with a fixed seed , so every run builds the same tables
+        let mut rng = Rng(0x9E37_79B9_7F4A_7C15);
+        for square in 0..64 {
+            let magic = find_magic(square, &ROOK_DIRECTIONS, &mut rng, &mut tables.sliding);
+            tables.rook_magics.push(magic);
+        }
+        for square in 0..64 {
+            let magic = find_magic(square, &BISHOP_DIRECTIONS, &mut rng, &mut tables.sliding);
+            tables.bishop_magics.push(magic);
+        }
+        tables
+    }
+}
+
+/// attacks of a slider found by walking the board , only used to fill the tables
+fn slide(square: u32, occupied: BitBoard, directions: &[(i32, i32)]) -> BitBoard {
+    let mut attacks = 0;
+    for offset in directions {
+        let mut pos = pos_of(square);
+        while let Some(next_pos) = pos.add_offset(offset) {
+            attacks |= 1 << square_of(&next_pos);
+            if occupied & 1 << square_of(&next_pos) != 0 {
+                break;
+            }
+            pos = next_pos;
+        }
+    }
+    attacks
+}
+
+/// squares whose occupancy matters for the slider , the last square of every ray never blocks anything
+fn relevant_mask(square: u32, directions: &[(i32, i32)]) -> BitBoard {
+    let mut mask = 0;
+    for offset in directions {
+        let mut pos = pos_of(square);
+        while let Some(next_pos) = pos.add_offset(offset) {
+            if next_pos.add_offset(offset).is_some() {
+                mask |= 1 << square_of(&next_pos);
+            }
+            pos = next_pos;
+        }
+    }
+    mask
+}
+
+/// tries random numbers until one maps every occupancy to a slot without clashing attacks ,
+/// the attacks are appended to `sliding`
+fn find_magic(
+    square: u32,
+    directions: &[(i32, i32)],
+    rng: &mut Rng,
+    sliding: &mut Vec<BitBoard>,
+) -> Magic {
+    let mask = relevant_mask(square, directions);
+    let shift = 64 - mask.count_ones();
+    // every subset of the mask with the attacks for it
+    let mut occupancies = vec![];
+    let mut subset: BitBoard = 0;
+    loop {
+        occupancies.push((subset, slide(square, subset, directions)));
+        subset = subset.wrapping_sub(mask) & mask;
+        if subset == 0 {
+            break;
+        }
+    }
+    let mut table = vec![0; occupancies.len()];
+    // which attempt wrote each slot , saves clearing the table between attempts
+    let mut written = vec![0u32; occupancies.len()];
+    let mut attempt = 0;
+    loop {
+        let magic = rng.sparse();
+        if (mask.wrapping_mul(magic) >> 56).count_ones() < 6 {
+            continue;
+        }
+        attempt += 1;
+        let fits = occupancies.iter().all(|&(occupied, attacks)| {
+            let index = (occupied.wrapping_mul(magic) >> shift) as usize;
+            if written[index] != attempt {
+                written[index] = attempt;
+                table[index] = attacks;
+                true
+            } else {
+                table[index] == attacks
+            }
+        });
+        if fits {
+            let offset = sliding.len();
+            sliding.extend_from_slice(&table);
+            return Magic {
+                mask,
+                magic,
+                shift,
+                offset,
+            };
+        }
+    }
+}
+
+/// xorshift64* , good enough for finding magics and making zobrist keys , the seed must not be 0
+pub struct Rng(pub u64);
+
+impl Rng {
+    pub fn next_u64(&mut self) -> u64 {
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        self.0.wrapping_mul(0x2545_F491_4F6C_DD1D)
+    }
+    /// magics with few bits set are found much faster
+    fn sparse(&mut self) -> u64 {
+        self.next_u64() & self.next_u64() & self.next_u64()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn magic_attacks_match_walking_the_board() {
+        let mut rng = Rng(42);
+        for square in 0..64 {
+            for _ in 0..200 {
+                let occupied = rng.sparse();
+                assert_eq!(
+                    rook_attacks(square, occupied),
+                    slide(square, occupied, &ROOK_DIRECTIONS)
+                );
+                assert_eq!(
+                    bishop_attacks(square, occupied),
+                    slide(square, occupied, &BISHOP_DIRECTIONS)
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn between_is_empty_off_the_line() {
+        let square = |name: &str| {
+            let pos = ChessPosition::iter()
+                .find(|pos| pos.to_string() == name)
+                .unwrap();
+            square_of(&pos)
+        };
+        let expected = (1 << square("b2")) | (1 << square("c3"));
+        assert_eq!(between(square("a1"), square("d4")), expected);
+        assert_eq!(between(square("d4"), square("a1")), expected);
+        assert_eq!(between(square("a1"), square("b3")), 0);
+        assert_eq!(between(square("e1"), square("e2")), 0);
+    }
+}
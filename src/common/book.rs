@@ -0,0 +1,310 @@
+use std::collections::BTreeMap;
+use std::io;
+
+use crate::common::bitboard::Rng;
+use crate::common::logic::{ChessPosition, GameState, PieceType, PlayerType};
+use crate::common::move_history::{ChessMove, ChessMoveKind};
+use crate::common::pgn::PgnGame;
+
+/// size of an entry in a polyglot `.bin` file
+const ENTRY_SIZE: usize = 16;
+
+/// one move of a position as stored in a polyglot book
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BookEntry {
+    pub key: u64,
+    /// to file , to row , from file , from row and promotion piece , 3 bits each
+    pub raw_move: u16,
+    pub weight: u16,
+    pub learn: u32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BookMove {
+    pub chess_move: ChessMove,
+    pub weight: u16,
+}
+
+/// a polyglot opening book , entries sorted by key
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct OpeningBook {
+    entries: Vec<BookEntry>,
+}
+
+impl OpeningBook {
+    /// None if the length is not a whole number of entries
+    pub fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        if bytes.len() % ENTRY_SIZE != 0 {
+            return None;
+        }
+        let mut entries: Vec<BookEntry> = bytes
+            .chunks_exact(ENTRY_SIZE)
+            .map(|chunk| BookEntry {
+                key: u64::from_be_bytes(chunk[0..8].try_into().unwrap()),
+                raw_move: u16::from_be_bytes(chunk[8..10].try_into().unwrap()),
+                weight: u16::from_be_bytes(chunk[10..12].try_into().unwrap()),
+                learn: u32::from_be_bytes(chunk[12..16].try_into().unwrap()),
+            })
+            .collect();
+        // books should be sorted already , lookups rely on it
+        entries.sort_by_key(|entry| entry.key);
+        Some(Self { entries })
+    }
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(self.entries.len() * ENTRY_SIZE);
+        for entry in &self.entries {
+            bytes.extend_from_slice(&entry.key.to_be_bytes());
+            bytes.extend_from_slice(&entry.raw_move.to_be_bytes());
+            bytes.extend_from_slice(&entry.weight.to_be_bytes());
+            bytes.extend_from_slice(&entry.learn.to_be_bytes());
+        }
+        bytes
+    }
+    pub fn open(path: &str) -> io::Result<Self> {
+        Self::from_bytes(&std::fs::read(path)?).ok_or_else(|| {
+            io::Error::new(io::ErrorKind::InvalidData, "not a polyglot opening book")
+        })
+    }
+    pub fn save(&self, path: &str) -> io::Result<()> {
+        std::fs::write(path, self.to_bytes())
+    }
+    pub fn entries(&self) -> &[BookEntry] {
+        &self.entries
+    }
+    /// the book moves of the position that are legal , heaviest first
+    pub fn moves(&self, game_state: &GameState) -> Vec<BookMove> {
+        let key = game_state.position_key();
+        let start = self.entries.partition_point(|entry| entry.key < key);
+        let legal_moves = game_state.generate_legal_moves();
+        let mut book_moves: Vec<BookMove> = self.entries[start..]
+            .iter()
+            .take_while(|entry| entry.key == key)
+            .filter_map(|entry| {
+                let chess_move = *legal_moves
+                    .iter()
+                    .find(|chess_move| encode_move(game_state, chess_move) == entry.raw_move)?;
+                Some(BookMove {
+                    chess_move,
+                    weight: entry.weight,
+                })
+            })
+            .collect();
+        book_moves.sort_by_key(|book_move| std::cmp::Reverse(book_move.weight));
+        book_moves
+    }
+    /// a book move picked at random , heavier moves more often , None out of book
+    pub fn pick_move(&self, game_state: &GameState, rng: &mut Rng) -> Option<ChessMove> {
+        let book_moves = self.moves(game_state);
+        let total: u64 = book_moves.iter().map(|m| m.weight as u64).sum();
+        if total == 0 {
+            return None;
+        }
+        let mut choice = rng.next_u64() % total;
+        for book_move in book_moves {
+            if choice < book_move.weight as u64 {
+                return Some(book_move.chess_move);
+            }
+            choice -= book_move.weight as u64;
+        }
+        None
+    }
+    /// a book of the first `max_plies` plies of the games , weighted like polyglot does with two
+    /// points for a win and one for a draw of the player who moved , unfinished games count as
+    /// draws so repertoire files without results work
+    pub fn from_games(games: &[PgnGame], max_plies: usize) -> Self {
+        let mut weights: BTreeMap<(u64, u16), u64> = BTreeMap::new();
+        for game in games {
+            let Some(mut game_state) = game.start_position() else {
+                continue;
+            };
+            for san in game.moves.iter().take(max_plies) {
+                let Some(chess_move) = game_state.move_from_san(san) else {
+                    break;
+                };
+                let points = match (game.result.as_str(), game_state.active_color) {
+                    ("1-0", PlayerType::White) | ("0-1", PlayerType::Black) => 2,
+                    ("1-0", PlayerType::Black) | ("0-1", PlayerType::White) => 0,
+                    _ => 1,
+                };
+                let weight = weights
+                    .entry((
+                        game_state.position_key(),
+                        encode_move(&game_state, &chess_move),
+                    ))
+                    .or_default();
+                *weight += points;
+                game_state.do_move(&chess_move);
+            }
+        }
+        // weights have to fit in 16 bits , the biggest one sets the scale
+        let max_weight = weights.values().copied().max().unwrap_or(0);
+        let scale = ((max_weight + u16::MAX as u64 - 1) / u16::MAX as u64).max(1);
+        let entries = weights
+            .into_iter()
+            .filter(|(_, weight)| *weight > 0)
+            .map(|((key, raw_move), weight)| BookEntry {
+                key,
+                raw_move,
+                weight: (weight / scale).max(1) as u16,
+                learn: 0,
+            })
+            .collect();
+        Self { entries }
+    }
+}
+
+/// the move in polyglot's encoding , castling is written as the king taking its own rook
+pub fn encode_move(game_state: &GameState, chess_move: &ChessMove) -> u16 {
+    let from = chess_move.move_vector.from_pos;
+    let to = match chess_move.move_kind {
+        ChessMoveKind::Castle(castle_type) => {
+            game_state.castling_rook_square(castle_type, chess_move.moved_piece.player_kind)
+        }
+        _ => chess_move.move_vector.to_pos,
+    };
+    let promotion = match chess_move.move_kind {
+        ChessMoveKind::Promotion(PieceType::Knight) => 1,
+        ChessMoveKind::Promotion(PieceType::Bishop) => 2,
+        ChessMoveKind::Promotion(PieceType::Rook) => 3,
+        ChessMoveKind::Promotion(PieceType::Queen) => 4,
+        _ => 0,
+    };
+    let square = |pos: ChessPosition| (8 * pos.rank + 7 - pos.file) as u16;
+    promotion << 12 | square(from) << 6 | square(to)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::OpeningBook;
+    use crate::common::bitboard::Rng;
+    use crate::common::logic::{GameState, START_FEN};
+    use crate::common::pgn;
+
+    const GAMES: &str = r#"[Result "1-0"]
+
+1. e4 e5 2. Nf3 Nc6 3. Bb5 1-0
+
+[Result "0-1"]
+
+1. e4 c5 2. Nf3 d6 0-1
+
+[Result "1/2-1/2"]
+
+1. d4 d5 2. c4 e6 1/2-1/2
+
+[Result "1-0"]
+
+1. e4 e5 2. Nf3 Nf6 3. Nxe5 1-0
+"#;
+
+    fn book() -> OpeningBook {
+        OpeningBook::from_games(&pgn::parse_pgn(GAMES), 4)
+    }
+
+    fn san_moves(book: &OpeningBook, game_state: &GameState) -> Vec<(String, u16)> {
+        book.moves(game_state)
+            .iter()
+            .map(|m| (game_state.to_san(&m.chess_move), m.weight))
+            .collect()
+    }
+
+    #[test]
+    fn builds_weighted_moves_from_games() {
+        let book = book();
+        let start = GameState::from_fen(START_FEN).unwrap();
+        // e4 won twice and lost once , d4 drew
+        assert_eq!(
+            san_moves(&book, &start),
+            vec![("e4".to_string(), 4), ("d4".to_string(), 1)]
+        );
+        let mut game_state = start.clone();
+        let e4 = game_state.move_from_san("e4").unwrap();
+        game_state.do_move(&e4);
+        assert_eq!(san_moves(&book, &game_state), vec![("c5".to_string(), 2)]);
+        // only the first four plies are kept
+        for san in ["e5", "Nf3", "Nc6"] {
+            let chess_move = game_state.move_from_san(san).unwrap();
+            game_state.do_move(&chess_move);
+        }
+        assert!(book.moves(&game_state).is_empty());
+    }
+
+    #[test]
+    fn round_trips_through_bytes() {
+        let book = book();
+        let bytes = book.to_bytes();
+        assert_eq!(bytes.len() % 16, 0);
+        assert_eq!(OpeningBook::from_bytes(&bytes), Some(book));
+        assert_eq!(OpeningBook::from_bytes(&bytes[1..]), None);
+    }
+
+    /// entries the way polyglot and every other program writes them
+    #[test]
+    fn reads_polyglot_entries() {
+        let entry = |key: u64, raw_move: u16, weight: u16| {
+            let mut bytes = key.to_be_bytes().to_vec();
+            bytes.extend_from_slice(&raw_move.to_be_bytes());
+            bytes.extend_from_slice(&weight.to_be_bytes());
+            bytes.extend_from_slice(&0u32.to_be_bytes());
+            bytes
+        };
+        // e2e4 and d2d4 from the start position , c7c5 after e4
+        let bytes = [
+            entry(0x463b_9618_1691_fc9c, 0x031c, 10),
+            entry(0x463b_9618_1691_fc9c, 0x02db, 5),
+            entry(0x823c_9b50_fd11_4196, 0x0ca2, 3),
+        ]
+        .concat();
+        let book = OpeningBook::from_bytes(&bytes).unwrap();
+        let mut game_state = GameState::from_fen(START_FEN).unwrap();
+        assert_eq!(
+            san_moves(&book, &game_state),
+            vec![("e4".to_string(), 10), ("d4".to_string(), 5)]
+        );
+        let e4 = game_state.move_from_san("e4").unwrap();
+        game_state.do_move(&e4);
+        assert_eq!(san_moves(&book, &game_state), vec![("c5".to_string(), 3)]);
+        // and books written here hold the same entries
+        let written = OpeningBook::from_games(&pgn::parse_pgn("1. e4 c5 *"), 2);
+        assert_eq!(
+            written.entries()[..],
+            [
+                super::BookEntry {
+                    key: 0x463b_9618_1691_fc9c,
+                    raw_move: 0x031c,
+                    weight: 1,
+                    learn: 0
+                },
+                super::BookEntry {
+                    key: 0x823c_9b50_fd11_4196,
+                    raw_move: 0x0ca2,
+                    weight: 1,
+                    learn: 0
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn castling_is_the_king_taking_its_rook() {
+        let game_state = GameState::from_fen("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1").unwrap();
+        let short = game_state.move_from_san("O-O").unwrap();
+        let long = game_state.move_from_san("O-O-O").unwrap();
+        // e1h1 and e1a1
+        assert_eq!(super::encode_move(&game_state, &short), 4 << 6 | 7);
+        assert_eq!(super::encode_move(&game_state, &long), 4 << 6);
+    }
+
+    #[test]
+    fn picks_only_book_moves() {
+        let book = book();
+        let start = GameState::from_fen(START_FEN).unwrap();
+        let mut rng = Rng(7);
+        for _ in 0..20 {
+            let chess_move = book.pick_move(&start, &mut rng).unwrap();
+            assert!(["e4", "d4"].contains(&start.to_san(&chess_move).as_str()));
+        }
+        let out_of_book = GameState::from_fen("4k3/8/8/8/8/8/8/4K3 w - - 0 1").unwrap();
+        assert_eq!(book.pick_move(&out_of_book, &mut rng), None);
+    }
+}
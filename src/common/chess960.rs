@@ -0,0 +1,101 @@
+use crate::common::bitboard::Rng;
+
+/// number of chess960 start positions , 518 is the usual one
+pub const POSITION_COUNT: u32 = 960;
+
+/// placements of the two knights on the five squares left after the bishops and queen
+const KNIGHT_PLACEMENTS: [(usize, usize); 10] = [
+    (0, 1),
+    (0, 2),
+    (0, 3),
+    (0, 4),
+    (1, 2),
+    (1, 3),
+    (1, 4),
+    (2, 3),
+    (2, 4),
+    (3, 4),
+];
+
+/// the back rank from the a file to the h file in Scharnagl's numbering , None past 959
+pub fn back_rank(index: u32) -> Option<[char; 8]> {
+    if index >= POSITION_COUNT {
+        return None;
+    }
+    let mut rank = [' '; 8];
+    let mut n = index as usize;
+    // bishops on a light square (b d f h) and a dark one (a c e g)
+    rank[2 * (n % 4) + 1] = 'B';
+    n /= 4;
+    rank[2 * (n % 4)] = 'B';
+    n /= 4;
+    let place = |rank: &mut [char; 8], nth_empty: usize, piece: char| {
+        let file = (0..8)
+            .filter(|file| rank[*file] == ' ')
+            .nth(nth_empty)
+            .unwrap();
+        rank[file] = piece;
+    };
+    place(&mut rank, n % 6, 'Q');
+    n /= 6;
+    let (first, second) = KNIGHT_PLACEMENTS[n];
+    // the later knight goes in first so the earlier one still counts the same empty squares
+    place(&mut rank, second, 'N');
+    place(&mut rank, first, 'N');
+    // the king always ends up between the rooks
+    for piece in ['R', 'K', 'R'] {
+        place(&mut rank, 0, piece);
+    }
+    Some(rank)
+}
+
+/// fen of the start position , castling rights in X-FEN
+pub fn start_fen(index: u32) -> Option<String> {
+    let white: String = back_rank(index)?.iter().collect();
+    Some(format!(
+        "{}/pppppppp/8/8/8/8/PPPPPPPP/{} w KQkq - 0 1",
+        white.to_ascii_lowercase(),
+        white
+    ))
+}
+
+/// a start position picked at random
+pub fn random_index() -> u32 {
+    let seed = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map_or(1, |time| time.as_nanos() as u64);
+    // the seed must not be 0
+    (Rng(seed | 1).next_u64() % POSITION_COUNT as u64) as u32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{back_rank, start_fen, POSITION_COUNT};
+    use crate::common::logic::{GameState, START_FEN};
+    use std::collections::HashSet;
+
+    #[test]
+    fn numbers_positions_like_scharnagl() {
+        assert_eq!(start_fen(518).unwrap(), START_FEN);
+        let rank = |index| back_rank(index).unwrap().iter().collect::<String>();
+        assert_eq!(rank(0), "BBQNNRKR");
+        assert_eq!(rank(959), "RKRNNQBB");
+        assert_eq!(back_rank(POSITION_COUNT), None);
+    }
+
+    #[test]
+    fn every_position_is_different_and_playable() {
+        let mut seen = HashSet::new();
+        for index in 0..POSITION_COUNT {
+            let rank = back_rank(index).unwrap();
+            assert!(seen.insert(rank), "{} repeats", index);
+            let king = rank.iter().position(|piece| *piece == 'K').unwrap();
+            let rooks: Vec<usize> = (0..8).filter(|file| rank[*file] == 'R').collect();
+            assert!(rooks[0] < king && king < rooks[1]);
+            let bishops: Vec<usize> = (0..8).filter(|file| rank[*file] == 'B').collect();
+            assert_ne!(bishops[0] % 2, bishops[1] % 2);
+            let game_state = GameState::from_fen_chess960(&start_fen(index).unwrap()).unwrap();
+            assert_eq!(game_state.to_fen(), start_fen(index).unwrap());
+        }
+    }
+}
@@ -1,50 +1,309 @@
 use crate::common::game_modes::GameType;
+use crate::common::time_control::{Delay, TimeControl};
 use serde::{Deserialize, Serialize};
-use std::time::{SystemTime, UNIX_EPOCH};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
 use super::logic::PlayerType;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize, Serialize)]
+pub enum ClockState {
+    /// waiting for the first move
+    #[default]
+    NotStarted,
+    Running,
+    /// stopped by the players , when the game ended or while the app was away
+    Paused,
+    /// the player ran out of time
+    Flagged(PlayerType),
+}
+
 #[derive(Deserialize, Serialize)]
 pub struct Clock {
-    pub black_time: u64,
-    pub white_time: u64,
-    last_update: u64,
+    pub time_control: TimeControl,
+    #[serde(default)]
+    pub state: ClockState,
+    pub black_time: Duration,
+    pub white_time: Duration,
+    /// moves each player has finished , the stage of the time control follows from them
+    pub black_moves: u32,
+    pub white_moves: u32,
+    /// time the player to move has spent on the move so far
+    move_time: Duration,
+    /// since the unix epoch
+    last_update: Duration,
+    /// paused by `set_away` , not by the players
+    #[serde(skip)]
+    paused_away: bool,
 }
 impl Default for Clock {
     fn default() -> Self {
-        Self {
-            black_time: 300,
-            white_time: 300,
-            last_update: SystemTime::now()
-                .duration_since(UNIX_EPOCH)
-                .unwrap()
-                .as_secs() as u64,
-        }
+        Self::new(GameType::Blitz.time_control())
     }
 }
 impl Clock {
-    pub fn from_game_type(game_type: GameType) -> Self {
+    pub fn new(time_control: TimeControl) -> Self {
         Self {
-            black_time: game_type.time_control(),
-            white_time: game_type.time_control(),
-            last_update: SystemTime::now()
-                .duration_since(UNIX_EPOCH)
-                .unwrap()
-                .as_secs() as u64,
+            black_time: time_control.initial_time(),
+            white_time: time_control.initial_time(),
+            time_control,
+            state: ClockState::NotStarted,
+            black_moves: 0,
+            white_moves: 0,
+            move_time: Duration::ZERO,
+            last_update: now(),
+            paused_away: false,
+        }
+    }
+    pub fn from_game_type(game_type: GameType) -> Self {
+        Self::new(game_type.time_control())
+    }
+    pub fn time(&self, player: PlayerType) -> Duration {
+        match player {
+            PlayerType::Black => self.black_time,
+            PlayerType::White => self.white_time,
+        }
+    }
+    fn time_mut(&mut self, player: PlayerType) -> &mut Duration {
+        match player {
+            PlayerType::Black => &mut self.black_time,
+            PlayerType::White => &mut self.white_time,
+        }
+    }
+    fn moves_mut(&mut self, player: PlayerType) -> &mut u32 {
+        match player {
+            PlayerType::Black => &mut self.black_moves,
+            PlayerType::White => &mut self.white_moves,
         }
     }
+    /// takes the time since the last update from the player while running , None once their
+    /// time has run out
     pub fn update_time(&mut self, cur_player: PlayerType) -> Option<()> {
-        let now = SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .unwrap()
-            .as_secs() as u64;
-        if now > self.last_update {
-            let elapsed = now - self.last_update;
-            self.last_update = now;
-            match cur_player {
-                PlayerType::Black => self.black_time = self.black_time.checked_sub(elapsed)?,
-                PlayerType::White => self.white_time = self.white_time.checked_sub(elapsed)?,
-            };
+        match self.state {
+            ClockState::Running => {}
+            ClockState::Flagged(_) => return None,
+            ClockState::NotStarted | ClockState::Paused => return Some(()),
+        }
+        let now = now();
+        let elapsed = now.saturating_sub(self.last_update);
+        self.last_update = now;
+        let result = self.spend(cur_player, elapsed);
+        if result.is_none() {
+            self.state = ClockState::Flagged(cur_player);
+        }
+        result
+    }
+    /// ends the player's move , adding the increment and any time the next stage brings , the
+    /// first move of the game starts the clock
+    pub fn switch(&mut self, player: PlayerType) {
+        match self.state {
+            ClockState::NotStarted => {
+                self.finish_move(player);
+                self.resume();
+            }
+            ClockState::Running => {
+                if self.update_time(player).is_some() {
+                    self.finish_move(player);
+                }
+            }
+            ClockState::Paused => self.finish_move(player),
+            ClockState::Flagged(_) => {}
+        }
+    }
+    /// stops the clock , the time already taken stays taken
+    pub fn pause(&mut self) {
+        if self.state == ClockState::Running {
+            self.state = ClockState::Paused;
+        }
+    }
+    /// pauses a running clock while the app is in the background and runs it again once the
+    /// app is back , a clock the players paused stays paused
+    pub fn set_away(&mut self, away: bool) {
+        if away && self.state == ClockState::Running {
+            self.pause();
+            self.paused_away = true;
+        } else if !away && self.paused_away {
+            self.paused_away = false;
+            self.resume();
+        }
+    }
+    /// runs a paused clock again , the time it was paused is not taken
+    pub fn resume(&mut self) {
+        if matches!(self.state, ClockState::NotStarted | ClockState::Paused) {
+            self.state = ClockState::Running;
+            self.last_update = now();
+        }
+    }
+    /// takes over the times of a clock kept elsewhere , e.g. on a game server
+    pub fn set_times(&mut self, white_time: Duration, black_time: Duration, running: bool) {
+        self.white_time = white_time;
+        self.black_time = black_time;
+        self.move_time = Duration::ZERO;
+        self.last_update = now();
+        if running {
+            self.state = ClockState::Running;
+        } else {
+            self.pause();
+        }
+    }
+    fn spend(&mut self, player: PlayerType, elapsed: Duration) -> Option<()> {
+        let moves = *self.moves_mut(player);
+        let delay = match self.time_control.stage(moves).delay {
+            Some(Delay::Simple(delay)) => delay,
+            _ => Duration::ZERO,
         };
+        // a simple delay is used up before the clock starts running
+        let charged =
+            (self.move_time + elapsed).saturating_sub(delay) - self.move_time.saturating_sub(delay);
+        self.move_time += elapsed;
+        let time = self.time_mut(player);
+        // the flag falls at zero , the time does not go below it
+        *time = time.saturating_sub(charged);
+        if time.is_zero() {
+            return None;
+        }
         Some(())
     }
+    fn finish_move(&mut self, player: PlayerType) {
+        if self.time(player).is_zero() {
+            return;
+        }
+        let moves = *self.moves_mut(player);
+        let stage = *self.time_control.stage(moves);
+        let mut bonus = stage.increment;
+        if let Some(Delay::Bronstein(delay)) = stage.delay {
+            bonus += self.move_time.min(delay);
+        }
+        *self.moves_mut(player) += 1;
+        bonus += self
+            .time_control
+            .stage_bonus(moves + 1)
+            .unwrap_or(Duration::ZERO);
+        *self.time_mut(player) += bonus;
+        self.move_time = Duration::ZERO;
+    }
+}
+
+fn now() -> Duration {
+    SystemTime::now().duration_since(UNIX_EPOCH).unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Clock, ClockState};
+    use crate::common::logic::PlayerType;
+    use crate::common::time_control::TimeControl;
+    use std::time::Duration;
+
+    fn clock(time_control: &str) -> Clock {
+        Clock::new(TimeControl::parse(time_control).unwrap())
+    }
+
+    #[test]
+    fn increment_is_added_after_each_move() {
+        let mut clock = clock("180+2");
+        clock.spend(PlayerType::White, Duration::from_millis(3500));
+        clock.finish_move(PlayerType::White);
+        assert_eq!(clock.white_time, Duration::from_millis(178_500));
+        assert_eq!(clock.black_time, Duration::from_secs(180));
+        assert_eq!(clock.white_moves, 1);
+    }
+
+    #[test]
+    fn simple_delay_runs_out_before_the_clock() {
+        let mut clock = clock("60d5");
+        clock.spend(PlayerType::White, Duration::from_secs(3));
+        assert_eq!(clock.white_time, Duration::from_secs(60));
+        clock.spend(PlayerType::White, Duration::from_secs(4));
+        assert_eq!(clock.white_time, Duration::from_secs(58));
+        clock.finish_move(PlayerType::White);
+        clock.spend(PlayerType::Black, Duration::from_secs(1));
+        assert_eq!(clock.black_time, Duration::from_secs(60));
+    }
+
+    #[test]
+    fn bronstein_delay_gives_back_the_time_used() {
+        let mut clock = clock("60b5");
+        clock.spend(PlayerType::White, Duration::from_secs(3));
+        clock.finish_move(PlayerType::White);
+        assert_eq!(clock.white_time, Duration::from_secs(60));
+        clock.spend(PlayerType::Black, Duration::from_secs(8));
+        clock.finish_move(PlayerType::Black);
+        assert_eq!(clock.black_time, Duration::from_secs(57));
+    }
+
+    #[test]
+    fn next_stage_adds_its_time() {
+        let mut clock = clock("2/60:30+10");
+        for _ in 0..2 {
+            clock.spend(PlayerType::White, Duration::from_secs(10));
+            clock.finish_move(PlayerType::White);
+        }
+        // 60 - 20 , then the second stage adds 30 with no increment for the first stage
+        assert_eq!(clock.white_time, Duration::from_secs(70));
+        clock.spend(PlayerType::White, Duration::from_secs(10));
+        clock.finish_move(PlayerType::White);
+        assert_eq!(clock.white_time, Duration::from_secs(70));
+    }
+
+    #[test]
+    fn starts_on_the_first_move_and_pauses() {
+        let mut clock = clock("60+1");
+        assert_eq!(clock.state, ClockState::NotStarted);
+        // time does not run before the first move
+        clock.last_update -= Duration::from_secs(10);
+        clock.update_time(PlayerType::White);
+        assert_eq!(clock.white_time, Duration::from_secs(60));
+        clock.switch(PlayerType::White);
+        assert_eq!(clock.state, ClockState::Running);
+        assert_eq!(clock.white_time, Duration::from_secs(61));
+        clock.pause();
+        clock.last_update -= Duration::from_secs(10);
+        clock.update_time(PlayerType::Black);
+        assert_eq!(clock.black_time, Duration::from_secs(60));
+        clock.resume();
+        clock.last_update -= Duration::from_secs(10);
+        clock.update_time(PlayerType::Black);
+        assert!(clock.black_time <= Duration::from_secs(50));
+    }
+
+    #[test]
+    fn pauses_while_the_app_is_away() {
+        let mut clock = clock("60");
+        clock.set_away(true);
+        assert_eq!(clock.state, ClockState::NotStarted);
+        clock.switch(PlayerType::White);
+        clock.set_away(true);
+        assert_eq!(clock.state, ClockState::Paused);
+        // the time away is not taken from the player to move
+        clock.last_update -= Duration::from_secs(10);
+        clock.update_time(PlayerType::Black);
+        clock.set_away(false);
+        assert_eq!(clock.state, ClockState::Running);
+        clock.update_time(PlayerType::Black);
+        assert!(clock.black_time > Duration::from_secs(59));
+        // a pause of the players outlasts the app being away
+        clock.pause();
+        clock.set_away(true);
+        clock.set_away(false);
+        assert_eq!(clock.state, ClockState::Paused);
+    }
+
+    #[test]
+    fn flag_falls_at_zero() {
+        let mut clock = clock("1");
+        clock.resume();
+        clock.last_update -= Duration::from_secs(2);
+        assert_eq!(clock.update_time(PlayerType::White), None);
+        assert_eq!(clock.state, ClockState::Flagged(PlayerType::White));
+        let mut clock = super::Clock::new(clock.time_control.clone());
+        assert_eq!(
+            clock.spend(PlayerType::Black, Duration::from_millis(900)),
+            Some(())
+        );
+        assert_eq!(
+            clock.spend(PlayerType::Black, Duration::from_millis(200)),
+            None
+        );
+        assert_eq!(clock.black_time, Duration::ZERO);
+    }
 }
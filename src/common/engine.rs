@@ -0,0 +1,584 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+
+use crate::common::bitboard;
+use crate::common::logic::{GameEnd, GameState, PieceType, PlayerType};
+use crate::common::move_history::{ChessMove, ChessMoveKind};
+use crate::common::transposition::{Bound, TranspositionTable};
+use crate::common::variant::Variant;
+
+/// score of being mated on the spot , mates further away score a little less
+pub const MATE_SCORE: i32 = 100_000;
+const INFINITY: i32 = MATE_SCORE + 1;
+/// search limit for `go infinite` and friends , far beyond what a search will reach
+pub const MAX_DEPTH: u32 = 64;
+/// strongest level offered to players , level 1 is the weakest
+pub const MAX_STRENGTH: u8 = 5;
+
+const PIECE_TYPES: [PieceType; 6] = [
+    PieceType::Pawn,
+    PieceType::Knight,
+    PieceType::Bishop,
+    PieceType::Rook,
+    PieceType::Queen,
+    PieceType::King,
+];
+
+/// moves until mate for a score , negative when the player to move gets mated
+pub fn mate_in(score: i32) -> Option<i32> {
+    if score > MATE_SCORE - 1000 {
+        Some((MATE_SCORE - score + 1) / 2)
+    } else if score < -MATE_SCORE + 1000 {
+        Some(-(MATE_SCORE + score) / 2)
+    } else {
+        None
+    }
+}
+
+/// how deep and for how long a search may go
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SearchLimits {
+    pub max_depth: u32,
+    pub time_limit: Duration,
+}
+
+impl SearchLimits {
+    pub fn from_strength(strength: u8) -> Self {
+        let (max_depth, millis) = match strength {
+            0 | 1 => (1, 100),
+            2 => (2, 300),
+            3 => (3, 1000),
+            4 => (5, 2000),
+            _ => (MAX_DEPTH, 5000),
+        };
+        Self {
+            max_depth,
+            time_limit: Duration::from_millis(millis),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SearchResult {
+    pub best_move: Option<ChessMove>,
+    /// in centipawns for the player to move
+    pub score: i32,
+    /// deepest iteration that was searched to the end
+    pub depth: u32,
+    pub nodes: u64,
+}
+
+/// searches the position with iterative deepening until the limits are hit or `stop` is set ,
+/// the best move of the last finished iteration is returned
+///
+/// the table keeps what was found for the next searches , it can be shared between them
+pub fn search(
+    game_state: &GameState,
+    limits: SearchLimits,
+    stop: &AtomicBool,
+    table: &mut TranspositionTable,
+) -> SearchResult {
+    search_with_progress(game_state, limits, stop, table, |_| {})
+}
+
+/// same as search , `report` is called after every finished iteration
+pub fn search_with_progress(
+    game_state: &GameState,
+    limits: SearchLimits,
+    stop: &AtomicBool,
+    table: &mut TranspositionTable,
+    mut report: impl FnMut(&SearchResult),
+) -> SearchResult {
+    table.new_search();
+    // the game so far , repeating one of its positions is a draw
+    let mut keys = game_state.position_keys().to_vec();
+    if keys.last() != Some(&game_state.position_key()) {
+        keys.push(game_state.position_key());
+    }
+    let mut searcher = Searcher {
+        game_state: game_state.scratch_copy(),
+        keys,
+        deadline: Instant::now() + limits.time_limit,
+        stop,
+        table,
+        nodes: 0,
+        aborted: false,
+    };
+    let mut result = SearchResult::default();
+    let mut root_moves = searcher.game_state.generate_legal_moves();
+    searcher.order_moves(&mut root_moves);
+    for depth in 1..=limits.max_depth.max(1) {
+        let mut alpha = -INFINITY;
+        let mut best = None;
+        for chess_move in &root_moves {
+            searcher.make_move(chess_move);
+            let score = -searcher.alpha_beta(depth - 1, 1, -INFINITY, -alpha);
+            searcher.unmake_move();
+            if searcher.aborted {
+                break;
+            }
+            if score > alpha {
+                alpha = score;
+                best = Some(*chess_move);
+            }
+        }
+        // the previous best move is searched first , so a cut short iteration can still improve on it
+        if let Some(best_move) = best {
+            result.best_move = Some(best_move);
+            result.score = alpha;
+            result.nodes = searcher.nodes;
+            if !searcher.aborted {
+                result.depth = depth;
+                report(&result);
+            }
+            if let Some(index) = root_moves.iter().position(|m| *m == best_move) {
+                root_moves[..=index].rotate_right(1);
+            }
+        }
+        if searcher.aborted || mate_in(alpha).is_some() {
+            break;
+        }
+    }
+    if result.best_move.is_none() {
+        result.best_move = root_moves.first().copied();
+    }
+    result.nodes = searcher.nodes;
+    result
+}
+
+struct Searcher<'a> {
+    game_state: GameState,
+    /// position keys of the game and of the moves searched , the current position last
+    keys: Vec<u64>,
+    deadline: Instant,
+    stop: &'a AtomicBool,
+    table: &'a mut TranspositionTable,
+    nodes: u64,
+    aborted: bool,
+}
+
+impl Searcher<'_> {
+    fn alpha_beta(&mut self, depth: u32, ply: i32, mut alpha: i32, beta: i32) -> i32 {
+        if let Some(game_end) = self.game_state.variant().game_end(&self.game_state) {
+            return self.game_end_score(game_end, ply);
+        }
+        // going back to a position is as good as a draw , the other side can do it again
+        if self.is_repetition() {
+            return 0;
+        }
+        if depth == 0 {
+            return self.quiescence(alpha, beta, ply);
+        }
+        if self.should_stop() {
+            return 0;
+        }
+        let key = self.game_state.position_key();
+        let mut table_move = None;
+        if let Some(entry) = self.table.probe(key) {
+            table_move = entry.best_move;
+            if entry.depth >= depth {
+                let score = score_from_table(entry.score, ply);
+                match entry.bound {
+                    Bound::Exact => return score,
+                    Bound::Lower if score >= beta => return beta,
+                    Bound::Upper if score <= alpha => return alpha,
+                    _ => {}
+                }
+            }
+        }
+        let mut moves = self.game_state.generate_legal_moves();
+        if moves.is_empty() {
+            let game_end = self.game_state.variant().no_moves_end(&self.game_state);
+            return self.game_end_score(game_end, ply);
+        }
+        self.order_moves(&mut moves);
+        // the best move found before is most likely best again
+        if let Some(index) = table_move.and_then(|best| moves.iter().position(|m| *m == best)) {
+            moves[..=index].rotate_right(1);
+        }
+        let mut best_move = None;
+        for chess_move in &moves {
+            self.make_move(chess_move);
+            let score = -self.alpha_beta(depth - 1, ply + 1, -beta, -alpha);
+            self.unmake_move();
+            if self.aborted {
+                return 0;
+            }
+            if score >= beta {
+                self.table.store(
+                    key,
+                    depth,
+                    score_to_table(beta, ply),
+                    Bound::Lower,
+                    Some(*chess_move),
+                );
+                return beta;
+            }
+            if score > alpha {
+                alpha = score;
+                best_move = Some(*chess_move);
+            }
+        }
+        let bound = if best_move.is_some() {
+            Bound::Exact
+        } else {
+            Bound::Upper
+        };
+        self.table
+            .store(key, depth, score_to_table(alpha, ply), bound, best_move);
+        alpha
+    }
+    /// only captures and promotions are searched , so the position is quiet when it is evaluated
+    fn quiescence(&mut self, mut alpha: i32, beta: i32, ply: i32) -> i32 {
+        if self.should_stop() {
+            return 0;
+        }
+        if let Some(game_end) = self.game_state.variant().game_end(&self.game_state) {
+            return self.game_end_score(game_end, ply);
+        }
+        let stand_pat = evaluate(&self.game_state);
+        if stand_pat >= beta {
+            return beta;
+        }
+        alpha = alpha.max(stand_pat);
+        let mut moves: Vec<ChessMove> = self
+            .game_state
+            .generate_legal_moves()
+            .into_iter()
+            .filter(|chess_move| self.is_tactical(chess_move))
+            .collect();
+        self.order_moves(&mut moves);
+        for chess_move in &moves {
+            self.game_state.apply_move(chess_move);
+            let score = -self.quiescence(-beta, -alpha, ply + 1);
+            self.game_state.unapply_move();
+            if self.aborted {
+                return 0;
+            }
+            if score >= beta {
+                return beta;
+            }
+            alpha = alpha.max(score);
+        }
+        alpha
+    }
+    /// quiescence only plays captures and promotions , which never repeat a position , so it
+    /// does not keep the keys
+    fn make_move(&mut self, chess_move: &ChessMove) {
+        self.game_state.apply_move(chess_move);
+        self.keys.push(self.game_state.position_key());
+    }
+    fn unmake_move(&mut self) {
+        self.game_state.unapply_move();
+        self.keys.pop();
+    }
+    /// whether the position came up before with the same player to move , since the last
+    /// capture or pawn move
+    fn is_repetition(&self) -> bool {
+        let Some(key) = self.keys.last() else {
+            return false;
+        };
+        self.keys
+            .iter()
+            .rev()
+            .take(self.game_state.half_move_clock as usize + 1)
+            .skip(2)
+            .step_by(2)
+            .any(|earlier| earlier == key)
+    }
+    /// mate scores for a game the player to move has won or lost , nearer ends score more
+    fn game_end_score(&self, game_end: GameEnd, ply: i32) -> i32 {
+        match game_end.winner() {
+            Some(winner) if winner == self.game_state.active_color => MATE_SCORE - ply,
+            Some(_) => -MATE_SCORE + ply,
+            None => 0,
+        }
+    }
+    /// counts the node and looks at the clock and the stop flag every so often
+    fn should_stop(&mut self) -> bool {
+        self.nodes += 1;
+        if self.nodes % 1024 == 0
+            && (Instant::now() >= self.deadline || self.stop.load(Ordering::Relaxed))
+        {
+            self.aborted = true;
+        }
+        self.aborted
+    }
+    fn captured_piece(&self, chess_move: &ChessMove) -> Option<PieceType> {
+        match chess_move.move_kind {
+            ChessMoveKind::Take(piece_type) => Some(piece_type),
+            ChessMoveKind::EnPassant(_) => Some(PieceType::Pawn),
+            ChessMoveKind::Promotion(_) => self
+                .game_state
+                .board
+                .get_piece_at_pos(&chess_move.move_vector.to_pos)
+                .map(|piece| piece.piece_kind),
+            ChessMoveKind::Normal | ChessMoveKind::Castle(_) => None,
+        }
+    }
+    fn is_tactical(&self, chess_move: &ChessMove) -> bool {
+        matches!(chess_move.move_kind, ChessMoveKind::Promotion(_))
+            || self.captured_piece(chess_move).is_some()
+    }
+    /// most valuable victim by least valuable attacker first , quiet moves last
+    fn order_moves(&self, moves: &mut [ChessMove]) {
+        moves.sort_by_cached_key(|chess_move| {
+            let mut score = match self.captured_piece(chess_move) {
+                Some(piece_type) => {
+                    10 * piece_value(piece_type) - piece_value(chess_move.moved_piece.piece_kind)
+                }
+                None => 0,
+            };
+            if let ChessMoveKind::Promotion(piece_type) = chess_move.move_kind {
+                score += piece_value(piece_type);
+            }
+            -score
+        });
+    }
+}
+
+/// mate scores count from the root , the table keeps them counted from the position
+fn score_to_table(score: i32, ply: i32) -> i32 {
+    match mate_in(score) {
+        Some(moves) if moves > 0 => score + ply,
+        Some(_) => score - ply,
+        None => score,
+    }
+}
+
+fn score_from_table(score: i32, ply: i32) -> i32 {
+    match mate_in(score) {
+        Some(moves) if moves > 0 => score - ply,
+        Some(_) => score + ply,
+        None => score,
+    }
+}
+
+fn piece_value(piece_type: PieceType) -> i32 {
+    match piece_type {
+        PieceType::Pawn => 100,
+        PieceType::Knight => 320,
+        PieceType::Bishop => 330,
+        PieceType::Rook => 500,
+        PieceType::Queen => 900,
+        // kings are never taken , mates are scored by the search
+        PieceType::King => 0,
+    }
+}
+
+/// material and piece square score in centipawns for the player to move
+pub fn evaluate(game_state: &GameState) -> i32 {
+    let bitboards = game_state.board.bitboards();
+    // the king should come out once the queens are gone
+    let endgame = bitboards.pieces(PieceType::Queen, PlayerType::White)
+        | bitboards.pieces(PieceType::Queen, PlayerType::Black)
+        == 0;
+    let mut score = 0;
+    for player_kind in [PlayerType::White, PlayerType::Black] {
+        for piece_type in PIECE_TYPES {
+            let table = piece_square_table(piece_type, endgame);
+            for square in bitboard::squares(bitboards.pieces(piece_type, player_kind)) {
+                let pos = bitboard::pos_of(square);
+                // the tables are written from white's side with a8 first
+                let row = match player_kind {
+                    PlayerType::White => 7 - pos.rank,
+                    PlayerType::Black => pos.rank,
+                };
+                let value = piece_value(piece_type) + table[(row * 8 + 7 - pos.file) as usize];
+                match player_kind {
+                    PlayerType::White => score += value,
+                    PlayerType::Black => score -= value,
+                }
+            }
+        }
+    }
+    // in antichess the pieces are a burden
+    if game_state.variant() == Variant::Antichess {
+        score = -score;
+    }
+    match game_state.active_color {
+        PlayerType::White => score,
+        PlayerType::Black => -score,
+    }
+}
+
+#[rustfmt::skip]
+const PAWN_TABLE: [i32; 64] = [
+     0,  0,  0,  0,  0,  0,  0,  0,
+    50, 50, 50, 50, 50, 50, 50, 50,
+    10, 10, 20, 30, 30, 20, 10, 10,
+     5,  5, 10, 25, 25, 10,  5,  5,
+     0,  0,  0, 20, 20,  0,  0,  0,
+     5, -5,-10,  0,  0,-10, -5,  5,
+     5, 10, 10,-20,-20, 10, 10,  5,
+     0,  0,  0,  0,  0,  0,  0,  0,
+];
+#[rustfmt::skip]
+const KNIGHT_TABLE: [i32; 64] = [
+    -50,-40,-30,-30,-30,-30,-40,-50,
+    -40,-20,  0,  0,  0,  0,-20,-40,
+    -30,  0, 10, 15, 15, 10,  0,-30,
+    -30,  5, 15, 20, 20, 15,  5,-30,
+    -30,  0, 15, 20, 20, 15,  0,-30,
+    -30,  5, 10, 15, 15, 10,  5,-30,
+    -40,-20,  0,  5,  5,  0,-20,-40,
+    -50,-40,-30,-30,-30,-30,-40,-50,
+];
+#[rustfmt::skip]
+const BISHOP_TABLE: [i32; 64] = [
+    -20,-10,-10,-10,-10,-10,-10,-20,
+    -10,  0,  0,  0,  0,  0,  0,-10,
+    -10,  0,  5, 10, 10,  5,  0,-10,
+    -10,  5,  5, 10, 10,  5,  5,-10,
+    -10,  0, 10, 10, 10, 10,  0,-10,
+    -10, 10, 10, 10, 10, 10, 10,-10,
+    -10,  5,  0,  0,  0,  0,  5,-10,
+    -20,-10,-10,-10,-10,-10,-10,-20,
+];
+#[rustfmt::skip]
+const ROOK_TABLE: [i32; 64] = [
+     0,  0,  0,  0,  0,  0,  0,  0,
+     5, 10, 10, 10, 10, 10, 10,  5,
+    -5,  0,  0,  0,  0,  0,  0, -5,
+    -5,  0,  0,  0,  0,  0,  0, -5,
+    -5,  0,  0,  0,  0,  0,  0, -5,
+    -5,  0,  0,  0,  0,  0,  0, -5,
+    -5,  0,  0,  0,  0,  0,  0, -5,
+     0,  0,  0,  5,  5,  0,  0,  0,
+];
+#[rustfmt::skip]
+const QUEEN_TABLE: [i32; 64] = [
+    -20,-10,-10, -5, -5,-10,-10,-20,
+    -10,  0,  0,  0,  0,  0,  0,-10,
+    -10,  0,  5,  5,  5,  5,  0,-10,
+     -5,  0,  5,  5,  5,  5,  0, -5,
+      0,  0,  5,  5,  5,  5,  0, -5,
+    -10,  5,  5,  5,  5,  5,  0,-10,
+    -10,  0,  5,  0,  0,  0,  0,-10,
+    -20,-10,-10, -5, -5,-10,-10,-20,
+];
+#[rustfmt::skip]
+const KING_MIDDLE_GAME_TABLE: [i32; 64] = [
+    -30,-40,-40,-50,-50,-40,-40,-30,
+    -30,-40,-40,-50,-50,-40,-40,-30,
+    -30,-40,-40,-50,-50,-40,-40,-30,
+    -30,-40,-40,-50,-50,-40,-40,-30,
+    -20,-30,-30,-40,-40,-30,-30,-20,
+    -10,-20,-20,-20,-20,-20,-20,-10,
+     20, 20,  0,  0,  0,  0, 20, 20,
+     20, 30, 10,  0,  0, 10, 30, 20,
+];
+#[rustfmt::skip]
+const KING_END_GAME_TABLE: [i32; 64] = [
+    -50,-40,-30,-20,-20,-30,-40,-50,
+    -30,-20,-10,  0,  0,-10,-20,-30,
+    -30,-10, 20, 30, 30, 20,-10,-30,
+    -30,-10, 30, 40, 40, 30,-10,-30,
+    -30,-10, 30, 40, 40, 30,-10,-30,
+    -30,-10, 20, 30, 30, 20,-10,-30,
+    -30,-30,  0,  0,  0,  0,-30,-30,
+    -50,-30,-30,-30,-30,-30,-30,-50,
+];
+
+fn piece_square_table(piece_type: PieceType, endgame: bool) -> &'static [i32; 64] {
+    match piece_type {
+        PieceType::Pawn => &PAWN_TABLE,
+        PieceType::Knight => &KNIGHT_TABLE,
+        PieceType::Bishop => &BISHOP_TABLE,
+        PieceType::Rook => &ROOK_TABLE,
+        PieceType::Queen => &QUEEN_TABLE,
+        PieceType::King if endgame => &KING_END_GAME_TABLE,
+        PieceType::King => &KING_MIDDLE_GAME_TABLE,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn best_move(fen: &str, max_depth: u32) -> String {
+        let game_state = GameState::from_fen(fen).unwrap();
+        let limits = SearchLimits {
+            max_depth,
+            time_limit: Duration::from_secs(60),
+        };
+        let mut table = TranspositionTable::new(1);
+        let chess_move = search(&game_state, limits, &AtomicBool::new(false), &mut table)
+            .best_move
+            .unwrap();
+        game_state.to_san(&chess_move)
+    }
+
+    #[test]
+    fn finds_mate_in_one() {
+        assert_eq!(best_move("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1", 2), "Ra8#");
+    }
+
+    #[test]
+    fn takes_a_hanging_queen() {
+        assert_eq!(
+            best_move(
+                "rnb1kbnr/pppp1ppp/8/4p1q1/4P3/3P4/PPP2PPP/RNBQKBNR w KQkq - 1 3",
+                3
+            ),
+            "Bxg5"
+        );
+    }
+
+    #[test]
+    fn repeats_a_position_when_behind() {
+        let mut game_state = GameState::from_fen("1n2k3/8/8/8/8/8/8/RQ2K3 b - - 0 1").unwrap();
+        for san in ["Nc6", "Kd1", "Nb8", "Ke1"] {
+            let chess_move = game_state.move_from_san(san).unwrap();
+            game_state.do_move(&chess_move);
+        }
+        let limits = SearchLimits {
+            max_depth: 3,
+            time_limit: Duration::from_secs(60),
+        };
+        let mut table = TranspositionTable::new(1);
+        let result = search(&game_state, limits, &AtomicBool::new(false), &mut table);
+        // Nc6 brings back the position after the first move
+        assert_eq!(game_state.to_san(&result.best_move.unwrap()), "Nc6");
+        assert_eq!(result.score, 0);
+    }
+
+    #[test]
+    fn avoids_repeating_a_position_when_ahead() {
+        let mut game_state = GameState::from_fen("1n2k3/8/8/8/8/8/8/RQ2K3 w - - 0 1").unwrap();
+        for san in ["Kd1", "Nc6", "Ke1", "Nb8"] {
+            let chess_move = game_state.move_from_san(san).unwrap();
+            game_state.do_move(&chess_move);
+        }
+        let limits = SearchLimits {
+            max_depth: 3,
+            time_limit: Duration::from_secs(60),
+        };
+        let mut table = TranspositionTable::new(1);
+        let result = search(&game_state, limits, &AtomicBool::new(false), &mut table);
+        assert_ne!(game_state.to_san(&result.best_move.unwrap()), "Kd1");
+        assert!(result.score > 500);
+    }
+
+    #[test]
+    fn start_position_is_even() {
+        assert_eq!(evaluate(&GameState::default()), 0);
+    }
+
+    #[test]
+    fn stops_when_asked() {
+        let limits = SearchLimits {
+            max_depth: 64,
+            time_limit: Duration::from_secs(60),
+        };
+        let mut table = TranspositionTable::new(1);
+        let result = search(
+            &GameState::default(),
+            limits,
+            &AtomicBool::new(true),
+            &mut table,
+        );
+        assert!(result.best_move.is_some());
+        assert!(result.depth < 64);
+    }
+}
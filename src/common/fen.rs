@@ -1,54 +1,254 @@
-use crate::common::logic::{Piece, PieceType, PlayerType};
-
-fn verify_fen_board(fen: &str) -> bool {
-    let (mut file, mut rank) = (0, 7);
-    for i in fen.chars() {
-        if i == '/' {
-            file = 0;
-            rank -= 1;
-            if rank < 0 {
-                // println!("f1");
-
-                return false;
+use crate::common::logic::{ChessPosition, Piece, PieceType, PlayerType};
+
+/// why a fen string could not be read
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FenError {
+    /// a fen needs 6 fields , or 4 when the clocks are left out
+    BadFieldCount(usize),
+    BadRankCount(usize),
+    /// the rank (1 to 8) does not have exactly 8 squares
+    BadRankLength(usize),
+    IllegalPieceChar(char),
+    MissingKing(PlayerType),
+    TooManyKings(PlayerType),
+    PawnOnBackRank,
+    BadActiveColor(String),
+    BadCastlingRights(String),
+    BadEnPassantSquare(String),
+    BadHalfMoveClock(String),
+    BadFullMoveNumber(String),
+    /// three-check positions give the checks each player has left , like `3+3`
+    BadCheckCount(String),
+}
+impl std::fmt::Display for FenError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::BadFieldCount(count) => write!(f, "expected 6 fields but found {}", count),
+            Self::BadRankCount(count) => write!(f, "expected 8 ranks but found {}", count),
+            Self::BadRankLength(rank) => write!(f, "rank {} does not have 8 squares", rank),
+            Self::IllegalPieceChar(ch) => write!(f, "'{}' is not a piece", ch),
+            Self::MissingKing(player) => write!(f, "{:?} has no king", player),
+            Self::TooManyKings(player) => write!(f, "{:?} has more than one king", player),
+            Self::PawnOnBackRank => write!(f, "pawns can not be on the first or last rank"),
+            Self::BadActiveColor(field) => write!(f, "'{}' is not w or b", field),
+            Self::BadCastlingRights(field) => {
+                write!(f, "'{}' are not possible castling rights", field)
             }
-            continue;
+            Self::BadEnPassantSquare(field) => {
+                write!(f, "'{}' is not a possible en passant square", field)
+            }
+            Self::BadHalfMoveClock(field) => write!(f, "'{}' is not a halfmove clock", field),
+            Self::BadFullMoveNumber(field) => write!(f, "'{}' is not a move number", field),
+            Self::BadCheckCount(field) => write!(f, "'{}' are not checks left", field),
         }
-        if i.is_numeric() {
-            file += i.to_digit(10).unwrap_or(0);
-        } else if i.is_ascii_alphabetic() {
-            file += 1;
-        } else {
-            // println!("f2");
-            return false;
+    }
+}
+impl std::error::Error for FenError {}
+
+/// `require_kings` asks for exactly one king of each player
+fn parse_fen_board(
+    fen_board: &str,
+    require_kings: bool,
+) -> Result<[[Option<Piece>; 8]; 8], FenError> {
+    let mut table: [[Option<Piece>; 8]; 8] = [[None; 8]; 8];
+    let ranks: Vec<&str> = fen_board.split('/').collect();
+    if ranks.len() != 8 {
+        return Err(FenError::BadRankCount(ranks.len()));
+    }
+    for (i, fen_rank) in ranks.iter().enumerate() {
+        let rank = 7 - i;
+        let mut file = 0;
+        for ch in fen_rank.chars() {
+            match ch {
+                '1'..='8' => file += ch.to_digit(10).unwrap() as usize,
+                _ => {
+                    let player_type = if ch.is_uppercase() {
+                        PlayerType::White
+                    } else {
+                        PlayerType::Black
+                    };
+                    let piece_type = match ch.to_ascii_lowercase() {
+                        'k' => PieceType::King,
+                        'p' => PieceType::Pawn,
+                        'n' => PieceType::Knight,
+                        'b' => PieceType::Bishop,
+                        'r' => PieceType::Rook,
+                        'q' => PieceType::Queen,
+                        _ => return Err(FenError::IllegalPieceChar(ch)),
+                    };
+                    if file < 8 {
+                        table[rank][7 - file] = Some(Piece::new(piece_type, player_type));
+                    }
+                    file += 1;
+                }
+            }
+            if file > 8 {
+                return Err(FenError::BadRankLength(rank + 1));
+            }
         }
-        if file > 8 {
-            return false;
+        if file != 8 {
+            return Err(FenError::BadRankLength(rank + 1));
+        }
+    }
+    for player_type in [PlayerType::White, PlayerType::Black] {
+        if !require_kings {
+            break;
+        }
+        let kings = table
+            .iter()
+            .flatten()
+            .filter(|piece| **piece == Some(Piece::new(PieceType::King, player_type)))
+            .count();
+        match kings {
+            0 => return Err(FenError::MissingKing(player_type)),
+            1 => {}
+            _ => return Err(FenError::TooManyKings(player_type)),
+        }
+    }
+    if table[0]
+        .iter()
+        .chain(table[7].iter())
+        .any(|piece| piece.is_some_and(|piece| piece.piece_kind == PieceType::Pawn))
+    {
+        return Err(FenError::PawnOnBackRank);
+    }
+    Ok(table)
+}
+
+/// files the rooks castle from when nothing else is said , in castling_availability order
+pub const STANDARD_ROOK_FILES: [usize; 4] = [0, 7, 0, 7];
+
+/// the outermost rook of the player on the short (h file) or long (a file) side of the king
+fn outermost_rook(
+    table: &[[Option<Piece>; 8]; 8],
+    player_type: PlayerType,
+    short: bool,
+) -> Option<usize> {
+    let rank = back_rank(player_type);
+    let king_file =
+        (0..8).find(|file| table[rank][*file] == Some(Piece::new(PieceType::King, player_type)))?;
+    let is_rook =
+        |file: &usize| table[rank][*file] == Some(Piece::new(PieceType::Rook, player_type));
+    if short {
+        (0..king_file).find(is_rook)
+    } else {
+        (king_file + 1..8).rev().find(is_rook)
+    }
+}
+
+fn back_rank(player_type: PlayerType) -> usize {
+    match player_type {
+        PlayerType::White => 0,
+        PlayerType::Black => 7,
+    }
+}
+
+/// castling rights and the files of their rooks , read as X-FEN or Shredder-FEN
+///
+/// `KQkq` stand for the outermost rook on each side , a file letter like `HAha` names the rook ,
+/// the king and that rook have to be on the back rank
+fn parse_castling_rights(
+    field: &str,
+    table: &[[Option<Piece>; 8]; 8],
+) -> Result<([bool; 4], [usize; 4]), FenError> {
+    let mut rights = [false; 4];
+    let mut rook_files = STANDARD_ROOK_FILES;
+    if field == "-" {
+        return Ok((rights, rook_files));
+    }
+    let bad_rights = || FenError::BadCastlingRights(field.to_string());
+    for ch in field.chars() {
+        let player_type = if ch.is_ascii_uppercase() {
+            PlayerType::White
+        } else {
+            PlayerType::Black
+        };
+        let rank = back_rank(player_type);
+        let king_file = (0..8)
+            .find(|file| table[rank][*file] == Some(Piece::new(PieceType::King, player_type)))
+            .ok_or_else(bad_rights)?;
+        let (short, rook_file) = match ch.to_ascii_lowercase() {
+            'k' => (true, outermost_rook(table, player_type, true)),
+            'q' => (false, outermost_rook(table, player_type, false)),
+            file_char @ 'a'..='h' => {
+                let file = 7 - (file_char as u8 - b'a') as usize;
+                let is_rook = table[rank][file] == Some(Piece::new(PieceType::Rook, player_type));
+                (file < king_file, Some(file).filter(|_| is_rook))
+            }
+            _ => return Err(bad_rights()),
+        };
+        let rook_file = rook_file.ok_or_else(bad_rights)?;
+        let index = match (player_type, short) {
+            (PlayerType::White, true) => 0,
+            (PlayerType::White, false) => 1,
+            (PlayerType::Black, true) => 2,
+            (PlayerType::Black, false) => 3,
+        };
+        if rights[index] {
+            return Err(bad_rights());
         }
+        rights[index] = true;
+        rook_files[index] = rook_file;
+    }
+    Ok((rights, rook_files))
+}
+
+/// the square has to be behind a pawn that could have just moved two squares
+fn parse_en_passant_square(
+    field: &str,
+    table: &[[Option<Piece>; 8]; 8],
+    active_color: PlayerType,
+) -> Result<Option<(usize, usize)>, FenError> {
+    if field == "-" {
+        return Ok(None);
     }
-    true
+    let bad_square = || FenError::BadEnPassantSquare(field.to_string());
+    let (file, rank) = pos_from_algebraic_notation(field).ok_or_else(bad_square)?;
+    // rank the pawn passed over , and the ranks it moved to and came from
+    let (target_rank, pawn_rank, start_rank, pawn_color) = match active_color {
+        PlayerType::White => (5, 4, 6, PlayerType::Black),
+        PlayerType::Black => (2, 3, 1, PlayerType::White),
+    };
+    if rank != target_rank
+        || table[rank][file].is_some()
+        || table[start_rank][file].is_some()
+        || table[pawn_rank][file] != Some(Piece::new(PieceType::Pawn, pawn_color))
+    {
+        return Err(bad_square());
+    }
+    Ok(Some((file, rank)))
+}
+
+/// checks white and black have left , as in `3+3`
+fn parse_remaining_checks(field: &str) -> Result<[u32; 2], FenError> {
+    let bad_count = || FenError::BadCheckCount(field.to_string());
+    let (white, black) = field.split_once('+').ok_or_else(bad_count)?;
+    let parse = |count: &str| count.parse::<u32>().map_err(|_| bad_count());
+    Ok([parse(white)?, parse(black)?])
 }
 
+/// (file , rank) in board coordinates , file 0 is the h file and rank 0 is the first rank
 fn pos_from_algebraic_notation(pos: &str) -> Option<(usize, usize)> {
     if pos.len() == 2 {
-        let mut it = pos.chars().into_iter();
+        let mut it = pos.chars();
         Some((
             match it.next() {
-                Some('a') => 0,
-                Some('b') => 1,
-                Some('c') => 2,
-                Some('d') => 3,
-                Some('e') => 4,
-                Some('f') => 5,
-                Some('g') => 6,
-                Some('h') => 7 as usize,
+                Some('a') => 7,
+                Some('b') => 6,
+                Some('c') => 5,
+                Some('d') => 4,
+                Some('e') => 3,
+                Some('f') => 2,
+                Some('g') => 1,
+                Some('h') => 0,
                 _ => {
                     return None;
                 }
             },
             match it.next() {
                 Some(num) => match num.to_digit(10) {
-                    Some(nu) => nu as usize,
-                    None => {
+                    Some(nu @ 1..=8) => nu as usize - 1,
+                    _ => {
                         return None;
                     }
                 },
@@ -64,114 +264,310 @@ fn pos_from_algebraic_notation(pos: &str) -> Option<(usize, usize)> {
 pub struct GameData {
     pub table: [[Option<Piece>; 8]; 8],
     pub active_color: PlayerType,
-    pub castling_availability: (bool, bool, bool, bool),
+    pub castling_availability: [bool; 4],
     // 0 -> white castle king side , 1 -> white castle queen side
     // 2 -> black castle king side , 3 -> black castle queen side
+    /// file each castling right's rook starts on , they only differ from the corners in chess960
+    pub castling_rook_files: [usize; 4],
     pub en_passant_target_square: Option<(usize, usize)>,
     pub halfmove_clock: u32, //The number of halfmoves since the last capture or pawn advance, used for the fifty-move rule.
     pub fullmove_number: u32, //  The number of the full moves. It starts at 1 and is incremented after Black's move.
+    /// checks white and black have left in three-check , written before the clocks
+    pub remaining_checks: Option<[u32; 2]>,
 }
 impl GameData {
-    pub fn from_fen(fen: &str) -> Option<Self> {
-        let (mut file, mut rank) = (0, 7);
-        let mut table: [[Option<Piece>; 8]; 8] = [[None; 8]; 8];
-        let mut sp = fen.split_ascii_whitespace();
-        let fen_board = match sp.next() {
-            Some(ele) => ele,
-            None => {
-                return None;
-            }
+    pub fn from_fen(fen: &str) -> Result<Self, FenError> {
+        Self::parse(fen, true)
+    }
+    /// same as from_fen for variants where kings can be taken , any number of them is fine
+    pub fn from_fen_any_kings(fen: &str) -> Result<Self, FenError> {
+        Self::parse(fen, false)
+    }
+    fn parse(fen: &str, require_kings: bool) -> Result<Self, FenError> {
+        let mut fields: Vec<&str> = fen.split_ascii_whitespace().collect();
+        let remaining_checks = match fields.get(4) {
+            Some(field) if field.contains('+') => Some(parse_remaining_checks(fields.remove(4))?),
+            _ => None,
         };
-        let cur_player = match sp.next() {
-            Some(ele) => match ele.len() {
-                1 => {
-                    let x = ele.chars().next();
-                    // dbg!(&x);
-                    match x {
-                        Some('w') => PlayerType::White,
-                        Some('b') => PlayerType::Black,
-                        _ => {
-                            return None;
-                        }
-                    }
-                }
-                _ => {
-                    return None;
-                }
-            },
-            None => {
-                return None;
-            }
+        if fields.len() != 6 && fields.len() != 4 {
+            return Err(FenError::BadFieldCount(fields.len()));
+        }
+        let table = parse_fen_board(fields[0], require_kings)?;
+        let active_color = match fields[1] {
+            "w" => PlayerType::White,
+            "b" => PlayerType::Black,
+            field => return Err(FenError::BadActiveColor(field.to_string())),
         };
-        let mut castle = (false, false, false, false);
-
-        match sp.next() {
-            Some(ele) => {
-                for ch in ele.chars() {
-                    match ch {
-                        'k' => {
-                            castle.2 = true;
-                        }
-                        'q' => {
-                            castle.3 = true;
-                        }
-                        'K' => {
-                            castle.0 = true;
-                        }
-                        'Q' => {
-                            castle.1 = true;
+        let (castling_availability, castling_rook_files) =
+            parse_castling_rights(fields[2], &table)?;
+        let en_passant_target_square = parse_en_passant_square(fields[3], &table, active_color)?;
+        // the clocks are optional
+        let halfmove_clock = match fields.get(4) {
+            Some(field) => field
+                .parse::<u32>()
+                .map_err(|_| FenError::BadHalfMoveClock(field.to_string()))?,
+            None => 0,
+        };
+        let fullmove_number = match fields.get(5) {
+            Some(field) => field
+                .parse::<u32>()
+                .ok()
+                .filter(|no| *no > 0)
+                .ok_or_else(|| FenError::BadFullMoveNumber(field.to_string()))?,
+            None => 1,
+        };
+        Ok(Self {
+            table,
+            active_color,
+            castling_availability,
+            castling_rook_files,
+            en_passant_target_square,
+            halfmove_clock,
+            fullmove_number,
+            remaining_checks,
+        })
+    }
+    pub fn to_fen(&self) -> String {
+        let mut fen = String::new();
+        for rank in (0..8).rev() {
+            let mut empty = 0;
+            for file in (0..8).rev() {
+                match self.table[rank][file] {
+                    Some(piece) => {
+                        if empty > 0 {
+                            fen.push_str(&empty.to_string());
+                            empty = 0;
                         }
-                        _ => {}
+                        let ch = match piece.piece_kind {
+                            PieceType::King => 'k',
+                            PieceType::Pawn => 'p',
+                            PieceType::Knight => 'n',
+                            PieceType::Bishop => 'b',
+                            PieceType::Rook => 'r',
+                            PieceType::Queen => 'q',
+                        };
+                        fen.push(match piece.player_kind {
+                            PlayerType::White => ch.to_ascii_uppercase(),
+                            PlayerType::Black => ch,
+                        });
                     }
+                    None => empty += 1,
                 }
             }
-            None => {
-                return None;
+            if empty > 0 {
+                fen.push_str(&empty.to_string());
             }
-        };
+            if rank > 0 {
+                fen.push('/');
+            }
+        }
+        fen.push(' ');
+        fen.push(match self.active_color {
+            PlayerType::White => 'w',
+            PlayerType::Black => 'b',
+        });
+        fen.push(' ');
+        // X-FEN , the file of the rook is only named when it is not the outermost one
+        let castle_string: String = [
+            (PlayerType::White, true, 'K'),
+            (PlayerType::White, false, 'Q'),
+            (PlayerType::Black, true, 'k'),
+            (PlayerType::Black, false, 'q'),
+        ]
+        .into_iter()
+        .enumerate()
+        .filter(|(index, _)| self.castling_availability[*index])
+        .map(|(index, (player_type, short, ch))| {
+            let rook_file = self.castling_rook_files[index];
+            if outermost_rook(&self.table, player_type, short) == Some(rook_file) {
+                ch
+            } else {
+                let file_char = (b'a' + 7 - rook_file as u8) as char;
+                match player_type {
+                    PlayerType::White => file_char.to_ascii_uppercase(),
+                    PlayerType::Black => file_char,
+                }
+            }
+        })
+        .collect();
+        if castle_string.is_empty() {
+            fen.push('-');
+        } else {
+            fen.push_str(&castle_string);
+        }
+        fen.push(' ');
+        match self
+            .en_passant_target_square
+            .and_then(ChessPosition::from_tuple)
+        {
+            Some(pos) => fen.push_str(&pos.to_string()),
+            None => fen.push('-'),
+        }
+        if let Some([white, black]) = self.remaining_checks {
+            fen.push_str(&format!(" {}+{}", white, black));
+        }
+        format!("{} {} {}", fen, self.halfmove_clock, self.fullmove_number)
+    }
+}
 
-        let en_pass = pos_from_algebraic_notation(sp.next()?);
+#[cfg(test)]
+mod tests {
+    use super::{FenError, GameData};
+    use crate::common::logic::{GameState, PlayerType};
 
-        let half_move_no = sp.next()?.parse::<u32>().ok()?;
+    const POSITIONS: [&str; 12] = [
+        "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+        "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1",
+        "rnbqkbnr/pp1ppppp/8/2p5/4P3/8/PPPP1PPP/RNBQKBNR w KQkq c6 0 2",
+        "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+        "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+        "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+        "rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8",
+        "r4rk1/1pp1qppp/p1np1n2/2b1p1B1/2B1P1b1/P1NP1N2/1PP1QPPP/R4RK1 w - - 0 10",
+        "4k3/8/8/8/8/8/8/4K2R w K - 0 1",
+        "r3k3/8/8/8/8/8/8/4K3 b q - 12 40",
+        "8/8/8/3pP3/8/8/8/k6K w - d6 0 57",
+        "7k/8/8/8/8/8/8/K7 b - - 99 120",
+    ];
 
-        let move_no = sp.next()?.parse::<u32>().ok()?;
+    #[test]
+    fn game_data_round_trips() {
+        for fen in POSITIONS {
+            assert_eq!(GameData::from_fen(fen).unwrap().to_fen(), fen);
+        }
+    }
 
-        if verify_fen_board(fen_board) {
-            for i in fen_board.chars() {
-                if i == '/' {
-                    file = 0;
-                    rank -= 1;
-                } else if i.is_numeric() {
-                    file += i.to_digit(10).unwrap_or(0);
-                } else {
-                    let player_type = if i.is_uppercase() {
-                        PlayerType::White
-                    } else {
-                        PlayerType::Black
-                    };
-                    let piece = match i.to_ascii_lowercase() {
-                        'k' => Some(Piece::new(PieceType::King, player_type)),
-                        'p' => Some(Piece::new(PieceType::Pawn, player_type)),
-                        'n' => Some(Piece::new(PieceType::Knight, player_type)),
-                        'b' => Some(Piece::new(PieceType::Bishop, player_type)),
-                        'r' => Some(Piece::new(PieceType::Rook, player_type)),
-                        'q' => Some(Piece::new(PieceType::Queen, player_type)),
-                        _ => None,
-                    };
-                    table[rank as usize][(7 - file) as usize] = piece;
-                    file += 1;
-                }
-            }
-            Some(Self {
-                table: table,
-                active_color: cur_player,
-                castling_availability: castle,
-                en_passant_target_square: en_pass,
-                halfmove_clock: half_move_no,
-                fullmove_number: move_no,
-            })
-        } else {
-            return None;
+    #[test]
+    fn game_state_round_trips() {
+        for fen in POSITIONS {
+            assert_eq!(GameState::from_fen(fen).unwrap().to_fen(), fen);
         }
     }
+
+    #[test]
+    fn en_passant_square_is_read_in_board_coordinates() {
+        let game_data = GameData::from_fen(POSITIONS[1]).unwrap();
+        // file 0 is the h file , so e3 is file 3 on the third rank
+        assert_eq!(game_data.en_passant_target_square, Some((3, 2)));
+    }
+
+    #[test]
+    fn bad_fens_are_rejected() {
+        let cases = [
+            (
+                "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq",
+                FenError::BadFieldCount(3),
+            ),
+            (
+                "rnbqkbnr/pppppppp/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+                FenError::BadRankCount(7),
+            ),
+            (
+                "rnbqkbnr/pppppppp/8/8/7/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+                FenError::BadRankLength(4),
+            ),
+            (
+                "rnbqkbnr/pppppppp/8/8/44P/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+                FenError::BadRankLength(4),
+            ),
+            (
+                "rnbqkbnr/pppppppp/8/8/3X4/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+                FenError::IllegalPieceChar('X'),
+            ),
+            (
+                "rnbqqbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQ - 0 1",
+                FenError::MissingKing(PlayerType::Black),
+            ),
+            (
+                "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBKKBNR w kq - 0 1",
+                FenError::TooManyKings(PlayerType::White),
+            ),
+            ("P3k3/8/8/8/8/8/8/4K3 w - - 0 1", FenError::PawnOnBackRank),
+            (
+                "4k3/8/8/8/8/8/8/4K3 x - - 0 1",
+                FenError::BadActiveColor("x".to_string()),
+            ),
+            (
+                "4k3/8/8/8/8/8/8/4K3 w K - 0 1",
+                FenError::BadCastlingRights("K".to_string()),
+            ),
+            (
+                "r3k2r/8/8/8/8/8/8/R3K2R w KKq - 0 1",
+                FenError::BadCastlingRights("KKq".to_string()),
+            ),
+            (
+                "r3k2r/8/8/8/8/8/8/R3K2R w Ab - 0 1",
+                FenError::BadCastlingRights("Ab".to_string()),
+            ),
+            (
+                "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e4 0 1",
+                FenError::BadEnPassantSquare("e4".to_string()),
+            ),
+            (
+                "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR b KQkq e3 0 1",
+                FenError::BadEnPassantSquare("e3".to_string()),
+            ),
+            (
+                "4k3/8/8/8/8/8/8/4K3 w - - x 1",
+                FenError::BadHalfMoveClock("x".to_string()),
+            ),
+            (
+                "4k3/8/8/8/8/8/8/4K3 w - - 0 0",
+                FenError::BadFullMoveNumber("0".to_string()),
+            ),
+        ];
+        for (fen, fen_error) in cases {
+            assert_eq!(GameData::from_fen(fen).err(), Some(fen_error), "{}", fen);
+        }
+    }
+
+    #[test]
+    fn three_check_counts_and_missing_kings() {
+        let fen = "rnbqkbnr/pppp1ppp/8/4p3/4P3/8/PPPP1PPP/RNBQKBNR w KQkq e6 2+3 0 2";
+        let game_data = GameData::from_fen(fen).unwrap();
+        assert_eq!(game_data.remaining_checks, Some([2, 3]));
+        assert_eq!(game_data.to_fen(), fen);
+        assert_eq!(
+            GameData::from_fen("4k3/8/8/8/8/8/8/4K3 w - - 3+x 0 1").err(),
+            Some(FenError::BadCheckCount("3+x".to_string()))
+        );
+        // antichess and atomic positions can be without kings
+        assert!(GameData::from_fen("8/8/8/8/8/8/8/4K3 w - - 0 1").is_err());
+        assert!(GameData::from_fen_any_kings("8/8/8/8/8/8/8/4K3 w - - 0 1").is_ok());
+    }
+
+    #[test]
+    fn clocks_can_be_left_out() {
+        let game_data = GameData::from_fen("4k3/8/8/8/8/8/8/4K3 w - -").unwrap();
+        assert_eq!(game_data.to_fen(), "4k3/8/8/8/8/8/8/4K3 w - - 0 1");
+    }
+
+    #[test]
+    fn chess960_castling_rights() {
+        // Shredder-FEN names the rook files , X-FEN only does when the rook is not the outermost
+        let game_data =
+            GameData::from_fen("bqnb1rkr/pp3ppp/3ppn2/2p5/5P2/P2P4/NPP1P1PP/BQ1BNRKR w HFhf - 2 9")
+                .unwrap();
+        assert_eq!(game_data.castling_availability, [true; 4]);
+        assert_eq!(game_data.castling_rook_files, [0, 2, 0, 2]);
+        assert_eq!(
+            game_data.to_fen(),
+            "bqnb1rkr/pp3ppp/3ppn2/2p5/5P2/P2P4/NPP1P1PP/BQ1BNRKR w KQkq - 2 9"
+        );
+        let inner_rook = "1r2k1r1/8/8/8/8/8/8/RR2K3 w Bq - 0 1";
+        let game_data = GameData::from_fen(inner_rook).unwrap();
+        assert_eq!(game_data.castling_rook_files[1], 6);
+        assert_eq!(game_data.castling_rook_files[3], 6);
+        assert_eq!(game_data.to_fen(), inner_rook);
+        assert_eq!(
+            GameData::from_fen("4k3/8/8/8/8/8/8/R3K3 w C - 0 1").err(),
+            Some(FenError::BadCastlingRights("C".to_string()))
+        );
+    }
+
+    #[test]
+    fn castling_rights_are_written_in_standard_order() {
+        let game_data = GameData::from_fen("r3k2r/8/8/8/8/8/8/R3K2R w qkQK - 0 1").unwrap();
+        assert_eq!(game_data.to_fen(), "r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1");
+    }
 }
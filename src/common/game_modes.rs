@@ -1,8 +1,12 @@
 use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+use crate::common::time_control::TimeControl;
 #[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
 pub enum GameMode {
     Local,
     Online,
+    VsComputer,
 }
 
 #[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
@@ -13,15 +17,18 @@ pub enum GameType {
     Bullet,
 }
 impl GameType {
-    pub fn time_control(&self) -> u64 {
-        match self {
+    pub fn time_control(&self) -> TimeControl {
+        let seconds = match self {
             GameType::Blitz => 300,
             GameType::Rapid => 600,
             GameType::Classical => 1800,
             GameType::Bullet => 60,
-        }
+        };
+        TimeControl::sudden_death(Duration::from_secs(seconds))
     }
-    pub fn to_string(&self) -> String {
+}
+impl ToString for GameType {
+    fn to_string(&self) -> String {
         match self {
             GameType::Blitz => "Blitz".to_string(),
             GameType::Rapid => "Rapid".to_string(),
@@ -1,29 +1,138 @@
 use serde::{Deserialize, Serialize};
 
-use crate::common::fen::GameData;
+use crate::common::bitboard::{self, BitBoards};
+use crate::common::fen::{FenError, GameData, STANDARD_ROOK_FILES};
+use crate::common::san;
+use crate::common::variant::{self, Variant, CHECKS_TO_WIN};
+use crate::common::zobrist;
 
-use super::move_history::{CastleType, ChessMove, ChessMoveKind, ChessMoveVector};
-#[derive(Debug, Clone, Copy)]
+use super::move_history::{
+    CastleType, ChessMove, ChessMoveKind, ChessMoveVector, HistoryEntry, MoveHistory,
+};
+
+pub const START_FEN: &str = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum GameEnd {
-    Checkmate,
-    StaleMate,
-    Draw,
+    Win {
+        winner: PlayerType,
+        reason: WinReason,
+    },
+    Draw(DrawReason),
+}
+impl GameEnd {
+    /// None for a draw
+    pub fn winner(&self) -> Option<PlayerType> {
+        match self {
+            Self::Win { winner, .. } => Some(*winner),
+            Self::Draw(_) => None,
+        }
+    }
 }
 impl ToString for GameEnd {
     fn to_string(&self) -> String {
         match self {
-            Self::Checkmate => "Checkmate".to_string(),
-            Self::StaleMate => "Stalemate".to_string(),
-            Self::Draw => "Draw".to_string(),
+            Self::Win { winner, reason } => {
+                format!("{} wins by {}", winner.to_string(), reason.to_string())
+            }
+            Self::Draw(reason) => format!("Draw by {}", reason.to_string()),
+        }
+    }
+}
+/// how the winner won
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum WinReason {
+    Checkmate,
+    /// the loser ran out of time
+    Timeout,
+    Resignation,
+    /// the king reached the centre in king of the hill
+    KingOfTheHill,
+    /// the third check in three-check
+    ThreeChecks,
+    /// the other king was blown up in atomic
+    KingExploded,
+    /// the winner lost every piece or was stalemated in antichess
+    NoMovesLeft,
+    /// the loser left an Online game and did not come back in time
+    Abandonment,
+}
+impl ToString for WinReason {
+    fn to_string(&self) -> String {
+        match self {
+            Self::Checkmate => "checkmate".to_string(),
+            Self::Timeout => "timeout".to_string(),
+            Self::Resignation => "resignation".to_string(),
+            Self::KingOfTheHill => "reaching the hill".to_string(),
+            Self::ThreeChecks => "three checks".to_string(),
+            Self::KingExploded => "exploding the king".to_string(),
+            Self::NoMovesLeft => "having no moves left".to_string(),
+            Self::Abandonment => "abandonment".to_string(),
         }
     }
 }
-#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize, Eq)]
+/// why a game was drawn
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DrawReason {
+    /// the player to move has no legal move but is not in check
+    Stalemate,
+    /// one player offered a draw and the other accepted
+    Agreement,
+    /// claimed after fifty moves by each player without a capture or pawn move
+    FiftyMoveRule,
+    /// forced after seventy five moves by each player without a capture or pawn move
+    SeventyFiveMoveRule,
+    /// claimed when the same position comes up for the third time
+    ThreefoldRepetition,
+    /// forced when the same position comes up for the fifth time
+    FivefoldRepetition,
+    /// neither player can checkmate anymore
+    InsufficientMaterial,
+    /// a player ran out of time but the other could never have checkmated
+    TimeoutVsInsufficientMaterial,
+    /// a player left but the other could never have checkmated
+    AbandonmentVsInsufficientMaterial,
+}
+impl ToString for DrawReason {
+    fn to_string(&self) -> String {
+        match self {
+            Self::Stalemate => "stalemate".to_string(),
+            Self::Agreement => "agreement".to_string(),
+            Self::FiftyMoveRule => "the fifty move rule".to_string(),
+            Self::SeventyFiveMoveRule => "the seventy five move rule".to_string(),
+            Self::ThreefoldRepetition => "threefold repetition".to_string(),
+            Self::FivefoldRepetition => "fivefold repetition".to_string(),
+            Self::InsufficientMaterial => "insufficient material".to_string(),
+            Self::TimeoutVsInsufficientMaterial => {
+                "timeout against insufficient material".to_string()
+            }
+            Self::AbandonmentVsInsufficientMaterial => {
+                "abandonment against insufficient material".to_string()
+            }
+        }
+    }
+}
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize, Eq, Hash)]
 pub enum PlayerType {
     Black,
     White,
 }
-#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+impl PlayerType {
+    pub fn opponent(self) -> Self {
+        match self {
+            PlayerType::Black => PlayerType::White,
+            PlayerType::White => PlayerType::Black,
+        }
+    }
+}
+impl ToString for PlayerType {
+    fn to_string(&self) -> String {
+        match self {
+            PlayerType::Black => "Black".to_string(),
+            PlayerType::White => "White".to_string(),
+        }
+    }
+}
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum PieceType {
     Pawn,
     Rook,
@@ -32,7 +141,7 @@ pub enum PieceType {
     Queen,
     King,
 }
-#[derive(Debug, Copy, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[derive(Debug, Copy, Clone, Serialize, Deserialize, PartialEq, Eq, Hash)]
 pub struct Piece {
     pub piece_kind: PieceType,
     pub player_kind: PlayerType,
@@ -41,13 +150,13 @@ pub struct Piece {
 impl Piece {
     pub fn new(piece_kind: PieceType, player_kind: PlayerType) -> Piece {
         Self {
-            piece_kind: piece_kind,
-            player_kind: player_kind,
+            piece_kind,
+            player_kind,
         }
     }
 }
 
-#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub struct ChessPosition {
     pub file: i32,
     pub rank: i32,
@@ -114,19 +223,73 @@ impl ChessPosition {
         }
     }
 }
+/// algebraic name of the square , e.g. "e4"
+impl ToString for ChessPosition {
+    fn to_string(&self) -> String {
+        format!("{}{}", (b'h' - self.file as u8) as char, self.rank + 1)
+    }
+}
+/// pieces by rank then file
+pub type BoardTable = [[Option<Piece>; 8]; 8];
+/// the pieces on the board , square by square and as bitboards kept in step with the squares
 #[derive(Clone, Serialize, Deserialize, Debug)]
-pub struct ChessBoard(pub [[Option<Piece>; 8]; 8]);
+#[serde(from = "BoardTable", into = "BoardTable")]
+pub struct ChessBoard {
+    table: BoardTable,
+    bitboards: BitBoards,
+    /// zobrist keys of all pieces xored together , updated with every piece set or removed
+    piece_key: u64,
+}
+impl From<BoardTable> for ChessBoard {
+    fn from(table: BoardTable) -> Self {
+        Self::new(table)
+    }
+}
+impl From<ChessBoard> for BoardTable {
+    fn from(board: ChessBoard) -> Self {
+        board.table
+    }
+}
 impl ChessBoard {
+    pub fn new(table: BoardTable) -> Self {
+        let mut piece_key = 0;
+        for pos in ChessPosition::iter() {
+            if let Some(piece) = table[pos.rank as usize][pos.file as usize] {
+                piece_key ^= zobrist::piece(piece, &pos);
+            }
+        }
+        Self {
+            table,
+            bitboards: BitBoards::from_table(&table),
+            piece_key,
+        }
+    }
+    pub fn table(&self) -> &BoardTable {
+        &self.table
+    }
+    pub fn bitboards(&self) -> &BitBoards {
+        &self.bitboards
+    }
+    pub fn piece_key(&self) -> u64 {
+        self.piece_key
+    }
     pub fn get_piece_at_pos(&self, pos: &ChessPosition) -> Option<Piece> {
-        self.0[pos.rank as usize][pos.file as usize]
+        self.table[pos.rank as usize][pos.file as usize]
     }
     fn set_piece_at_pos(&mut self, pos: &ChessPosition, piece: Piece) {
-        self.0[pos.rank as usize][pos.file as usize] = Some(piece);
+        self.remove_piece_at_pos(pos);
+        self.table[pos.rank as usize][pos.file as usize] = Some(piece);
+        self.bitboards.set(bitboard::square_of(pos), piece);
+        self.piece_key ^= zobrist::piece(piece, pos);
     }
     fn remove_piece_at_pos(&mut self, pos: &ChessPosition) {
-        self.0[pos.rank as usize][pos.file as usize] = None;
+        if let Some(piece) = self.table[pos.rank as usize][pos.file as usize].take() {
+            self.bitboards.remove(bitboard::square_of(pos), piece);
+            self.piece_key ^= zobrist::piece(piece, pos);
+        }
     }
-    fn perform_move(&mut self, chess_move: &ChessMove) {
+    /// `castling_rook_files` are the files the rooks castle from , as in GameState
+    fn perform_move(&mut self, chess_move: &ChessMove, castling_rook_files: [i32; 4]) {
         match chess_move.move_kind {
             ChessMoveKind::Normal | ChessMoveKind::Take(_) => {
                 self.set_piece_at_pos(&chess_move.move_vector.to_pos, chess_move.moved_piece);
@@ -137,42 +300,20 @@ impl ChessBoard {
                 self.remove_piece_at_pos(&chess_move.move_vector.from_pos);
                 self.remove_piece_at_pos(&en_pass_pos);
             }
-            ChessMoveKind::Castle(castle_type) => match castle_type {
-                CastleType::Short => {
-                    self.set_piece_at_pos(
-                        &ChessPosition::new(6, chess_move.move_vector.from_pos.rank).unwrap(),
-                        chess_move.moved_piece,
-                    );
-                    self.remove_piece_at_pos(&chess_move.move_vector.from_pos);
-                    self.set_piece_at_pos(
-                        &ChessPosition::new(5, chess_move.move_vector.from_pos.rank).unwrap(),
-                        self.get_piece_at_pos(
-                            &ChessPosition::new(7, chess_move.move_vector.from_pos.rank).unwrap(),
-                        )
-                        .unwrap(),
-                    );
-                    self.remove_piece_at_pos(
-                        &ChessPosition::new(7, chess_move.move_vector.from_pos.rank).unwrap(),
-                    );
-                }
-                CastleType::Long => {
-                    self.set_piece_at_pos(
-                        &ChessPosition::new(2, chess_move.move_vector.from_pos.rank).unwrap(),
-                        chess_move.moved_piece,
-                    );
-                    self.remove_piece_at_pos(&chess_move.move_vector.from_pos);
-                    self.set_piece_at_pos(
-                        &ChessPosition::new(3, chess_move.move_vector.from_pos.rank).unwrap(),
-                        self.get_piece_at_pos(
-                            &ChessPosition::new(0, chess_move.move_vector.from_pos.rank).unwrap(),
-                        )
-                        .unwrap(),
-                    );
-                    self.remove_piece_at_pos(
-                        &ChessPosition::new(0, chess_move.move_vector.from_pos.rank).unwrap(),
-                    );
-                }
-            },
+            ChessMoveKind::Castle(castle_type) => {
+                let player = chess_move.moved_piece.player_kind;
+                let king_from = chess_move.move_vector.from_pos;
+                let rook_from = ChessPosition {
+                    file: castling_rook_files[castling_index(castle_type, player)],
+                    rank: king_from.rank,
+                };
+                let (king_to, rook_to) = castle_destinations(castle_type, king_from.rank);
+                // in chess960 the king or rook can end up where the other one started
+                self.remove_piece_at_pos(&king_from);
+                self.remove_piece_at_pos(&rook_from);
+                self.set_piece_at_pos(&king_to, chess_move.moved_piece);
+                self.set_piece_at_pos(&rook_to, Piece::new(PieceType::Rook, player));
+            }
             ChessMoveKind::Promotion(piece_type) => {
                 self.set_piece_at_pos(
                     &chess_move.move_vector.to_pos,
@@ -182,51 +323,197 @@ impl ChessBoard {
             }
         }
     }
+    /// reverts perform_move , captured_piece is the piece that was taken by the move if any
+    fn unperform_move(
+        &mut self,
+        chess_move: &ChessMove,
+        captured_piece: Option<Piece>,
+        castling_rook_files: [i32; 4],
+    ) {
+        if let ChessMoveKind::Castle(castle_type) = chess_move.move_kind {
+            let player = chess_move.moved_piece.player_kind;
+            let king_from = chess_move.move_vector.from_pos;
+            let rook_from = ChessPosition {
+                file: castling_rook_files[castling_index(castle_type, player)],
+                rank: king_from.rank,
+            };
+            let (king_to, rook_to) = castle_destinations(castle_type, king_from.rank);
+            self.remove_piece_at_pos(&king_to);
+            self.remove_piece_at_pos(&rook_to);
+            self.set_piece_at_pos(&king_from, chess_move.moved_piece);
+            self.set_piece_at_pos(&rook_from, Piece::new(PieceType::Rook, player));
+            return;
+        }
+        self.set_piece_at_pos(&chess_move.move_vector.from_pos, chess_move.moved_piece);
+        self.remove_piece_at_pos(&chess_move.move_vector.to_pos);
+        match chess_move.move_kind {
+            ChessMoveKind::EnPassant(en_pass_pos) => {
+                if let Some(piece) = captured_piece {
+                    self.set_piece_at_pos(&en_pass_pos, piece);
+                }
+            }
+            _ => {
+                if let Some(piece) = captured_piece {
+                    self.set_piece_at_pos(&chess_move.move_vector.to_pos, piece);
+                }
+            }
+        }
+    }
+}
+/// squares the king and the rook end up on when castling on the given rank , the same in chess960
+pub fn castle_destinations(castle_type: CastleType, rank: i32) -> (ChessPosition, ChessPosition) {
+    match castle_type {
+        CastleType::Short => (
+            ChessPosition { file: 1, rank },
+            ChessPosition { file: 2, rank },
+        ),
+        CastleType::Long => (
+            ChessPosition { file: 5, rank },
+            ChessPosition { file: 4, rank },
+        ),
+    }
+}
+/// position of the castling right in castling_availability
+pub fn castling_index(castle_type: CastleType, player: PlayerType) -> usize {
+    match (player, castle_type) {
+        (PlayerType::White, CastleType::Short) => 0,
+        (PlayerType::White, CastleType::Long) => 1,
+        (PlayerType::Black, CastleType::Short) => 2,
+        (PlayerType::Black, CastleType::Long) => 3,
+    }
+}
+fn standard_rook_files() -> [i32; 4] {
+    STANDARD_ROOK_FILES.map(|file| file as i32)
 }
 #[derive(Clone, Serialize, Deserialize, Debug)]
 pub struct GameState {
     pub board: ChessBoard,
     pub active_color: PlayerType,
     /// white short , white long , black short , black long
-    castling_availability: (bool, bool, bool, bool),
+    castling_availability: [bool; 4],
+    /// files the rooks castle from , in the same order , they never change during a game
+    #[serde(default = "standard_rook_files")]
+    castling_rook_files: [i32; 4],
+    /// castling moves are written as the king taking its own rook , as chess960 guis expect
+    #[serde(default)]
+    chess960: bool,
+    #[serde(default)]
+    variant: Variant,
+    /// checks each player has given , white first , only counted in three-check
+    #[serde(default)]
+    checks_given: [u32; 2],
     en_passant_target_square: Option<ChessPosition>,
     pub half_move_clock: u32,
     pub full_move_no: u32,
 
-    pub prev_moves: Vec<ChessMove>,
-    pub promotion: bool,
+    #[serde(default)]
+    pub move_history: MoveHistory,
+    /// records of the moves played so far , latest last
+    #[serde(default)]
+    move_records: Vec<MoveRecord>,
+    /// moves taken back with undo_move , the next one to redo is last
+    #[serde(default)]
+    redo_moves: Vec<ChessMove>,
+    /// position_key of every position of the game , the current one last
+    #[serde(default)]
+    position_keys: Vec<u64>,
+    /// set once the game is over , no more moves can be done then
+    #[serde(default)]
+    game_end: Option<GameEnd>,
+    /// the player whose draw offer has not been answered yet
+    #[serde(default)]
+    pub draw_offer: Option<PlayerType>,
 
     pub recurrance: bool,
 }
+/// everything needed to take a move back
+#[derive(Clone, Copy, Serialize, Deserialize, Debug)]
+pub struct MoveRecord {
+    pub chess_move: ChessMove,
+    pub captured_piece: Option<Piece>,
+    castling_availability: [bool; 4],
+    en_passant_target_square: Option<ChessPosition>,
+    half_move_clock: u32,
+    full_move_no: u32,
+    #[serde(default)]
+    checks_given: [u32; 2],
+    /// pieces blown up by an atomic capture besides the one taken , with their squares
+    #[serde(default)]
+    exploded: [Option<(ChessPosition, Piece)>; 8],
+}
 
-impl Default for GameState {
-    fn default() -> Self {
-        let def =
-            GameData::from_fen("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1").unwrap();
+/// pieces a pawn can be promoted to , in the order they are offered
+pub const PROMOTION_PIECES: [PieceType; 4] = [
+    PieceType::Queen,
+    PieceType::Rook,
+    PieceType::Bishop,
+    PieceType::Knight,
+];
 
-        let en_pass = if let Some(ele) = def.en_passant_target_square {
-            ChessPosition::from_tuple((ele.0, ele.1))
-        } else {
-            None
-        };
+pub const KNIGHT_OFFSETS: [(i32, i32); 8] = [
+    (1, 2),
+    (2, 1),
+    (2, -1),
+    (1, -2),
+    (-1, -2),
+    (-2, -1),
+    (-2, 1),
+    (-1, 2),
+];
+/// one step in every direction , queens slide along the same ones
+pub const KING_OFFSETS: [(i32, i32); 8] = [
+    (1, 0),
+    (0, -1),
+    (-1, 0),
+    (0, 1),
+    (1, 1),
+    (1, -1),
+    (-1, -1),
+    (-1, 1),
+];
 
-        Self {
-            board: ChessBoard(def.table),
-            active_color: def.active_color,
-            castling_availability: def.castling_availability,
-            en_passant_target_square: en_pass,
-            half_move_clock: def.halfmove_clock,
-            full_move_no: def.fullmove_number,
-            prev_moves: vec![],
-            promotion: false,
-            recurrance: false,
+/// pushes a pawn move , expanding it into every promotion choice if it lands on the last rank
+fn push_pawn_move(moves: &mut Vec<ChessMove>, chess_move: ChessMove) {
+    let to_rank = chess_move.move_vector.to_pos.rank;
+    if to_rank == 0 || to_rank == 7 {
+        for piece_type in PROMOTION_PIECES {
+            moves.push(ChessMove::new(
+                ChessMoveKind::Promotion(piece_type),
+                chess_move.move_vector,
+                chess_move.moved_piece,
+            ));
         }
+    } else {
+        moves.push(chess_move);
+    }
+}
+
+impl Default for GameState {
+    fn default() -> Self {
+        Self::from_fen(START_FEN).unwrap()
     }
 }
 
 impl GameState {
-    pub fn from_fen(fen_string: &str) -> Option<Self> {
-        let def = GameData::from_fen(fen_string).unwrap();
+    pub fn from_fen(fen_string: &str) -> Result<Self, FenError> {
+        Self::from_fen_variant(fen_string, Variant::Standard, false)
+    }
+    /// same as from_fen , for a chess960 game that may have started from any position
+    pub fn from_fen_chess960(fen_string: &str) -> Result<Self, FenError> {
+        Self::from_fen_variant(fen_string, Variant::Standard, true)
+    }
+    /// the position played with the rules of the variant , chess960 castling is also used
+    /// when the castling rights can only be chess960 ones
+    pub fn from_fen_variant(
+        fen_string: &str,
+        variant: Variant,
+        chess960: bool,
+    ) -> Result<Self, FenError> {
+        let def = if variant.needs_kings() {
+            GameData::from_fen(fen_string)?
+        } else {
+            GameData::from_fen_any_kings(fen_string)?
+        };
 
         let en_pass = if let Some(ele) = def.en_passant_target_square {
             ChessPosition::from_tuple((ele.0, ele.1))
@@ -234,19 +521,67 @@ impl GameState {
             None
         };
 
-        Some(Self {
-            board: ChessBoard(def.table),
+        let mut game_state = Self {
+            board: ChessBoard::new(def.table),
             active_color: def.active_color,
             castling_availability: def.castling_availability,
+            castling_rook_files: def.castling_rook_files.map(|file| file as i32),
+            chess960,
+            variant,
+            checks_given: def.remaining_checks.map_or([0; 2], |checks| {
+                checks.map(|left| CHECKS_TO_WIN.saturating_sub(left))
+            }),
             en_passant_target_square: en_pass,
             half_move_clock: def.halfmove_clock,
             full_move_no: def.fullmove_number,
-            prev_moves: vec![],
-            promotion: false,
+            move_history: MoveHistory::new(def.to_fen()),
+            move_records: vec![],
+            redo_moves: vec![],
+            position_keys: vec![],
+            game_end: None,
+            draw_offer: None,
             recurrance: false,
-        })
+        };
+        // a castling king or rook off its usual square only happens in chess960
+        game_state.chess960 |= (0..4).any(|index| {
+            let player = if index < 2 {
+                PlayerType::White
+            } else {
+                PlayerType::Black
+            };
+            let rank = if index < 2 { 0 } else { 7 };
+            game_state.castling_availability[index]
+                && (game_state.castling_rook_files[index] != standard_rook_files()[index]
+                    || game_state.board.table[rank][3] != Some(Piece::new(PieceType::King, player)))
+        });
+        game_state.position_keys.push(game_state.position_key());
+        Ok(game_state)
+    }
+    pub fn is_chess960(&self) -> bool {
+        self.chess960
+    }
+    pub fn variant(&self) -> Variant {
+        self.variant
+    }
+    /// checks the player has given so far , only counted in three-check
+    pub fn checks_given(&self, player: PlayerType) -> u32 {
+        self.checks_given[variant::player_index(player)]
+    }
+    /// the square the rook castles from
+    pub fn castling_rook_square(
+        &self,
+        castle_type: CastleType,
+        player: PlayerType,
+    ) -> ChessPosition {
+        ChessPosition {
+            file: self.castling_rook_files[castling_index(castle_type, player)],
+            rank: match player {
+                PlayerType::White => 0,
+                PlayerType::Black => 7,
+            },
+        }
     }
-    fn generate_pseudo_legal_moves(&self) -> Vec<ChessMove> {
+    pub fn generate_pseudo_legal_moves(&self) -> Vec<ChessMove> {
         let mut moves: Vec<ChessMove> = vec![];
         for rank in 0..8 {
             for file in 0..8 {
@@ -263,23 +598,203 @@ impl GameState {
         }
         moves
     }
-    fn generate_legal_moves(&self) -> Vec<ChessMove> {
-        let mut moves: Vec<ChessMove> = vec![];
-        for rank in 0..8 {
-            for file in 0..8 {
-                let pos = ChessPosition::new(file, rank).unwrap();
-                // if current piece is of active color then only generate moves
-                if self
-                    .board
-                    .get_piece_at_pos(&pos)
-                    .is_some_and(|piece| piece.player_kind == self.active_color)
-                {
-                    moves.append(&mut self.generate_legal_moves_for_pos(&pos));
+    /// legal moves worked out from attack maps , checks and pins limit where each piece may go
+    /// so no move has to be tried on the board
+    pub fn generate_legal_moves(&self) -> Vec<ChessMove> {
+        if let Some(moves) = self.variant.legal_moves(self) {
+            return moves;
+        }
+        let bitboards = self.board.bitboards();
+        let (player, opponent) = (self.active_color, self.active_color.opponent());
+        let own = bitboards.player(player);
+        let occupied = bitboards.occupied();
+        let Some(king_square) = bitboard::squares(bitboards.pieces(PieceType::King, player)).next()
+        else {
+            // without a king nothing can be left in check
+            return self.generate_pseudo_legal_moves();
+        };
+        let mut moves = vec![];
+
+        // the king is taken off the board so it can not hide behind itself from a slider
+        let king = Piece::new(PieceType::King, player);
+        for to in bitboard::squares(bitboard::king_attacks(king_square) & !own) {
+            if bitboards.attackers(to, opponent, occupied ^ 1 << king_square) == 0 {
+                moves.push(self.bitboard_move(king_square, to, king));
+            }
+        }
+        let checkers = bitboards.attackers(king_square, opponent, occupied);
+        if checkers.count_ones() > 1 {
+            return moves;
+        }
+        // in check every other move has to take the checking piece or block it
+        let check_mask = match bitboard::squares(checkers).next() {
+            Some(checker) => bitboard::between(king_square, checker) | 1 << checker,
+            None => !0,
+        };
+        // a pinned piece may only move between the king and the piece pinning it
+        let mut pin_masks = [!0; 64];
+        let enemies = bitboards.player(opponent);
+        let queens = bitboards.pieces(PieceType::Queen, opponent);
+        let snipers = (bitboard::rook_attacks(king_square, enemies)
+            & (bitboards.pieces(PieceType::Rook, opponent) | queens))
+            | (bitboard::bishop_attacks(king_square, enemies)
+                & (bitboards.pieces(PieceType::Bishop, opponent) | queens));
+        for sniper in bitboard::squares(snipers) {
+            let ray = bitboard::between(king_square, sniper);
+            let blockers = ray & occupied;
+            if blockers.count_ones() == 1 && blockers & own != 0 {
+                pin_masks[blockers.trailing_zeros() as usize] = ray | 1 << sniper;
+            }
+        }
+
+        for from in bitboard::squares(own & !(1 << king_square)) {
+            let piece = match self.board.get_piece_at_pos(&bitboard::pos_of(from)) {
+                Some(piece) => piece,
+                None => continue,
+            };
+            let allowed = check_mask & pin_masks[from as usize];
+            let attacks = match piece.piece_kind {
+                PieceType::Pawn => {
+                    self.push_bitboard_pawn_moves(&mut moves, from, piece, allowed, king_square);
+                    continue;
                 }
+                PieceType::Knight => bitboard::knight_attacks(from),
+                PieceType::Bishop => bitboard::bishop_attacks(from, occupied),
+                PieceType::Rook => bitboard::rook_attacks(from, occupied),
+                PieceType::Queen => bitboard::queen_attacks(from, occupied),
+                PieceType::King => bitboard::king_attacks(from),
+            };
+            for to in bitboard::squares(attacks & !own & allowed) {
+                moves.push(self.bitboard_move(from, to, piece));
             }
         }
+
+        if checkers == 0 {
+            self.push_castle_moves(&mut moves, bitboard::pos_of(king_square), king);
+        }
         moves
     }
+    /// a normal move or a capture of whatever stands on the target square
+    fn bitboard_move(&self, from: u32, to: u32, piece: Piece) -> ChessMove {
+        let to_pos = bitboard::pos_of(to);
+        let move_kind = match self.board.get_piece_at_pos(&to_pos) {
+            Some(taken_piece) => ChessMoveKind::Take(taken_piece.piece_kind),
+            None => ChessMoveKind::Normal,
+        };
+        ChessMove::new(
+            move_kind,
+            ChessMoveVector::new(bitboard::pos_of(from), to_pos),
+            piece,
+        )
+    }
+    fn push_bitboard_pawn_moves(
+        &self,
+        moves: &mut Vec<ChessMove>,
+        from: u32,
+        piece: Piece,
+        allowed: bitboard::BitBoard,
+        king_square: u32,
+    ) {
+        let bitboards = self.board.bitboards();
+        let occupied = bitboards.occupied();
+        let (step, start_rank): (i32, u32) = match piece.player_kind {
+            PlayerType::White => (8, 1),
+            PlayerType::Black => (-8, 6),
+        };
+        // a pawn on the last rank can only be there from an odd fen and has nowhere to go
+        let Ok(single) = u32::try_from(from as i32 + step) else {
+            return;
+        };
+        if single < 64 && occupied & 1 << single == 0 {
+            if allowed & 1 << single != 0 {
+                push_pawn_move(moves, self.bitboard_move(from, single, piece));
+            }
+            let double = (single as i32 + step) as u32;
+            if from / 8 == start_rank && occupied & 1 << double == 0 && allowed & 1 << double != 0 {
+                moves.push(self.bitboard_move(from, double, piece));
+            }
+        }
+        let attacks = bitboard::pawn_attacks(piece.player_kind, from);
+        let enemies = bitboards.player(piece.player_kind.opponent());
+        for to in bitboard::squares(attacks & enemies & allowed) {
+            push_pawn_move(moves, self.bitboard_move(from, to, piece));
+        }
+        if let Some(target_pos) = self.en_passant_target_square {
+            let target = bitboard::square_of(&target_pos);
+            if attacks & 1 << target == 0 {
+                return;
+            }
+            // the taken pawn is beside the moving one , not on the target square
+            let taken_pos = ChessPosition {
+                file: target_pos.file,
+                rank: (from / 8) as i32,
+            };
+            let taken = bitboard::square_of(&taken_pos);
+            // two pawns leave the rank at once , so pins are checked with the board after the move
+            let occupied_after = (occupied ^ 1 << from ^ 1 << taken) | 1 << target;
+            let checkers =
+                bitboards.attackers(king_square, piece.player_kind.opponent(), occupied_after)
+                    & !(1 << taken);
+            if checkers == 0 {
+                moves.push(ChessMove::new(
+                    ChessMoveKind::EnPassant(taken_pos),
+                    ChessMoveVector::new(bitboard::pos_of(from), target_pos),
+                    piece,
+                ));
+            }
+        }
+    }
+    /// castling on either side , with the king and rook starting from any file as in chess960
+    fn push_castle_moves(&self, moves: &mut Vec<ChessMove>, king_pos: ChessPosition, king: Piece) {
+        let bitboards = self.board.bitboards();
+        let occupied = bitboards.occupied();
+        let opponent = king.player_kind.opponent();
+        let king_square = bitboard::square_of(&king_pos);
+        // the king can not castle out of , through or into check
+        if bitboards.attackers(king_square, opponent, occupied) != 0 {
+            return;
+        }
+        let span = |from: u32, to: u32| bitboard::between(from, to) | 1 << from | 1 << to;
+        for castle_type in [CastleType::Short, CastleType::Long] {
+            if !self.castling_availability[castling_index(castle_type, king.player_kind)] {
+                continue;
+            }
+            let rook_pos = self.castling_rook_square(castle_type, king.player_kind);
+            if rook_pos.rank != king_pos.rank {
+                continue;
+            }
+            let rook_square = bitboard::square_of(&rook_pos);
+            let (king_to, rook_to) = castle_destinations(castle_type, king_pos.rank);
+            let (king_to, rook_to) = (bitboard::square_of(&king_to), bitboard::square_of(&rook_to));
+            // every square the king or rook passes or lands on has to be empty but for the two
+            let path = span(king_square, king_to) | span(rook_square, rook_to);
+            if path & occupied & !(1 << king_square | 1 << rook_square) != 0 {
+                continue;
+            }
+            let passed = span(king_square, king_to) & !(1 << king_square | 1 << king_to);
+            if bitboard::squares(passed)
+                .any(|square| bitboards.attackers(square, opponent, occupied) != 0)
+            {
+                continue;
+            }
+            // the rook leaving can open a line to where the king lands
+            let occupied_after =
+                occupied & !(1 << king_square | 1 << rook_square) | 1 << king_to | 1 << rook_to;
+            if bitboards.attackers(king_to, opponent, occupied_after) != 0 {
+                continue;
+            }
+            let to_pos = if self.chess960 {
+                rook_pos
+            } else {
+                bitboard::pos_of(king_to)
+            };
+            moves.push(ChessMove::new(
+                ChessMoveKind::Castle(castle_type),
+                ChessMoveVector::new(king_pos, to_pos),
+                king,
+            ));
+        }
+    }
     pub fn generate_pseudo_legal_moves_for_pos(&self, pos: &ChessPosition) -> Vec<ChessMove> {
         let piece_option = self.board.get_piece_at_pos(pos);
         if piece_option.is_some_and(|f| f.player_kind != self.active_color) {
@@ -291,11 +806,14 @@ impl GameState {
                     let mut moves = vec![];
                     if let Some(final_pos) = ChessPosition::new(pos.file, pos.rank - 1) {
                         if self.board.get_piece_at_pos(&final_pos).is_none() {
-                            moves.push(ChessMove::new(
-                                ChessMoveKind::Normal,
-                                ChessMoveVector::new(*pos, final_pos),
-                                piece,
-                            ));
+                            push_pawn_move(
+                                &mut moves,
+                                ChessMove::new(
+                                    ChessMoveKind::Normal,
+                                    ChessMoveVector::new(*pos, final_pos),
+                                    piece,
+                                ),
+                            );
                             if let Some(final_pos) = ChessPosition::new(pos.file, pos.rank - 2) {
                                 if self.board.get_piece_at_pos(&final_pos).is_none()
                                     && pos.rank == 6
@@ -313,22 +831,26 @@ impl GameState {
                     [(-1, -1), (1, -1)]
                         .iter()
                         .filter_map(|(file, rank)| {
-                            Some(ChessPosition::new(pos.file + file, pos.rank + rank)?)
+                            ChessPosition::new(pos.file + file, pos.rank + rank)
                         })
                         .filter_map(|final_pos| {
-                            if self
+                            if let Some(taken_piece) = self
                                 .board
                                 .get_piece_at_pos(&final_pos)
-                                .is_some_and(|x| x.player_kind != self.active_color)
+                                .filter(|x| x.player_kind != self.active_color)
                             {
                                 Some(ChessMove::new(
-                                    ChessMoveKind::Take(piece.piece_kind),
+                                    ChessMoveKind::Take(taken_piece.piece_kind),
                                     ChessMoveVector::new(*pos, final_pos),
                                     piece,
                                 ))
                             } else if final_pos == self.en_passant_target_square? {
+                                // the taken pawn is beside the moving one , not on the target square
                                 Some(ChessMove::new(
-                                    ChessMoveKind::EnPassant(self.en_passant_target_square?),
+                                    ChessMoveKind::EnPassant(ChessPosition::new(
+                                        final_pos.file,
+                                        pos.rank,
+                                    )?),
                                     ChessMoveVector::new(*pos, final_pos),
                                     piece,
                                 ))
@@ -336,18 +858,21 @@ impl GameState {
                                 None
                             }
                         })
-                        .for_each(|x| moves.push(x));
+                        .for_each(|x| push_pawn_move(&mut moves, x));
                     moves
                 }
                 (PieceType::Pawn, PlayerType::White) => {
                     let mut moves = vec![];
                     if let Some(final_pos) = ChessPosition::new(pos.file, pos.rank + 1) {
                         if self.board.get_piece_at_pos(&final_pos).is_none() {
-                            moves.push(ChessMove::new(
-                                ChessMoveKind::Normal,
-                                ChessMoveVector::new(*pos, final_pos),
-                                piece,
-                            ));
+                            push_pawn_move(
+                                &mut moves,
+                                ChessMove::new(
+                                    ChessMoveKind::Normal,
+                                    ChessMoveVector::new(*pos, final_pos),
+                                    piece,
+                                ),
+                            );
                             if let Some(final_pos) = ChessPosition::new(pos.file, pos.rank + 2) {
                                 if self.board.get_piece_at_pos(&final_pos).is_none()
                                     && pos.rank == 1
@@ -366,22 +891,26 @@ impl GameState {
                     [(-1, 1), (1, 1)]
                         .iter()
                         .filter_map(|(file, rank)| {
-                            Some(ChessPosition::new(pos.file + file, pos.rank + rank)?)
+                            ChessPosition::new(pos.file + file, pos.rank + rank)
                         })
                         .filter_map(|final_pos| {
-                            if self
+                            if let Some(taken_piece) = self
                                 .board
                                 .get_piece_at_pos(&final_pos)
-                                .is_some_and(|x| x.player_kind != self.active_color)
+                                .filter(|x| x.player_kind != self.active_color)
                             {
                                 Some(ChessMove::new(
-                                    ChessMoveKind::Take(piece.piece_kind),
+                                    ChessMoveKind::Take(taken_piece.piece_kind),
                                     ChessMoveVector::new(*pos, final_pos),
                                     piece,
                                 ))
                             } else if final_pos == self.en_passant_target_square? {
+                                // the taken pawn is beside the moving one , not on the target square
                                 Some(ChessMove::new(
-                                    ChessMoveKind::EnPassant(self.en_passant_target_square?),
+                                    ChessMoveKind::EnPassant(ChessPosition::new(
+                                        final_pos.file,
+                                        pos.rank,
+                                    )?),
                                     ChessMoveVector::new(*pos, final_pos),
                                     piece,
                                 ))
@@ -389,7 +918,7 @@ impl GameState {
                                 None
                             }
                         })
-                        .for_each(|x| moves.push(x));
+                        .for_each(|x| push_pawn_move(&mut moves, x));
                     moves
                 }
                 (PieceType::Rook, _) => self.get_pseudo_legal_moves_from_offsets(
@@ -398,106 +927,22 @@ impl GameState {
                     &piece,
                     8,
                 ),
-                (PieceType::Knight, _) => self.get_pseudo_legal_moves_from_offsets(
-                    pos,
-                    &[
-                        (1, 2),
-                        (2, 1),
-                        (2, -1),
-                        (1, -2),
-                        (-1, -2),
-                        (-2, -1),
-                        (-2, 1),
-                        (-1, 2),
-                    ],
-                    &piece,
-                    1,
-                ),
+                (PieceType::Knight, _) => {
+                    self.get_pseudo_legal_moves_from_offsets(pos, &KNIGHT_OFFSETS, &piece, 1)
+                }
                 (PieceType::Bishop, _) => self.get_pseudo_legal_moves_from_offsets(
                     pos,
                     &[(1, 1), (1, -1), (-1, -1), (-1, 1)],
                     &piece,
                     8,
                 ),
-                (PieceType::Queen, _) => self.get_pseudo_legal_moves_from_offsets(
-                    pos,
-                    &[
-                        (1, 0),
-                        (0, -1),
-                        (-1, 0),
-                        (0, 1),
-                        (1, 1),
-                        (1, -1),
-                        (-1, -1),
-                        (-1, 1),
-                    ],
-                    &piece,
-                    8,
-                ),
+                (PieceType::Queen, _) => {
+                    self.get_pseudo_legal_moves_from_offsets(pos, &KING_OFFSETS, &piece, 8)
+                }
                 (PieceType::King, _) => {
-                    let mut moves = self.get_pseudo_legal_moves_from_offsets(
-                        pos,
-                        &[
-                            (1, 0),
-                            (0, -1),
-                            (-1, 0),
-                            (0, 1),
-                            (1, 1),
-                            (1, -1),
-                            (-1, -1),
-                            (-1, 1),
-                        ],
-                        &piece,
-                        1,
-                    );
-                    match piece.player_kind {
-                        PlayerType::White => {
-                            if self.castling_availability.0
-                                && self.board.0[0][1].is_none()
-                                && self.board.0[0][2].is_none()
-                            {
-                                moves.push(ChessMove::new(
-                                    ChessMoveKind::Castle(CastleType::Short),
-                                    ChessMoveVector::new(*pos, ChessPosition::new(1, 0).unwrap()),
-                                    piece,
-                                ));
-                            }
-                            if self.castling_availability.1
-                                && self.board.0[0][4].is_none()
-                                && self.board.0[0][5].is_none()
-                                && self.board.0[0][6].is_none()
-                            {
-                                moves.push(ChessMove::new(
-                                    ChessMoveKind::Castle(CastleType::Long),
-                                    ChessMoveVector::new(*pos, ChessPosition::new(5, 0).unwrap()),
-                                    piece,
-                                ));
-                            }
-                        }
-                        PlayerType::Black => {
-                            if self.castling_availability.2
-                                && self.board.0[7][1].is_none()
-                                && self.board.0[7][2].is_none()
-                            {
-                                moves.push(ChessMove::new(
-                                    ChessMoveKind::Castle(CastleType::Short),
-                                    ChessMoveVector::new(*pos, ChessPosition::new(1, 7).unwrap()),
-                                    piece,
-                                ));
-                            }
-                            if self.castling_availability.1
-                                && self.board.0[7][4].is_none()
-                                && self.board.0[7][5].is_none()
-                                && self.board.0[7][6].is_none()
-                            {
-                                moves.push(ChessMove::new(
-                                    ChessMoveKind::Castle(CastleType::Long),
-                                    ChessMoveVector::new(*pos, ChessPosition::new(5, 7).unwrap()),
-                                    piece,
-                                ));
-                            }
-                        }
-                    }
+                    let mut moves =
+                        self.get_pseudo_legal_moves_from_offsets(pos, &KING_OFFSETS, &piece, 1);
+                    self.push_castle_moves(&mut moves, *pos, piece);
                     moves
                 }
             },
@@ -523,14 +968,14 @@ impl GameState {
                         moves.push(ChessMove::new(
                             ChessMoveKind::Take(to_piece.piece_kind),
                             ChessMoveVector::new(*loc, to_pos),
-                            piece.clone(),
+                            *piece,
                         ));
                         break;
                     } else {
                         moves.push(ChessMove::new(
                             ChessMoveKind::Normal,
                             ChessMoveVector::new(*loc, to_pos),
-                            piece.clone(),
+                            *piece,
                         ));
                     }
                 } else {
@@ -563,17 +1008,31 @@ impl GameState {
         from_pos: &ChessPosition,
         to_pos: &ChessPosition,
     ) -> Option<ChessMove> {
-        let moves = self.generate_pseudo_legal_moves();
-        for chess_move in moves {
-            if (chess_move.move_vector.from_pos == *from_pos)
-                && (chess_move.move_vector.to_pos == *to_pos)
-            {
-                return Some(chess_move);
-            }
-        }
-        None
+        self.generate_pseudo_legal_moves()
+            .into_iter()
+            .find(|chess_move| {
+                (chess_move.move_vector.from_pos == *from_pos)
+                    && (chess_move.move_vector.to_pos == *to_pos)
+            })
+    }
+    /// same as make_move but picks the promotion move for the given piece type
+    pub fn make_promotion_move(
+        &self,
+        from_pos: &ChessPosition,
+        to_pos: &ChessPosition,
+        piece_type: PieceType,
+    ) -> Option<ChessMove> {
+        self.generate_legal_moves_for_pos(from_pos)
+            .into_iter()
+            .find(|chess_move| {
+                (chess_move.move_vector.to_pos == *to_pos)
+                    && (chess_move.move_kind == ChessMoveKind::Promotion(piece_type))
+            })
     }
     pub fn do_move(&mut self, chess_move: &ChessMove) -> MoveResult {
+        if self.game_end.is_some() {
+            return MoveResult::InvalidMove;
+        }
         // impliment chess checks and making a move that will result in check is not a move
         if chess_move.move_vector.from_pos != chess_move.move_vector.to_pos {
             if (if !self.recurrance {
@@ -581,61 +1040,27 @@ impl GameState {
             } else {
                 self.generate_pseudo_legal_moves_for_pos(&chess_move.move_vector.from_pos)
             })
-            .contains(&chess_move)
+            .contains(chess_move)
             {
-                self.board.perform_move(chess_move);
-                if self.active_color == PlayerType::Black {
-                    self.full_move_no += 1;
-                }
-                if self.active_color == PlayerType::White {
-                    self.half_move_clock += 1;
-                }
-                if chess_move.move_vector.from_pos.file == 0
-                    && chess_move.move_vector.from_pos.rank == 0
-                {
-                    self.castling_availability.0 = false;
-                }
-                if chess_move.move_vector.from_pos.file == 3
-                    && chess_move.move_vector.from_pos.rank == 0
-                {
-                    self.castling_availability.0 = false;
-                    self.castling_availability.1 = false;
-                }
-
-                if chess_move.move_vector.from_pos.file == 7
-                    && chess_move.move_vector.from_pos.rank == 0
-                {
-                    self.castling_availability.1 = false;
-                }
-                if chess_move.move_vector.from_pos.file == 0
-                    && chess_move.move_vector.from_pos.rank == 7
-                {
-                    self.castling_availability.2 = false;
-                }
-                if chess_move.move_vector.from_pos.file == 3
-                    && chess_move.move_vector.from_pos.rank == 7
-                {
-                    self.castling_availability.2 = false;
-                    self.castling_availability.3 = false;
-                }
-
-                if chess_move.move_vector.from_pos.file == 7
-                    && chess_move.move_vector.from_pos.rank == 7
-                {
-                    self.castling_availability.3 = false;
-                }
-                self.active_color = match self.active_color {
-                    PlayerType::Black => PlayerType::White,
-                    PlayerType::White => PlayerType::Black,
+                let notation = if self.recurrance {
+                    String::new()
+                } else {
+                    self.to_san(chess_move)
                 };
-                if self.recurrance == false {
-                    dbg!(self.generate_legal_moves().len());
-                    if self.is_stale_mate() {
-                        println!("Stalemate");
-                        return MoveResult::GameEnd(GameEnd::StaleMate);
-                    } else if self.is_check_mate() {
-                        println!("Checkmate");
-                        return MoveResult::GameEnd(GameEnd::Checkmate);
+                self.apply_move(chess_move);
+                // a new move makes the undone moves unreachable
+                self.redo_moves.clear();
+                if !self.recurrance {
+                    // moving instead of accepting turns the offer down
+                    if self.draw_offer == Some(self.active_color) {
+                        self.draw_offer = None;
+                    }
+                    self.push_history(notation);
+                    self.position_keys.push(self.position_key());
+                    if let Some(game_end) = self.forced_game_end() {
+                        self.game_end = Some(game_end);
+                        self.draw_offer = None;
+                        return MoveResult::GameEnd(game_end);
                     }
                 }
             }
@@ -645,86 +1070,556 @@ impl GameState {
 
         MoveResult::Normal
     }
-    pub fn generate_legal_moves_for_pos(&self, pos: &ChessPosition) -> Vec<ChessMove> {
-        self.generate_pseudo_legal_moves_for_pos(pos)
+    /// performs the move without checking it and records what is needed to undo it ,
+    /// the move history is left alone
+    pub fn apply_move(&mut self, chess_move: &ChessMove) {
+        let captured_piece = match chess_move.move_kind {
+            ChessMoveKind::EnPassant(en_pass_pos) => self.board.get_piece_at_pos(&en_pass_pos),
+            ChessMoveKind::Castle(_) => None,
+            _ => self.board.get_piece_at_pos(&chess_move.move_vector.to_pos),
+        };
+        self.move_records.push(MoveRecord {
+            chess_move: *chess_move,
+            captured_piece,
+            castling_availability: self.castling_availability,
+            en_passant_target_square: self.en_passant_target_square,
+            half_move_clock: self.half_move_clock,
+            full_move_no: self.full_move_no,
+            checks_given: self.checks_given,
+            exploded: [None; 8],
+        });
+        self.board
+            .perform_move(chess_move, self.castling_rook_files);
+        if self.variant == Variant::Atomic && captured_piece.is_some() {
+            self.explode(chess_move.move_vector.to_pos);
+        }
+        if self.active_color == PlayerType::Black {
+            self.full_move_no += 1;
+        }
+        // captures and pawn moves can not be taken back , the fifty move count starts over
+        if chess_move.moved_piece.piece_kind == PieceType::Pawn || captured_piece.is_some() {
+            self.half_move_clock = 0;
+        } else {
+            self.half_move_clock += 1;
+        }
+        // moving the king or a rook , or having a rook taken , loses the castling right
+        let player = chess_move.moved_piece.player_kind;
+        for castle_type in [CastleType::Short, CastleType::Long] {
+            if chess_move.moved_piece.piece_kind == PieceType::King {
+                self.castling_availability[castling_index(castle_type, player)] = false;
+            }
+            for rook_owner in [PlayerType::White, PlayerType::Black] {
+                let rook_pos = self.castling_rook_square(castle_type, rook_owner);
+                if chess_move.move_vector.from_pos == rook_pos
+                    || chess_move.move_vector.to_pos == rook_pos
+                {
+                    self.castling_availability[castling_index(castle_type, rook_owner)] = false;
+                }
+            }
+        }
+        // a pawn moving two squares can be taken en passant on the square it passed
+        self.en_passant_target_square = if chess_move.moved_piece.piece_kind == PieceType::Pawn
+            && chess_move
+                .move_vector
+                .from_pos
+                .abs_rank_diff(&chess_move.move_vector.to_pos)
+                == 2
+        {
+            ChessPosition::new(
+                chess_move.move_vector.from_pos.file,
+                (chess_move.move_vector.from_pos.rank + chess_move.move_vector.to_pos.rank) / 2,
+            )
+        } else {
+            None
+        };
+        self.active_color = match self.active_color {
+            PlayerType::Black => PlayerType::White,
+            PlayerType::White => PlayerType::Black,
+        };
+        if self.variant == Variant::ThreeCheck && self.is_check() {
+            self.checks_given[variant::player_index(player)] += 1;
+        }
+    }
+    /// blows up the capturing piece and every piece but the pawns around the square , the
+    /// pieces are kept in the last move record
+    fn explode(&mut self, center: ChessPosition) {
+        let mut exploded = [None; 8];
+        let around = KING_OFFSETS
             .iter()
-            .filter_map(|chess_move| {
-                let mut new_state: GameState = self.clone();
-                new_state.recurrance = true;
-                new_state.do_move(chess_move);
-                if !new_state.is_prev_in_check() {
-                    Some(chess_move.clone())
-                } else {
-                    None
+            .filter_map(|offset| center.add_offset(offset))
+            .filter_map(|pos| Some((pos, self.board.get_piece_at_pos(&pos)?)))
+            .filter(|(_, piece)| piece.piece_kind != PieceType::Pawn);
+        for (slot, (pos, piece)) in exploded.iter_mut().zip(around) {
+            *slot = Some((pos, piece));
+        }
+        self.board.remove_piece_at_pos(&center);
+        for (pos, piece) in exploded.iter().flatten() {
+            self.board.remove_piece_at_pos(pos);
+            // a king or rook blown up takes its castling rights with it
+            for castle_type in [CastleType::Short, CastleType::Long] {
+                let index = castling_index(castle_type, piece.player_kind);
+                if piece.piece_kind == PieceType::King
+                    || *pos == self.castling_rook_square(castle_type, piece.player_kind)
+                {
+                    self.castling_availability[index] = false;
                 }
+            }
+        }
+        if let Some(record) = self.move_records.last_mut() {
+            record.exploded = exploded;
+        }
+    }
+    fn push_history(&mut self, notation: String) {
+        if let Some(record) = self.move_records.last() {
+            let entry = HistoryEntry::new(
+                record.chess_move,
+                notation,
+                self.to_fen(),
+                record.captured_piece,
+                record.full_move_no,
+            );
+            self.move_history.push(entry);
+        }
+    }
+    /// takes back the last move , returns it if there was one
+    pub fn undo_move(&mut self) -> Option<ChessMove> {
+        let chess_move = self.unapply_move()?;
+        self.move_history.pop();
+        self.position_keys.pop();
+        self.game_end = None;
+        self.draw_offer = None;
+        self.redo_moves.push(chess_move);
+        Some(chess_move)
+    }
+    /// plays the last undone move again
+    pub fn redo_move(&mut self) -> Option<MoveResult> {
+        let chess_move = self.redo_moves.pop()?;
+        // do_move forgets the undone moves , keep the rest of them around
+        let redo_moves = std::mem::take(&mut self.redo_moves);
+        let move_result = self.do_move(&chess_move);
+        self.redo_moves = redo_moves;
+        Some(move_result)
+    }
+    pub fn can_undo(&self) -> bool {
+        !self.move_records.is_empty()
+    }
+    pub fn can_redo(&self) -> bool {
+        !self.redo_moves.is_empty()
+    }
+    /// takes back the last move done with apply_move
+    pub fn unapply_move(&mut self) -> Option<ChessMove> {
+        let record = self.move_records.pop()?;
+        // the capturing piece comes back first so taking the move back finds it where it landed
+        if self.variant == Variant::Atomic && record.captured_piece.is_some() {
+            let landed = match record.chess_move.move_kind {
+                ChessMoveKind::Promotion(piece_type) => {
+                    Piece::new(piece_type, record.chess_move.moved_piece.player_kind)
+                }
+                _ => record.chess_move.moved_piece,
+            };
+            self.board
+                .set_piece_at_pos(&record.chess_move.move_vector.to_pos, landed);
+            for (pos, piece) in record.exploded.iter().flatten() {
+                self.board.set_piece_at_pos(pos, *piece);
+            }
+        }
+        self.board.unperform_move(
+            &record.chess_move,
+            record.captured_piece,
+            self.castling_rook_files,
+        );
+        self.castling_availability = record.castling_availability;
+        self.en_passant_target_square = record.en_passant_target_square;
+        self.half_move_clock = record.half_move_clock;
+        self.full_move_no = record.full_move_no;
+        self.checks_given = record.checks_given;
+        self.active_color = record.chess_move.moved_piece.player_kind;
+        Some(record.chess_move)
+    }
+    /// copy of the position without the history , for trying out moves
+    pub fn scratch_copy(&self) -> GameState {
+        GameState {
+            board: self.board.clone(),
+            active_color: self.active_color,
+            castling_availability: self.castling_availability,
+            castling_rook_files: self.castling_rook_files,
+            chess960: self.chess960,
+            variant: self.variant,
+            checks_given: self.checks_given,
+            en_passant_target_square: self.en_passant_target_square,
+            half_move_clock: self.half_move_clock,
+            full_move_no: self.full_move_no,
+            move_history: MoveHistory::default(),
+            move_records: vec![],
+            redo_moves: vec![],
+            position_keys: vec![],
+            game_end: None,
+            draw_offer: None,
+            recurrance: true,
+        }
+    }
+    /// copy of the position and the keys of the positions before it , for searching the game
+    /// from here , repeating one of those positions is a draw
+    pub fn search_copy(&self) -> GameState {
+        GameState {
+            position_keys: self.position_keys.clone(),
+            ..self.scratch_copy()
+        }
+    }
+    pub fn to_fen(&self) -> String {
+        GameData {
+            table: self.board.table,
+            active_color: self.active_color,
+            castling_availability: self.castling_availability,
+            castling_rook_files: self.castling_rook_files.map(|file| file as usize),
+            en_passant_target_square: self
+                .en_passant_target_square
+                .map(|pos| (pos.file as usize, pos.rank as usize)),
+            halfmove_clock: self.half_move_clock,
+            fullmove_number: self.full_move_no,
+            remaining_checks: (self.variant == Variant::ThreeCheck).then(|| {
+                self.checks_given
+                    .map(|given| CHECKS_TO_WIN.saturating_sub(given))
+            }),
+        }
+        .to_fen()
+    }
+    /// the position reached after the given number of plies , ply 0 is the start position
+    pub fn position_at_ply(&self, ply: usize) -> Option<GameState> {
+        GameState::from_fen_variant(
+            self.move_history.fen_at_ply(ply)?,
+            self.variant,
+            self.chess960,
+        )
+        .ok()
+    }
+    pub fn generate_legal_moves_for_pos(&self, pos: &ChessPosition) -> Vec<ChessMove> {
+        self.generate_legal_moves()
+            .into_iter()
+            .filter(|chess_move| chess_move.move_vector.from_pos == *pos)
+            .collect()
+    }
+    /// number of positions reached by playing every legal move sequence of the given length
+    pub fn perft(&self, depth: u32) -> u64 {
+        self.scratch_copy().perft_inner(depth)
+    }
+    /// perft split by the first move , for finding which move the generator gets wrong
+    pub fn divide(&self, depth: u32) -> Vec<(ChessMove, u64)> {
+        let mut new_state = self.scratch_copy();
+        new_state
+            .generate_legal_moves()
+            .into_iter()
+            .map(|chess_move| {
+                new_state.apply_move(&chess_move);
+                let nodes = new_state.perft_inner(depth.saturating_sub(1));
+                new_state.unapply_move();
+                (chess_move, nodes)
             })
             .collect()
     }
-    pub fn is_prev_in_check(&self) -> bool {
-        let king_pos = self
-            .board
-            .0
+    fn perft_inner(&mut self, depth: u32) -> u64 {
+        if depth == 0 {
+            return 1;
+        }
+        let moves = self.generate_legal_moves();
+        if depth == 1 {
+            return moves.len() as u64;
+        }
+        moves
             .iter()
-            .enumerate()
-            .find_map(|(rank, row)| {
-                row.iter().enumerate().find_map(|(file, piece)| {
-                    if piece.is_some_and(|piece| {
-                        piece.piece_kind == PieceType::King
-                            && piece.player_kind != self.active_color
-                    }) {
-                        Some(ChessPosition::new(file as i32, rank as i32).unwrap())
-                    } else {
-                        None
-                    }
-                })
+            .map(|chess_move| {
+                self.apply_move(chess_move);
+                let nodes = self.perft_inner(depth - 1);
+                self.unapply_move();
+                nodes
             })
-            .unwrap();
-        // dbg!(king_pos);
-        self.generate_pseudo_legal_moves()
-            .iter()
-            .map(|chess_move| chess_move.move_vector.to_pos)
-            .any(|pos| pos == king_pos)
+            .sum()
     }
-    // TODO : Rewrite this so it is always right
-    pub fn is_check(&self) -> bool {
-        let king_pos = self
-            .board
-            .0
-            .iter()
-            .enumerate()
-            .find_map(|(rank, row)| {
-                row.iter().enumerate().find_map(|(file, piece)| {
-                    if piece.is_some_and(|piece| {
-                        piece.piece_kind == PieceType::King
-                            && piece.player_kind == self.active_color
-                    }) {
-                        Some(ChessPosition::new(file as i32, rank as i32).unwrap())
-                    } else {
-                        None
-                    }
-                })
+    pub fn king_pos(&self, player_kind: PlayerType) -> Option<ChessPosition> {
+        bitboard::squares(self.board.bitboards().pieces(PieceType::King, player_kind))
+            .next()
+            .map(bitboard::pos_of)
+    }
+    /// whether any piece of the given player attacks the square
+    pub fn is_square_attacked(&self, pos: &ChessPosition, by_player: PlayerType) -> bool {
+        let bitboards = self.board.bitboards();
+        bitboards.attackers(bitboard::square_of(pos), by_player, bitboards.occupied()) != 0
+    }
+    /// whether the player's king is attacked , never in antichess , nor in atomic while the
+    /// kings stand next to each other since neither can take the other
+    pub fn is_king_attacked(&self, player: PlayerType) -> bool {
+        if !self.variant.has_check() {
+            return false;
+        }
+        let Some(king_pos) = self.king_pos(player) else {
+            return false;
+        };
+        if self.variant == Variant::Atomic
+            && self.king_pos(player.opponent()).is_some_and(|other| {
+                king_pos.abs_file_diff(&other) <= 1 && king_pos.abs_rank_diff(&other) <= 1
             })
-            .unwrap();
-        // dbg!(king_pos);
-        let mut new_state: GameState = self.clone();
-        // TODO : use undo here when made
-        new_state.active_color = match self.active_color {
-            PlayerType::Black => PlayerType::White,
-            PlayerType::White => PlayerType::Black,
+        {
+            return false;
+        }
+        self.is_square_attacked(&king_pos, player.opponent())
+    }
+    /// whether the player who just moved left their king in check
+    pub fn is_prev_in_check(&self) -> bool {
+        self.is_king_attacked(self.active_color.opponent())
+    }
+    /// whether the player to move is in check
+    pub fn is_check(&self) -> bool {
+        self.is_king_attacked(self.active_color)
+    }
+    /// whether the move takes a piece , promotions included
+    pub fn is_capture(&self, chess_move: &ChessMove) -> bool {
+        match chess_move.move_kind {
+            ChessMoveKind::Take(_) | ChessMoveKind::EnPassant(_) => true,
+            ChessMoveKind::Promotion(_) => self
+                .board
+                .get_piece_at_pos(&chess_move.move_vector.to_pos)
+                .is_some(),
+            ChessMoveKind::Normal | ChessMoveKind::Castle(_) => false,
+        }
+    }
+    /// whether the move puts the opponent in check , and whether that check is mate
+    pub fn check_status_after(&self, chess_move: &ChessMove) -> (bool, bool) {
+        let mut new_state = self.scratch_copy();
+        new_state.apply_move(chess_move);
+        let is_check = new_state.is_check();
+        (
+            is_check,
+            is_check && new_state.generate_legal_moves().is_empty(),
+        )
+    }
+    /// the move in Standard Algebraic Notation
+    pub fn to_san(&self, chess_move: &ChessMove) -> String {
+        san::move_to_san(self, chess_move)
+    }
+    /// the legal move described by the SAN text , if there is exactly one
+    pub fn move_from_san(&self, san: &str) -> Option<ChessMove> {
+        san::move_from_san(self, san)
+    }
+    /// zobrist key of the position in the polyglot layout , the en passant square only counts
+    /// when a pawn stands next to the one that moved , in three-check so do the checks given
+    pub fn position_key(&self) -> u64 {
+        let mut key = self.board.piece_key();
+        for (index, available) in self.castling_availability.into_iter().enumerate() {
+            if available {
+                key ^= zobrist::castling(index);
+            }
+        }
+        if let Some(en_passant_square) = self.en_passant_target_square {
+            let bitboards = self.board.bitboards();
+            // like polyglot a pawn next to the one that moved is enough , pins do not matter
+            let capturers = bitboard::pawn_attacks(
+                self.active_color.opponent(),
+                bitboard::square_of(&en_passant_square),
+            ) & bitboards.pieces(PieceType::Pawn, self.active_color);
+            if capturers != 0 {
+                key ^= zobrist::en_passant(&en_passant_square);
+            }
+        }
+        if self.active_color == PlayerType::White {
+            key ^= zobrist::white_to_move();
+        }
+        if self.variant == Variant::ThreeCheck {
+            for player in [PlayerType::White, PlayerType::Black] {
+                key ^= zobrist::checks_given(player, self.checks_given(player));
+            }
+        }
+        key
+    }
+    /// remembers the current position for spotting repetitions , after a move played with
+    /// `apply_move` , `do_move` does this itself
+    pub fn record_position(&mut self) {
+        self.position_keys.push(self.position_key());
+    }
+    /// position_key of every position of the game , the current one last
+    pub fn position_keys(&self) -> &[u64] {
+        &self.position_keys
+    }
+    /// how often the current position has come up in the game , itself included
+    pub fn repetition_count(&self) -> usize {
+        let Some(current_key) = self.position_keys.last() else {
+            return 1;
         };
-        dbg!(&new_state.generate_pseudo_legal_moves().len());
-        new_state
-            .generate_pseudo_legal_moves()
+        // positions before the last capture or pawn move can not come up again ,
+        // and only every second one has the same player to move
+        self.position_keys
             .iter()
-            .map(|chess_move| chess_move.move_vector.to_pos)
-            .any(|pos| pos == king_pos)
+            .rev()
+            .take(self.half_move_clock as usize + 1)
+            .step_by(2)
+            .filter(|key| *key == current_key)
+            .count()
+    }
+    /// neither player has the pieces to checkmate , whatever the other one does
+    pub fn is_insufficient_material(&self) -> bool {
+        let bitboards = self.board.bitboards();
+        let count = |piece_type| {
+            (bitboards.pieces(piece_type, PlayerType::White)
+                | bitboards.pieces(piece_type, PlayerType::Black))
+            .count_ones()
+        };
+        if count(PieceType::Pawn) + count(PieceType::Rook) + count(PieceType::Queen) > 0 {
+            return false;
+        }
+        let (knights, bishops) = (count(PieceType::Knight), count(PieceType::Bishop));
+        if knights + bishops <= 1 {
+            return true;
+        }
+        // bishops that all stand on the same color can never attack the other one
+        let bishop_squares = bitboards.pieces(PieceType::Bishop, PlayerType::White)
+            | bitboards.pieces(PieceType::Bishop, PlayerType::Black);
+        let mut bishop_colors = bitboard::squares(bishop_squares).map(|square| {
+            let pos = bitboard::pos_of(square);
+            (pos.file + pos.rank) % 2
+        });
+        let first_color = bishop_colors.next();
+        knights == 0 && bishop_colors.all(|color| Some(color) == first_color)
+    }
+    /// whether the player could still checkmate with some series of legal moves , used when the
+    /// other player runs out of time
+    pub fn has_mating_material(&self, player: PlayerType) -> bool {
+        let bitboards = self.board.bitboards();
+        let own = |piece_type| bitboards.pieces(piece_type, player).count_ones();
+        let theirs = |piece_type| bitboards.pieces(piece_type, player.opponent()).count_ones();
+        if own(PieceType::Pawn) + own(PieceType::Rook) + own(PieceType::Queen) > 0 {
+            return true;
+        }
+        match (own(PieceType::Knight), own(PieceType::Bishop)) {
+            (0, 0) => false,
+            // a lone knight mates when an enemy piece blocks the king in
+            (1, 0) => bitboards.player(player.opponent()).count_ones() > 1,
+            (0, _) => {
+                // bishops mate when enemy pieces block the king in , or on both colors
+                let bishop_squares = bitboards.pieces(PieceType::Bishop, PlayerType::White)
+                    | bitboards.pieces(PieceType::Bishop, PlayerType::Black);
+                let mut bishop_colors = bitboard::squares(bishop_squares).map(|square| {
+                    let pos = bitboard::pos_of(square);
+                    (pos.file + pos.rank) % 2
+                });
+                let first_color = bishop_colors.next();
+                theirs(PieceType::Pawn)
+                    + theirs(PieceType::Knight)
+                    + theirs(PieceType::Rook)
+                    + theirs(PieceType::Queen)
+                    > 0
+                    || bishop_colors.any(|color| Some(color) != first_color)
+            }
+            _ => true,
+        }
+    }
+    /// a draw the player to move may claim , if there is one
+    pub fn claimable_draw(&self) -> Option<DrawReason> {
+        if self.game_end.is_some() {
+            None
+        } else if self.repetition_count() >= 3 {
+            Some(DrawReason::ThreefoldRepetition)
+        } else if self.half_move_clock >= 100 {
+            Some(DrawReason::FiftyMoveRule)
+        } else {
+            None
+        }
+    }
+    /// ends the game in a draw if one can be claimed
+    pub fn claim_draw(&mut self) -> Option<MoveResult> {
+        let draw_reason = self.claimable_draw()?;
+        self.end_game(GameEnd::Draw(draw_reason))
+    }
+    /// ends the game when the player runs out of time
+    pub fn flag(&mut self, player: PlayerType) -> Option<MoveResult> {
+        self.forfeit(
+            player,
+            WinReason::Timeout,
+            DrawReason::TimeoutVsInsufficientMaterial,
+        )
+    }
+    /// ends the game when the player left it and did not come back , e.g. an Online game
+    pub fn abandon(&mut self, player: PlayerType) -> Option<MoveResult> {
+        self.forfeit(
+            player,
+            WinReason::Abandonment,
+            DrawReason::AbandonmentVsInsufficientMaterial,
+        )
+    }
+    /// the opponent of the player wins , unless they could never have checkmated
+    fn forfeit(
+        &mut self,
+        player: PlayerType,
+        win_reason: WinReason,
+        draw_reason: DrawReason,
+    ) -> Option<MoveResult> {
+        let game_end =
+            if !self.variant.has_material_draws() || self.has_mating_material(player.opponent()) {
+                GameEnd::Win {
+                    winner: player.opponent(),
+                    reason: win_reason,
+                }
+            } else {
+                GameEnd::Draw(draw_reason)
+            };
+        self.end_game(game_end)
+    }
+    pub fn resign(&mut self, player: PlayerType) -> Option<MoveResult> {
+        self.end_game(GameEnd::Win {
+            winner: player.opponent(),
+            reason: WinReason::Resignation,
+        })
+    }
+    /// the offer stands until the other player answers it or moves
+    pub fn offer_draw(&mut self, player: PlayerType) {
+        if !self.is_game_over() {
+            self.draw_offer = Some(player);
+        }
+    }
+    pub fn accept_draw(&mut self) -> Option<MoveResult> {
+        self.draw_offer.take()?;
+        self.end_game(GameEnd::Draw(DrawReason::Agreement))
+    }
+    pub fn decline_draw(&mut self) {
+        self.draw_offer = None;
+    }
+    /// ends the game for a reason found elsewhere , e.g. by a game server , None when the game
+    /// was already over
+    pub fn end_game(&mut self, game_end: GameEnd) -> Option<MoveResult> {
+        if self.is_game_over() {
+            return None;
+        }
+        self.game_end = Some(game_end);
+        self.draw_offer = None;
+        Some(MoveResult::GameEnd(game_end))
+    }
+    /// wins of the variant , checkmate , stalemate and the draws that do not need to be claimed
+    fn forced_game_end(&self) -> Option<GameEnd> {
+        if let Some(game_end) = self.variant.game_end(self) {
+            Some(game_end)
+        } else if self.generate_legal_moves().is_empty() {
+            Some(self.variant.no_moves_end(self))
+        } else if self.repetition_count() >= 5 {
+            Some(GameEnd::Draw(DrawReason::FivefoldRepetition))
+        } else if self.half_move_clock >= 150 {
+            Some(GameEnd::Draw(DrawReason::SeventyFiveMoveRule))
+        } else if self.variant.has_material_draws() && self.is_insufficient_material() {
+            Some(GameEnd::Draw(DrawReason::InsufficientMaterial))
+        } else {
+            None
+        }
+    }
+    /// how the game ended , None while it is still going
+    pub fn game_end(&self) -> Option<GameEnd> {
+        self.game_end.or_else(|| self.forced_game_end())
+    }
+    pub fn is_game_over(&self) -> bool {
+        self.game_end().is_some()
     }
     pub fn is_check_mate(&self) -> bool {
         // dbg!("no moves are ", self.generate_legal_moves().len());
-        (self.generate_legal_moves().len() == 0) && self.is_check()
+        self.generate_legal_moves().is_empty() && self.is_check()
     }
     pub fn is_stale_mate(&self) -> bool {
-        (self.generate_legal_moves().len() == 0) && !self.is_check()
+        self.generate_legal_moves().is_empty() && !self.is_check()
     }
 }
 
@@ -1,5 +1,18 @@
+pub mod binary;
+pub mod bitboard;
+pub mod book;
+pub mod chess960;
 pub mod clock;
-mod fen;
+pub mod engine;
+pub mod fen;
 pub mod game_modes;
 pub mod logic;
 pub mod move_history;
+pub mod pgn;
+pub mod protocol;
+pub mod san;
+pub mod time_control;
+pub mod transposition;
+pub mod uci;
+pub mod variant;
+pub mod zobrist;
@@ -1,6 +1,7 @@
-use super::logic::{ChessPosition, Piece, PieceType, PlayerType};
+use super::logic::{ChessPosition, Piece, PieceType, PlayerType, START_FEN};
 
 use serde::{Deserialize, Serialize};
+use std::time::{SystemTime, UNIX_EPOCH};
 #[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct ChessMoveVector {
     pub from_pos: ChessPosition,
@@ -11,9 +12,10 @@ impl ChessMoveVector {
         Self { from_pos, to_pos }
     }
 }
+/// coordinate notation , e.g. "e2e4"
 impl ToString for ChessMoveVector {
     fn to_string(&self) -> String {
-        "todo".to_string()
+        format!("{}{}", self.from_pos.to_string(), self.to_pos.to_string())
     }
 }
 #[derive(Debug, Copy, Clone, Serialize, Deserialize, PartialEq, Eq)]
@@ -32,9 +34,24 @@ impl ChessMove {
         }
     }
 }
+/// coordinate notation with the promotion piece appended , e.g. "e7e8q"
 impl ToString for ChessMove {
     fn to_string(&self) -> String {
-        "todo".to_string()
+        match self.move_kind {
+            ChessMoveKind::Promotion(piece_type) => format!(
+                "{}{}",
+                self.move_vector.to_string(),
+                match piece_type {
+                    PieceType::Rook => 'r',
+                    PieceType::Knight => 'n',
+                    PieceType::Bishop => 'b',
+                    // antichess lets pawns become kings
+                    PieceType::King => 'k',
+                    _ => 'q',
+                }
+            ),
+            _ => self.move_vector.to_string(),
+        }
     }
 }
 
@@ -53,5 +70,114 @@ pub enum CastleType {
     Short,
     Long,
 }
-#[derive(Debug, Serialize, Deserialize)]
-pub struct MoveHistory {}
+/// a move that was played along with what it led to
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HistoryEntry {
+    pub chess_move: ChessMove,
+    /// how the move is shown in the move list
+    pub notation: String,
+    /// fen of the position after the move
+    pub fen: String,
+    pub captured_piece: Option<Piece>,
+    /// full move number the move was played in
+    pub full_move_no: u32,
+    /// seconds since the unix epoch when the move was played
+    pub timestamp: u64,
+}
+impl HistoryEntry {
+    pub fn new(
+        chess_move: ChessMove,
+        notation: String,
+        fen: String,
+        captured_piece: Option<Piece>,
+        full_move_no: u32,
+    ) -> Self {
+        Self {
+            chess_move,
+            notation,
+            fen,
+            captured_piece,
+            full_move_no,
+            timestamp: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap()
+                .as_secs(),
+        }
+    }
+}
+/// the moves played in a game , starting from start_fen
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MoveHistory {
+    start_fen: String,
+    entries: Vec<HistoryEntry>,
+}
+impl Default for MoveHistory {
+    fn default() -> Self {
+        Self::new(START_FEN.to_string())
+    }
+}
+impl MoveHistory {
+    pub fn new(start_fen: String) -> Self {
+        Self {
+            start_fen,
+            entries: vec![],
+        }
+    }
+    pub fn push(&mut self, entry: HistoryEntry) {
+        self.entries.push(entry);
+    }
+    pub fn pop(&mut self) -> Option<HistoryEntry> {
+        self.entries.pop()
+    }
+    /// number of plies played
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+    pub fn entries(&self) -> &[HistoryEntry] {
+        &self.entries
+    }
+    pub fn start_fen(&self) -> &str {
+        &self.start_fen
+    }
+    /// fen after the given number of plies , ply 0 is the start position
+    pub fn fen_at_ply(&self, ply: usize) -> Option<&str> {
+        match ply {
+            0 => Some(&self.start_fen),
+            _ => self.entries.get(ply - 1).map(|entry| entry.fen.as_str()),
+        }
+    }
+    /// rows of (move number , white move , black move) for the move list ,
+    /// each move comes with the ply reached after playing it
+    pub fn full_moves(&self) -> Vec<FullMove<'_>> {
+        let mut rows: Vec<FullMove<'_>> = vec![];
+        for (i, entry) in self.entries.iter().enumerate() {
+            let ply_entry = Some((i + 1, entry));
+            match entry.chess_move.moved_piece.player_kind {
+                PlayerType::White => rows.push((entry.full_move_no, ply_entry, None)),
+                PlayerType::Black => match rows.last_mut() {
+                    Some(row) if row.0 == entry.full_move_no && row.2.is_none() => {
+                        row.2 = ply_entry;
+                    }
+                    _ => rows.push((entry.full_move_no, None, ply_entry)),
+                },
+            }
+        }
+        rows
+    }
+    /// pieces of the given player that have been captured so far
+    pub fn captured_pieces(&self, player_kind: PlayerType) -> Vec<Piece> {
+        self.entries
+            .iter()
+            .filter_map(|entry| entry.captured_piece)
+            .filter(|piece| piece.player_kind == player_kind)
+            .collect()
+    }
+}
+pub type FullMove<'a> = (
+    u32,
+    Option<(usize, &'a HistoryEntry)>,
+    Option<(usize, &'a HistoryEntry)>,
+);
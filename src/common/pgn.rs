@@ -0,0 +1,377 @@
+use crate::common::logic::{GameState, PlayerType};
+use crate::common::time_control::TimeControl;
+use crate::common::variant::Variant;
+
+/// tags every exported game starts with , in this order
+const SEVEN_TAG_ROSTER: [&str; 7] = ["Event", "Site", "Date", "Round", "White", "Black", "Result"];
+
+const RESULTS: [&str; 4] = ["1-0", "0-1", "1/2-1/2", "*"];
+
+/// a single game of a pgn file , only the main line of the moves is kept
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PgnGame {
+    /// tag pairs in the order they appear
+    pub tags: Vec<(String, String)>,
+    /// moves of the main line in SAN
+    pub moves: Vec<String>,
+    pub result: String,
+}
+
+impl PgnGame {
+    /// builds the pgn of the game played so far in the given state
+    pub fn from_game_state(game_state: &GameState, time_control: Option<&TimeControl>) -> Self {
+        let result = game_result(game_state);
+        let date = match game_state.move_history.entries().first() {
+            Some(entry) => chrono::DateTime::from_timestamp(entry.timestamp as i64, 0)
+                .map(|time| time.with_timezone(&chrono::Local).date_naive()),
+            None => Some(chrono::Local::now().date_naive()),
+        };
+        let mut game = Self::default();
+        game.set_tag("Event", "Local game");
+        game.set_tag("Site", "rusty chess");
+        game.set_tag(
+            "Date",
+            &date.map_or("????.??.??".to_string(), |date| {
+                date.format("%Y.%m.%d").to_string()
+            }),
+        );
+        game.set_tag("Round", "-");
+        game.set_tag("White", "?");
+        game.set_tag("Black", "?");
+        game.set_tag("Result", result);
+        game.set_tag(
+            "TimeControl",
+            &time_control.map_or("-".to_string(), |time_control| time_control.to_string()),
+        );
+        if game_state.variant() != Variant::Standard {
+            game.set_tag("Variant", game_state.variant().name());
+        } else if game_state.is_chess960() {
+            game.set_tag("Variant", "Chess960");
+        }
+        let start_fen = game_state.move_history.start_fen();
+        if start_fen != game_state.variant().start_fen() {
+            game.set_tag("SetUp", "1");
+            game.set_tag("FEN", start_fen);
+        }
+        game.moves = game_state
+            .move_history
+            .entries()
+            .iter()
+            .map(|entry| entry.notation.clone())
+            .collect();
+        game.result = result.to_string();
+        game
+    }
+    pub fn tag(&self, name: &str) -> Option<&str> {
+        self.tags
+            .iter()
+            .find(|(tag_name, _)| tag_name == name)
+            .map(|(_, value)| value.as_str())
+    }
+    pub fn set_tag(&mut self, name: &str, value: &str) {
+        match self.tags.iter_mut().find(|(tag_name, _)| tag_name == name) {
+            Some(tag) => tag.1 = value.to_string(),
+            None => self.tags.push((name.to_string(), value.to_string())),
+        }
+    }
+    /// fen of the position the game starts from
+    pub fn start_fen(&self) -> &str {
+        self.tag("FEN").unwrap_or(self.variant().start_fen())
+    }
+    /// the rules the Variant tag names , standard ones for chess960 or a variant not known
+    pub fn variant(&self) -> Variant {
+        self.tag("Variant")
+            .and_then(Variant::from_name)
+            .unwrap_or_default()
+    }
+    /// whether the Variant tag says the game is chess960
+    pub fn is_chess960(&self) -> bool {
+        self.tag("Variant").is_some_and(|variant| {
+            ["chess960", "chess 960", "fischerandom"].contains(&variant.to_lowercase().as_str())
+        })
+    }
+    /// the position before the first move , None if the FEN tag is broken
+    pub fn start_position(&self) -> Option<GameState> {
+        GameState::from_fen_variant(self.start_fen(), self.variant(), self.is_chess960()).ok()
+    }
+    /// plays the moves from the start position , None if any of them is not legal
+    pub fn replay(&self) -> Option<GameState> {
+        let mut game_state = self.start_position()?;
+        for san in &self.moves {
+            let chess_move = game_state.move_from_san(san)?;
+            game_state.do_move(&chess_move);
+        }
+        Some(game_state)
+    }
+    pub fn to_pgn(&self) -> String {
+        let mut pgn = String::new();
+        // the seven tag roster comes first , in its fixed order
+        for name in SEVEN_TAG_ROSTER {
+            let value = self.tag(name).unwrap_or("?");
+            pgn.push_str(&format!("[{} \"{}\"]\n", name, escape(value)));
+        }
+        for (name, value) in &self.tags {
+            if !SEVEN_TAG_ROSTER.contains(&name.as_str()) {
+                pgn.push_str(&format!("[{} \"{}\"]\n", name, escape(value)));
+            }
+        }
+        pgn.push('\n');
+
+        let (mut move_no, mut player) = start_move_no_and_player(self.start_fen());
+        let mut tokens = vec![];
+        for (i, san) in self.moves.iter().enumerate() {
+            match player {
+                PlayerType::White => tokens.push(format!("{}.", move_no)),
+                PlayerType::Black if i == 0 => tokens.push(format!("{}...", move_no)),
+                PlayerType::Black => {}
+            }
+            tokens.push(san.clone());
+            if player == PlayerType::Black {
+                move_no += 1;
+            }
+            player = match player {
+                PlayerType::White => PlayerType::Black,
+                PlayerType::Black => PlayerType::White,
+            };
+        }
+        tokens.push(if self.result.is_empty() {
+            "*".to_string()
+        } else {
+            self.result.clone()
+        });
+
+        // movetext lines are kept under 80 characters
+        let mut line_len = 0;
+        for token in tokens {
+            if line_len > 0 && line_len + 1 + token.len() > 79 {
+                pgn.push('\n');
+                line_len = 0;
+            } else if line_len > 0 {
+                pgn.push(' ');
+                line_len += 1;
+            }
+            line_len += token.len();
+            pgn.push_str(&token);
+        }
+        pgn.push('\n');
+        pgn
+    }
+}
+
+/// "1-0" , "0-1" , "1/2-1/2" or "*" for a game that is still going
+fn game_result(game_state: &GameState) -> &'static str {
+    match game_state.game_end().map(|game_end| game_end.winner()) {
+        Some(Some(PlayerType::White)) => "1-0",
+        Some(Some(PlayerType::Black)) => "0-1",
+        Some(None) => "1/2-1/2",
+        None => "*",
+    }
+}
+
+fn start_move_no_and_player(fen: &str) -> (u32, PlayerType) {
+    let fields: Vec<&str> = fen.split_ascii_whitespace().collect();
+    let player = match fields.get(1) {
+        Some(&"b") => PlayerType::Black,
+        _ => PlayerType::White,
+    };
+    // the move number is last , three-check fens have the checks left before the clocks
+    let move_no = match fields.len() {
+        6 | 7 => fields.last().and_then(|no| no.parse().ok()).unwrap_or(1),
+        _ => 1,
+    };
+    (move_no, player)
+}
+
+fn escape(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+/// reads every game in the pgn text , comments , NAGs and variations are skipped
+pub fn parse_pgn(text: &str) -> Vec<PgnGame> {
+    let mut games = vec![];
+    let mut game = PgnGame::default();
+    let mut in_movetext = false;
+    // how deep inside variations we are , moves are only kept at depth 0
+    let mut depth = 0;
+    let mut at_line_start = true;
+    let mut chars = text.chars().peekable();
+    while let Some(ch) = chars.next() {
+        let line_start = at_line_start;
+        at_line_start = ch == '\n';
+        match ch {
+            '%' if line_start => {
+                // escaped line
+                for ch in chars.by_ref() {
+                    if ch == '\n' {
+                        at_line_start = true;
+                        break;
+                    }
+                }
+            }
+            '[' if depth == 0 => {
+                if in_movetext {
+                    // a new tag section without a result before it
+                    games.push(std::mem::take(&mut game));
+                    in_movetext = false;
+                }
+                let mut tag = String::new();
+                let mut in_string = false;
+                while let Some(ch) = chars.next() {
+                    match ch {
+                        '\\' if in_string => {
+                            if let Some(escaped) = chars.next() {
+                                tag.push('\\');
+                                tag.push(escaped);
+                            }
+                            continue;
+                        }
+                        '"' => in_string = !in_string,
+                        ']' if !in_string => break,
+                        _ => {}
+                    }
+                    tag.push(ch);
+                }
+                if let Some((name, value)) = parse_tag(&tag) {
+                    game.set_tag(&name, &value);
+                }
+            }
+            '{' => {
+                for ch in chars.by_ref() {
+                    if ch == '}' {
+                        break;
+                    }
+                }
+            }
+            ';' => {
+                for ch in chars.by_ref() {
+                    if ch == '\n' {
+                        at_line_start = true;
+                        break;
+                    }
+                }
+            }
+            '(' => depth += 1,
+            ')' => depth = (depth - 1).max(0),
+            _ if ch.is_whitespace() => {}
+            _ => {
+                let mut token = ch.to_string();
+                while let Some(&next) = chars.peek() {
+                    if next.is_whitespace() || "{}();[".contains(next) {
+                        break;
+                    }
+                    token.push(next);
+                    chars.next();
+                }
+                in_movetext = true;
+                // NAGs , and annotations like "!?" written apart from their move
+                if depth > 0 || token.starts_with('$') || token.chars().all(|ch| "!?".contains(ch))
+                {
+                    continue;
+                }
+                if RESULTS.contains(&token.as_str()) {
+                    game.result = token;
+                    games.push(std::mem::take(&mut game));
+                    in_movetext = false;
+                    continue;
+                }
+                // move numbers like "12." or "12..." , possibly glued to the move , the zeros of
+                // "0-0" are no move number
+                let san = match token.trim_start_matches(|ch: char| ch.is_ascii_digit()) {
+                    rest if rest.starts_with('.') => rest.trim_start_matches('.'),
+                    _ => token.as_str(),
+                };
+                if !san.is_empty() {
+                    game.moves.push(san.to_string());
+                }
+            }
+        }
+    }
+    if in_movetext || !game.tags.is_empty() {
+        games.push(game);
+    }
+    for game in games.iter_mut() {
+        if game.result.is_empty() {
+            game.result = game.tag("Result").unwrap_or("*").to_string();
+        }
+    }
+    games
+}
+
+fn parse_tag(tag: &str) -> Option<(String, String)> {
+    let (name, value) = tag.trim().split_once(char::is_whitespace)?;
+    let value = value.trim().strip_prefix('"')?.strip_suffix('"')?;
+    Some((
+        name.to_string(),
+        value.replace("\\\"", "\"").replace("\\\\", "\\"),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::parse_pgn;
+
+    fn moves(text: &str) -> Vec<String> {
+        parse_pgn(text).remove(0).moves
+    }
+
+    #[test]
+    fn reads_several_games() {
+        let games = parse_pgn(
+            r#"[Event "first"]
+[White "a \"quoted\" name"]
+[Result "1-0"]
+
+1. e4 e5 2. Qh5 Nc6 3. Bc4 Nf6 4. Qxf7# 1-0
+
+[Event "second"]
+[Result "1/2-1/2"]
+
+1. d4 d5 1/2-1/2
+
+1. c4 *
+"#,
+        );
+        assert_eq!(games.len(), 3);
+        assert_eq!(games[0].tag("Event"), Some("first"));
+        assert_eq!(games[0].tag("White"), Some("a \"quoted\" name"));
+        assert_eq!(games[0].moves.len(), 7);
+        assert_eq!(games[0].result, "1-0");
+        assert!(games[0].replay().unwrap().is_game_over());
+        assert_eq!(games[1].moves, ["d4", "d5"]);
+        assert_eq!(games[1].result, "1/2-1/2");
+        assert_eq!(games[2].moves, ["c4"]);
+        assert_eq!(games[2].result, "*");
+    }
+
+    #[test]
+    fn skips_comments_nags_and_variations() {
+        let text = "1. e4 {the king's pawn ; not a comment end} e5 ; to the end of the line 2. Nc3
+2. Nf3 Nc6 $1 3. Bb5 !? (3. Bc4 Bc5 (3... Nf6 {a (nested} 4. Ng5) 4. c3) 3... a6 *";
+        assert_eq!(moves(text), ["e4", "e5", "Nf3", "Nc6", "Bb5", "a6"]);
+    }
+
+    #[test]
+    fn skips_escaped_lines() {
+        let text = "%a line for other programs 1. d4\n1. e4 e5\n% 2. d4\n2. Nf3 *";
+        assert_eq!(moves(text), ["e4", "e5", "Nf3"]);
+    }
+
+    #[test]
+    fn results_end_games_without_tags() {
+        let games = parse_pgn("1. e4 0-1 1. d4 1-0 1. c4");
+        let results: Vec<&str> = games.iter().map(|game| game.result.as_str()).collect();
+        assert_eq!(results, ["0-1", "1-0", "*"]);
+    }
+
+    #[test]
+    fn castling_with_zeros() {
+        let text = "1. e4 e5 2. Nf3 Nc6 3. Bc4 Bc5 4. 0-0 d6 5. d3 Be6 6. Nc3 Qd7 7. a3 0-0-0 *";
+        let game = parse_pgn(text).remove(0);
+        assert_eq!(game.moves[6], "0-0");
+        assert_eq!(game.moves[13], "0-0-0");
+        assert!(game.replay().is_some());
+        // glued to the move number too
+        assert_eq!(moves("1.e4 e5 2.Nf3 Nf6 3.Bc4 Bc5 4.0-0 *")[6], "0-0");
+        assert_eq!(moves("1. e4 e5 1...Nc6 *")[2], "Nc6");
+    }
+}
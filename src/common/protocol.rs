@@ -0,0 +1,735 @@
+//! messages between the game server and its clients
+//!
+//! every message is one `Message` , sent in one of two encodings :
+//!
+//! - JSON , one object per line over TCP or one per WebSocket text frame , readable for
+//!   debugging , e.g. `{"Move":{"ply":0,"uci":"e2e4"}}`
+//! - binary , one frame per message over TCP or one per WebSocket binary frame , the frame is
+//!   `BINARY_MAGIC` , the version of the sender and the length of the payload as varints and
+//!   the payload in the form of `common::binary`
+//!
+//! a client says which version it speaks in its first message , e.g. `Join` , the server
+//! answers with the version both understand , e.g. in `Game` , and sends nothing newer than
+//! that , the server reads the encoding from the first bytes of the connection and answers in
+//! the same one
+//!
+//! versions stay compatible as long as variants and fields are only added at the end , new
+//! fields with a serde default , nothing is renamed , reordered or removed , the same goes for
+//! the enums sent inside messages , e.g. `Variant` and `WinReason` , readers skip messages
+//! they do not know and fields added after their time , binary payloads have no field names
+//! so new fields go at the end of a message and not into the structs it carries
+//!
+//! version 1 had `Join` , `Game` , `Move` , `Clock` , `GameOver` and `Error` in JSON , version 2
+//! added the binary encoding , the versions in `Join` and `Game` , draw offers , resigning ,
+//! chat and reconnecting , version 3 added abandoned games , which older peers are told as
+//! resigned or as drawn by timeout , see `Message::for_version` , version 4 added spectators
+//! and the list of games going on , version 5 added seat tokens , a seat is only taken back
+//! with the token its player was sent in `Game`
+use serde::{Deserialize, Serialize};
+use std::io::{self, BufRead, Read};
+use std::time::Duration;
+
+use crate::common::binary;
+use crate::common::clock::{Clock, ClockState};
+use crate::common::logic::{DrawReason, GameEnd, PlayerType, WinReason};
+use crate::common::variant::Variant;
+use crate::websocket;
+
+/// where `rusty_chess_server` listens unless told otherwise
+pub const DEFAULT_ADDRESS: &str = "127.0.0.1:7878";
+/// the version spoken by this build
+pub const PROTOCOL_VERSION: u32 = 5;
+/// first byte of a binary frame , JSON starts with `{` and WebSocket with `GET`
+pub const BINARY_MAGIC: u8 = 0xc5;
+/// longest binary payload or JSON line taken in , game messages are far shorter
+const MAX_PAYLOAD_LEN: u64 = 1 << 20;
+/// longest chat text passed on
+pub const MAX_CHAT_LEN: usize = 500;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum Encoding {
+    #[default]
+    Binary,
+    Json,
+}
+
+fn first_version() -> u32 {
+    1
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum Message {
+    /// the first message of a client , the first player to join a game id creates it with
+    /// these settings and plays white , the settings of the second player are ignored
+    Join {
+        game_id: String,
+        user_id: String,
+        /// pgn TimeControl value
+        time_control: String,
+        variant: Variant,
+        /// start position , the start position of the variant when None
+        fen: Option<String>,
+        /// protocol version of the client
+        #[serde(default = "first_version")]
+        version: u32,
+        /// to take back the seat of `user_id` , see `Game`
+        #[serde(default)]
+        seat_token: Option<String>,
+    },
+    /// the whole game as the server has it , sent on joining , when the opponent joins and
+    /// after a move of the client was refused
+    Game {
+        color: PlayerType,
+        /// user id of the opponent , None until they join
+        opponent: Option<String>,
+        time_control: String,
+        variant: Variant,
+        start_fen: String,
+        /// every move played , in long algebraic notation
+        moves: Vec<String>,
+        clock: ClockSync,
+        game_end: Option<GameEnd>,
+        /// protocol version used from now on
+        #[serde(default = "first_version")]
+        version: u32,
+        /// the secret of the player's seat , only they are sent it , the seat is taken back
+        /// with it in `Reconnect` or `Join` once their connection is lost
+        #[serde(default)]
+        seat_token: Option<String>,
+    },
+    /// a move in long algebraic notation , `ply` is the number of moves played before it so
+    /// moves that crossed each other are not played twice
+    Move {
+        ply: usize,
+        uci: String,
+    },
+    /// clock sync , the server clock after a move or a fallen flag
+    Clock(ClockSync),
+    GameOver(GameEnd),
+    /// a message was refused , the connection stays open
+    Error(String),
+    /// offers a draw , or takes the offer of the opponent , passed on to the opponent
+    DrawOffer,
+    /// turns the offer of the opponent down , passed on to the opponent
+    DrawDecline,
+    Resign,
+    /// `from` is filled in by the server
+    Chat {
+        from: String,
+        text: String,
+    },
+    /// takes the seat of the user in a game that already exists , instead of `Join` , the
+    /// server answers with `Game`
+    Reconnect {
+        game_id: String,
+        user_id: String,
+        version: u32,
+        /// the one sent in `Game` , without it no seat is given back
+        #[serde(default)]
+        seat_token: Option<String>,
+    },
+    /// watches a game instead of playing it , instead of `Join` , the server answers with
+    /// `Watching` and passes on moves , clocks and the end of the game
+    Watch {
+        game_id: String,
+        version: u32,
+    },
+    /// the game as spectators see it , sent on watching and when a player joins
+    Watching {
+        white: Option<String>,
+        black: Option<String>,
+        time_control: String,
+        variant: Variant,
+        start_fen: String,
+        /// the moves spectators may see yet
+        moves: Vec<String>,
+        clock: ClockSync,
+        game_end: Option<GameEnd>,
+        /// how long after being played moves reach spectators
+        delay_ms: u64,
+        version: u32,
+    },
+    /// asks for the games going on , before joining or watching one
+    ListGames {
+        version: u32,
+    },
+    GameList(Vec<GameSummary>),
+}
+
+/// a game going on , as listed for spectators , the user ids are only names , seats are taken
+/// back with the seat token only their player was sent
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GameSummary {
+    pub game_id: String,
+    pub white: Option<String>,
+    pub black: Option<String>,
+    pub time_control: String,
+    pub variant: Variant,
+    /// moves played so far
+    pub plies: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ClockSync {
+    pub white_ms: u64,
+    pub black_ms: u64,
+    /// whether the time of the player to move is running
+    pub running: bool,
+}
+
+impl ClockSync {
+    pub fn from_clock(clock: &Clock) -> Self {
+        Self {
+            white_ms: clock.white_time.as_millis() as u64,
+            black_ms: clock.black_time.as_millis() as u64,
+            running: clock.state == ClockState::Running,
+        }
+    }
+    /// sets the clock to the times of the server
+    pub fn apply(&self, clock: &mut Clock) {
+        clock.set_times(
+            Duration::from_millis(self.white_ms),
+            Duration::from_millis(self.black_ms),
+            self.running,
+        );
+    }
+}
+
+impl Message {
+    /// the version the message came in with , peers speaking an older one do not get it
+    pub fn since(&self) -> u32 {
+        match self {
+            Message::Join { .. }
+            | Message::Game { .. }
+            | Message::Move { .. }
+            | Message::Clock(_)
+            | Message::GameOver(_)
+            | Message::Error(_) => 1,
+            Message::DrawOffer
+            | Message::DrawDecline
+            | Message::Resign
+            | Message::Chat { .. }
+            | Message::Reconnect { .. } => 2,
+            Message::Watch { .. }
+            | Message::Watching { .. }
+            | Message::ListGames { .. }
+            | Message::GameList(_) => 4,
+        }
+    }
+    /// the message as a peer speaking the version can read it , None when it is too new
+    pub fn for_version(&self, version: u32) -> Option<Message> {
+        if self.since() > version {
+            return None;
+        }
+        let mut message = self.clone();
+        if version < 3 {
+            match &mut message {
+                Message::GameOver(game_end) => *game_end = game_end_before_v3(*game_end),
+                Message::Game { game_end, .. } | Message::Watching { game_end, .. } => {
+                    *game_end = game_end.map(game_end_before_v3)
+                }
+                _ => {}
+            }
+        }
+        Some(message)
+    }
+    /// the message in the encoding , without a newline
+    pub fn encode(&self, encoding: Encoding) -> Vec<u8> {
+        match encoding {
+            Encoding::Json => self.to_json().into_bytes(),
+            Encoding::Binary => self.to_binary(),
+        }
+    }
+    /// None for messages that are broken or too new to be known
+    pub fn decode(bytes: &[u8], encoding: Encoding) -> Option<Self> {
+        match encoding {
+            Encoding::Json => Self::from_json(std::str::from_utf8(bytes).ok()?),
+            Encoding::Binary => Self::from_binary(bytes),
+        }
+    }
+    pub fn to_json(&self) -> String {
+        // every part of a message can be written as json
+        serde_json::to_string(self).unwrap()
+    }
+    pub fn from_json(line: &str) -> Option<Self> {
+        serde_json::from_str(line).ok()
+    }
+    /// the whole frame
+    pub fn to_binary(&self) -> Vec<u8> {
+        // every part of a message has a known length
+        let payload = binary::to_bytes(self).unwrap();
+        let mut frame = vec![BINARY_MAGIC];
+        binary::write_varint(&mut frame, PROTOCOL_VERSION as u64);
+        binary::write_varint(&mut frame, payload.len() as u64);
+        frame.extend_from_slice(&payload);
+        frame
+    }
+    /// a whole frame , as `read_binary_frame` gives it
+    pub fn from_binary(frame: &[u8]) -> Option<Self> {
+        let (&magic, rest) = frame.split_first()?;
+        if magic != BINARY_MAGIC {
+            return None;
+        }
+        let (_version, rest) = binary::read_varint(rest)?;
+        let (len, rest) = binary::read_varint(rest)?;
+        let payload = rest.get(..usize::try_from(len).ok()?)?;
+        binary::from_bytes(payload).ok()
+    }
+}
+
+/// abandonment as versions before 3 knew it
+fn game_end_before_v3(game_end: GameEnd) -> GameEnd {
+    match game_end {
+        GameEnd::Win {
+            winner,
+            reason: WinReason::Abandonment,
+        } => GameEnd::Win {
+            winner,
+            reason: WinReason::Resignation,
+        },
+        GameEnd::Draw(DrawReason::AbandonmentVsInsufficientMaterial) => {
+            GameEnd::Draw(DrawReason::TimeoutVsInsufficientMaterial)
+        }
+        game_end => game_end,
+    }
+}
+
+/// how the messages of a connection are framed
+pub enum Framing<'a> {
+    /// JSON lines and binary frames over plain TCP
+    Plain,
+    /// WebSocket frames , `pong` answers the pings of the other side
+    WebSocket {
+        role: websocket::Role,
+        pong: &'a mut dyn FnMut(&[u8]) -> io::Result<()>,
+    },
+}
+
+/// the next message of the stream and its encoding , still to be decoded , None at the end ,
+/// WebSocket text frames are JSON , over plain TCP JSON lines and binary frames can be mixed
+pub fn read_message(
+    reader: &mut impl BufRead,
+    framing: &mut Framing<'_>,
+) -> io::Result<Option<(Encoding, Vec<u8>)>> {
+    if let Framing::WebSocket { role, pong } = framing {
+        return Ok(
+            websocket::read_message(reader, *role, *pong)?.map(|data| match data {
+                websocket::Data::Text(text) => (Encoding::Json, text.into_bytes()),
+                websocket::Data::Binary(bytes) => (Encoding::Binary, bytes),
+            }),
+        );
+    }
+    match reader.fill_buf()?.first() {
+        None => Ok(None),
+        Some(&BINARY_MAGIC) => {
+            Ok(read_binary_frame(reader)?.map(|frame| (Encoding::Binary, frame)))
+        }
+        Some(_) => {
+            let mut line = Vec::new();
+            reader
+                .take(MAX_PAYLOAD_LEN + 1)
+                .read_until(b'\n', &mut line)?;
+            if line.len() as u64 > MAX_PAYLOAD_LEN {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "JSON line too long",
+                ));
+            }
+            Ok(Some((Encoding::Json, line)))
+        }
+    }
+}
+
+/// the next binary frame of the stream , None at its end
+pub fn read_binary_frame(reader: &mut impl Read) -> io::Result<Option<Vec<u8>>> {
+    let mut byte = [0u8; 1];
+    match reader.read_exact(&mut byte) {
+        Err(error) if error.kind() == io::ErrorKind::UnexpectedEof => return Ok(None),
+        result => result?,
+    }
+    if byte[0] != BINARY_MAGIC {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "not a binary frame",
+        ));
+    }
+    let mut frame = vec![BINARY_MAGIC];
+    let mut varint = |frame: &mut Vec<u8>| -> io::Result<u64> {
+        let start = frame.len();
+        loop {
+            reader.read_exact(&mut byte)?;
+            frame.push(byte[0]);
+            // a u64 takes at most ten bytes , read_varint refuses longer ones
+            if byte[0] & 0x80 == 0 || frame.len() - start == 10 {
+                break;
+            }
+        }
+        binary::read_varint(&frame[start..])
+            .map(|(n, _)| n)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "bad varint"))
+    };
+    varint(&mut frame)?;
+    let len = varint(&mut frame)?;
+    if len > MAX_PAYLOAD_LEN {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "binary frame too long",
+        ));
+    }
+    let start = frame.len();
+    frame.resize(start + len as usize, 0);
+    reader.read_exact(&mut frame[start..])?;
+    Ok(Some(frame))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn examples() -> Vec<Message> {
+        vec![
+            Message::Join {
+                game_id: "game 1".to_string(),
+                user_id: "alice".to_string(),
+                time_control: "300+2".to_string(),
+                variant: Variant::Atomic,
+                fen: None,
+                version: PROTOCOL_VERSION,
+                seat_token: None,
+            },
+            Message::Move {
+                ply: 0,
+                uci: "e2e4".to_string(),
+            },
+            Message::Clock(ClockSync {
+                white_ms: 299_500,
+                black_ms: 300_000,
+                running: true,
+            }),
+            Message::GameOver(GameEnd::Win {
+                winner: PlayerType::White,
+                reason: WinReason::Timeout,
+            }),
+            Message::Error("not your move".to_string()),
+            Message::DrawOffer,
+            Message::Resign,
+            Message::Chat {
+                from: "bob".to_string(),
+                text: "good \"game\" \\ \n ♞ \u{1f40e} \u{7f}".to_string(),
+            },
+            Message::Reconnect {
+                game_id: "game 1".to_string(),
+                user_id: "bob".to_string(),
+                version: PROTOCOL_VERSION,
+                seat_token: Some("0f1e2d3c4b5a6978".to_string()),
+            },
+        ]
+    }
+
+    #[test]
+    fn messages_round_trip() {
+        for message in examples() {
+            let line = message.to_json();
+            assert!(!line.contains('\n'));
+            assert_eq!(Message::from_json(&line).as_ref(), Some(&message));
+            let frame = message.to_binary();
+            assert_eq!(Message::from_binary(&frame).as_ref(), Some(&message));
+            let read = read_binary_frame(&mut frame.as_slice()).unwrap().unwrap();
+            assert_eq!(read, frame);
+        }
+        assert_eq!(
+            Message::Move {
+                ply: 2,
+                uci: "g1f3".to_string()
+            }
+            .to_json(),
+            r#"{"Move":{"ply":2,"uci":"g1f3"}}"#
+        );
+        assert_eq!(Message::from_json("{\"Move\":{}}"), None);
+    }
+
+    #[test]
+    fn binary_is_compact() {
+        let message = Message::Move {
+            ply: 2,
+            uci: "g1f3".to_string(),
+        };
+        // magic , version , length , variant , ply , the length of the move and its letters
+        assert_eq!(message.to_binary().len(), 10);
+        assert!(message.to_binary().len() * 3 < message.to_json().len());
+    }
+
+    /// `Message` as version 1 had it , which only spoke JSON
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    enum MessageV1 {
+        Join {
+            game_id: String,
+            user_id: String,
+            time_control: String,
+            variant: Variant,
+            fen: Option<String>,
+        },
+        Game {
+            color: PlayerType,
+            opponent: Option<String>,
+            time_control: String,
+            variant: Variant,
+            start_fen: String,
+            moves: Vec<String>,
+            clock: ClockSync,
+            game_end: Option<GameEnd>,
+        },
+        Move {
+            ply: usize,
+            uci: String,
+        },
+        Clock(ClockSync),
+        GameOver(GameEnd),
+        Error(String),
+    }
+
+    /// what a later version could look like , a field added to `Move` and a new message
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    enum MessageLater {
+        // not sent in these tests
+        Join,
+        Game,
+        Move {
+            ply: usize,
+            uci: String,
+            #[serde(default)]
+            think_ms: Option<u64>,
+        },
+        Clock(ClockSync),
+        GameOver(GameEnd),
+        Error(String),
+        DrawOffer,
+        DrawDecline,
+        Resign,
+        Chat {
+            from: String,
+            text: String,
+        },
+        Reconnect,
+        Watch,
+        Watching,
+        ListGames,
+        GameList,
+        Takeback {
+            ply: usize,
+        },
+    }
+
+    fn later_frame(message: &MessageLater) -> Vec<u8> {
+        let payload = binary::to_bytes(message).unwrap();
+        let mut frame = vec![BINARY_MAGIC];
+        binary::write_varint(&mut frame, 9);
+        binary::write_varint(&mut frame, payload.len() as u64);
+        frame.extend_from_slice(&payload);
+        frame
+    }
+
+    #[test]
+    fn version_1_clients_are_understood() {
+        let join = MessageV1::Join {
+            game_id: "game".to_string(),
+            user_id: "alice".to_string(),
+            time_control: "300".to_string(),
+            variant: Variant::Standard,
+            fen: None,
+        };
+        let Some(Message::Join { version, .. }) =
+            Message::from_json(&serde_json::to_string(&join).unwrap())
+        else {
+            panic!("join not read");
+        };
+        assert_eq!(version, 1);
+
+        let game = Message::Game {
+            color: PlayerType::Black,
+            opponent: Some("alice".to_string()),
+            time_control: "300".to_string(),
+            variant: Variant::Standard,
+            start_fen: Variant::Standard.start_fen().to_string(),
+            moves: vec!["e2e4".to_string()],
+            clock: ClockSync {
+                white_ms: 300_000,
+                black_ms: 300_000,
+                running: true,
+            },
+            game_end: None,
+            version: 1,
+            seat_token: Some("0f1e2d3c4b5a6978".to_string()),
+        };
+        // the version and token fields are new to them and left out
+        let old_game: MessageV1 = serde_json::from_str(&game.to_json()).unwrap();
+        assert!(matches!(old_game, MessageV1::Game { moves, .. } if moves == ["e2e4"]));
+        // messages they do not know are not read , the server does not send them anyway
+        assert!(serde_json::from_str::<MessageV1>(&Message::Resign.to_json()).is_err());
+        assert_eq!(Message::Resign.for_version(1), None);
+        let old_move: MessageV1 = serde_json::from_str(&examples()[1].to_json()).unwrap();
+        assert_eq!(
+            old_move,
+            MessageV1::Move {
+                ply: 0,
+                uci: "e2e4".to_string()
+            }
+        );
+    }
+
+    #[test]
+    fn version_4_reconnects_come_without_a_token() {
+        let reconnect = Message::Reconnect {
+            game_id: "game".to_string(),
+            user_id: "alice".to_string(),
+            version: 4,
+            seat_token: None,
+        };
+        // the token is the last field , a version 4 client ends the payload before it
+        let mut payload = binary::to_bytes(&reconnect).unwrap();
+        assert_eq!(payload.pop(), Some(0));
+        let mut frame = vec![BINARY_MAGIC];
+        binary::write_varint(&mut frame, 4);
+        binary::write_varint(&mut frame, payload.len() as u64);
+        frame.extend_from_slice(&payload);
+        assert_eq!(Message::from_binary(&frame), Some(reconnect.clone()));
+        let line = r#"{"Reconnect":{"game_id":"game","user_id":"alice","version":4}}"#;
+        assert_eq!(Message::from_json(line), Some(reconnect));
+    }
+
+    #[test]
+    fn older_peers_are_told_abandonment_as_they_know_it() {
+        let abandoned = Message::GameOver(GameEnd::Win {
+            winner: PlayerType::Black,
+            reason: WinReason::Abandonment,
+        });
+        assert_eq!(
+            abandoned.for_version(2),
+            Some(Message::GameOver(GameEnd::Win {
+                winner: PlayerType::Black,
+                reason: WinReason::Resignation,
+            }))
+        );
+        assert_eq!(
+            abandoned.for_version(PROTOCOL_VERSION).as_ref(),
+            Some(&abandoned)
+        );
+    }
+
+    #[test]
+    fn overlong_messages_are_refused() {
+        let mut line = vec![b'{'; MAX_PAYLOAD_LEN as usize + 1];
+        line.push(b'\n');
+        let error = read_message(&mut line.as_slice(), &mut Framing::Plain).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+        // the longest line allowed still comes through
+        let mut line = vec![b'{'; MAX_PAYLOAD_LEN as usize - 1];
+        line.push(b'\n');
+        let (encoding, bytes) = read_message(&mut line.as_slice(), &mut Framing::Plain)
+            .unwrap()
+            .unwrap();
+        assert_eq!((encoding, bytes.len()), (Encoding::Json, line.len()));
+    }
+
+    #[test]
+    fn broken_binary_frames_are_refused() {
+        let frame = |len: u64, payload: &[u8]| {
+            let mut frame = vec![BINARY_MAGIC, PROTOCOL_VERSION as u8];
+            binary::write_varint(&mut frame, len);
+            frame.extend_from_slice(payload);
+            frame
+        };
+        let kind = |bytes: &[u8]| read_binary_frame(&mut &bytes[..]).unwrap_err().kind();
+        // a length beyond the limit is refused before anything is read or allocated
+        assert_eq!(
+            kind(&frame(MAX_PAYLOAD_LEN + 1, &[])),
+            io::ErrorKind::InvalidData
+        );
+        assert_eq!(kind(&frame(u64::MAX, &[])), io::ErrorKind::InvalidData);
+        // a varint that never ends
+        let mut endless = [BINARY_MAGIC].as_slice().chain(io::repeat(0x80));
+        let error = read_binary_frame(&mut endless).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+        // frames cut short
+        let whole = Message::Move {
+            ply: 0,
+            uci: "e2e4".to_string(),
+        }
+        .to_binary();
+        for len in 1..whole.len() {
+            assert_eq!(kind(&whole[..len]), io::ErrorKind::UnexpectedEof);
+        }
+        // frames read whole whose payload is no message
+        for bytes in [
+            frame(1, &[100]),
+            frame(2, &[0xff, 0x7f]),
+            frame(0, &[]),
+            frame(3, &[2, 0, 9]),
+            frame(3, &[0, 0xff, 0x7f]),
+        ] {
+            let read = read_binary_frame(&mut bytes.as_slice()).unwrap().unwrap();
+            assert_eq!(Message::from_binary(&read), None, "{:?}", bytes);
+        }
+        // a length beyond the frame given
+        assert_eq!(Message::from_binary(&frame(9, &[2, 0])), None);
+    }
+
+    #[test]
+    fn later_versions_are_understood() {
+        let later_move = MessageLater::Move {
+            ply: 4,
+            uci: "b1c3".to_string(),
+            think_ms: Some(1500),
+        };
+        let takeback = MessageLater::Takeback { ply: 3 };
+        let current_move = Message::Move {
+            ply: 4,
+            uci: "b1c3".to_string(),
+        };
+
+        // binary , the unknown message is skipped and the next one is read whole
+        let mut stream = later_frame(&takeback);
+        stream.extend(later_frame(&later_move));
+        let mut reader = stream.as_slice();
+        let mut next = || {
+            let (encoding, bytes) = read_message(&mut reader, &mut Framing::Plain)
+                .unwrap()
+                .unwrap();
+            Message::decode(&bytes, encoding)
+        };
+        assert_eq!(next(), None);
+        assert_eq!(next(), Some(current_move.clone()));
+
+        // json lines , the same
+        let stream = format!(
+            "{}\n{}\n",
+            serde_json::to_string(&takeback).unwrap(),
+            serde_json::to_string(&later_move).unwrap()
+        );
+        let mut reader = stream.as_bytes();
+        let mut next = || {
+            let (encoding, bytes) = read_message(&mut reader, &mut Framing::Plain)
+                .unwrap()
+                .unwrap();
+            Message::decode(&bytes, encoding)
+        };
+        assert_eq!(next(), None);
+        assert_eq!(next(), Some(current_move.clone()));
+
+        // the later version reads ours , the field we do not send takes its default
+        let frame = current_move.to_binary();
+        let payload_start = frame.len() - binary::to_bytes(&current_move).unwrap().len();
+        let read: MessageLater = binary::from_bytes(&frame[payload_start..]).unwrap();
+        let read_json: MessageLater = serde_json::from_str(&current_move.to_json()).unwrap();
+        for read in [read, read_json] {
+            assert_eq!(
+                read,
+                MessageLater::Move {
+                    ply: 4,
+                    uci: "b1c3".to_string(),
+                    think_ms: None
+                }
+            );
+        }
+    }
+}
@@ -0,0 +1,251 @@
+use crate::common::logic::{ChessPosition, GameState, PieceType};
+use crate::common::move_history::{CastleType, ChessMove, ChessMoveKind};
+
+fn piece_letter(piece_type: PieceType) -> Option<char> {
+    match piece_type {
+        PieceType::Pawn => None,
+        PieceType::Rook => Some('R'),
+        PieceType::Knight => Some('N'),
+        PieceType::Bishop => Some('B'),
+        PieceType::Queen => Some('Q'),
+        PieceType::King => Some('K'),
+    }
+}
+
+fn piece_from_letter(letter: char) -> Option<PieceType> {
+    match letter {
+        'R' => Some(PieceType::Rook),
+        'N' => Some(PieceType::Knight),
+        'B' => Some(PieceType::Bishop),
+        'Q' => Some(PieceType::Queen),
+        'K' => Some(PieceType::King),
+        _ => None,
+    }
+}
+
+fn file_letter(pos: &ChessPosition) -> char {
+    (b'h' - pos.file as u8) as char
+}
+
+fn rank_digit(pos: &ChessPosition) -> char {
+    (b'1' + pos.rank as u8) as char
+}
+
+/// Standard Algebraic Notation of a legal move in the given position , e.g. "Nbd2" , "exd8=Q#"
+pub fn move_to_san(game_state: &GameState, chess_move: &ChessMove) -> String {
+    let mut san = match chess_move.move_kind {
+        ChessMoveKind::Castle(CastleType::Short) => "O-O".to_string(),
+        ChessMoveKind::Castle(CastleType::Long) => "O-O-O".to_string(),
+        _ => {
+            let (from_pos, to_pos) = (
+                chess_move.move_vector.from_pos,
+                chess_move.move_vector.to_pos,
+            );
+            let mut san = String::new();
+            match piece_letter(chess_move.moved_piece.piece_kind) {
+                Some(letter) => {
+                    san.push(letter);
+                    // other pieces of the same kind that can go to the same square
+                    let others: Vec<ChessPosition> = game_state
+                        .generate_legal_moves()
+                        .iter()
+                        .filter(|other| {
+                            other.moved_piece == chess_move.moved_piece
+                                && other.move_vector.to_pos == to_pos
+                                && other.move_vector.from_pos != from_pos
+                        })
+                        .map(|other| other.move_vector.from_pos)
+                        .collect();
+                    if !others.is_empty() {
+                        if others.iter().all(|pos| pos.file != from_pos.file) {
+                            san.push(file_letter(&from_pos));
+                        } else if others.iter().all(|pos| pos.rank != from_pos.rank) {
+                            san.push(rank_digit(&from_pos));
+                        } else {
+                            san.push(file_letter(&from_pos));
+                            san.push(rank_digit(&from_pos));
+                        }
+                    }
+                    if game_state.is_capture(chess_move) {
+                        san.push('x');
+                    }
+                }
+                None => {
+                    if game_state.is_capture(chess_move) {
+                        san.push(file_letter(&from_pos));
+                        san.push('x');
+                    }
+                }
+            }
+            san.push_str(&to_pos.to_string());
+            if let ChessMoveKind::Promotion(piece_type) = chess_move.move_kind {
+                san.push('=');
+                san.push(piece_letter(piece_type).unwrap_or('Q'));
+            }
+            san
+        }
+    };
+    match game_state.check_status_after(chess_move) {
+        (true, true) => san.push('#'),
+        (true, false) => san.push('+'),
+        _ => {}
+    }
+    san
+}
+
+/// finds the legal move in the given position that the SAN text describes
+pub fn move_from_san(game_state: &GameState, san: &str) -> Option<ChessMove> {
+    let san = san
+        .trim()
+        .trim_end_matches(|ch| matches!(ch, '+' | '#' | '!' | '?'));
+    let legal_moves = game_state.generate_legal_moves();
+    let castle_type = match san {
+        "O-O" | "0-0" => Some(CastleType::Short),
+        "O-O-O" | "0-0-0" => Some(CastleType::Long),
+        _ => None,
+    };
+    if let Some(castle_type) = castle_type {
+        return legal_moves
+            .into_iter()
+            .find(|chess_move| chess_move.move_kind == ChessMoveKind::Castle(castle_type));
+    }
+
+    let mut chars: Vec<char> = san.chars().filter(|ch| !matches!(ch, 'x' | '-')).collect();
+    let promotion = match chars.last().copied().and_then(piece_from_letter) {
+        Some(piece_type) => {
+            chars.pop();
+            if chars.last() == Some(&'=') {
+                chars.pop();
+            }
+            Some(piece_type)
+        }
+        None => None,
+    };
+    let piece_type = match chars.first().copied().and_then(piece_from_letter) {
+        Some(piece_type) => {
+            chars.remove(0);
+            piece_type
+        }
+        None => PieceType::Pawn,
+    };
+    if chars.len() < 2 {
+        return None;
+    }
+    let to_square: String = chars.split_off(chars.len() - 2).into_iter().collect();
+    // whatever is left is the file and/or rank the piece comes from
+    let (mut from_file, mut from_rank) = (None, None);
+    for ch in chars {
+        match ch {
+            'a'..='h' => from_file = Some(ch),
+            '1'..='8' => from_rank = Some(ch),
+            _ => return None,
+        }
+    }
+
+    let mut candidates = legal_moves.into_iter().filter(|chess_move| {
+        let from_pos = chess_move.move_vector.from_pos;
+        chess_move.moved_piece.piece_kind == piece_type
+            && chess_move.move_vector.to_pos.to_string() == to_square
+            && from_file.map_or(true, |file| file == file_letter(&from_pos))
+            && from_rank.map_or(true, |rank| rank == rank_digit(&from_pos))
+            && match chess_move.move_kind {
+                ChessMoveKind::Promotion(piece_type) => promotion == Some(piece_type),
+                ChessMoveKind::Castle(_) => false,
+                _ => promotion.is_none(),
+            }
+    });
+    let chess_move = candidates.next()?;
+    // ambiguous text does not describe a move
+    if candidates.next().is_some() {
+        return None;
+    }
+    Some(chess_move)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{move_from_san, move_to_san};
+    use crate::common::logic::{GameState, START_FEN};
+
+    /// the SAN of the move that the text parses to
+    fn san(fen: &str, text: &str) -> Option<String> {
+        let game_state = GameState::from_fen(fen).unwrap();
+        let chess_move = move_from_san(&game_state, text)?;
+        Some(move_to_san(&game_state, &chess_move))
+    }
+
+    #[test]
+    fn disambiguates_by_file_rank_or_square() {
+        let knights = "4k3/8/8/8/8/5N2/8/1N2K3 w - - 0 1";
+        assert_eq!(san(knights, "Nbd2").as_deref(), Some("Nbd2"));
+        assert_eq!(san(knights, "Nfd2").as_deref(), Some("Nfd2"));
+        assert_eq!(san(knights, "Nb1d2").as_deref(), Some("Nbd2"));
+        let rooks = "7k/8/8/8/8/4R3/8/K3R3 w - - 0 1";
+        assert_eq!(san(rooks, "R1e2").as_deref(), Some("R1e2"));
+        assert_eq!(san(rooks, "R3e2").as_deref(), Some("R3e2"));
+        let queens = "8/8/1k6/8/4Q2Q/8/8/K6Q w - - 0 1";
+        assert_eq!(san(queens, "Qh4e1").as_deref(), Some("Qh4e1"));
+        assert_eq!(san(queens, "Qh1e1").as_deref(), Some("Q1e1"));
+        assert_eq!(san(queens, "Qe4e1").as_deref(), Some("Qee1"));
+    }
+
+    #[test]
+    fn promotions_checks_and_mates() {
+        let pawn = "7k/1P6/8/8/8/8/8/K7 w - - 0 1";
+        assert_eq!(san(pawn, "b8=Q").as_deref(), Some("b8=Q+"));
+        assert_eq!(san(pawn, "b8Q+").as_deref(), Some("b8=Q+"));
+        assert_eq!(san(pawn, "b8=N").as_deref(), Some("b8=N"));
+        // a promotion has to name its piece
+        assert_eq!(san(pawn, "b8"), None);
+        let back_rank = "6k1/5ppp/8/8/8/8/8/K3R3 w - - 0 1";
+        assert_eq!(san(back_rank, "Re8").as_deref(), Some("Re8#"));
+        assert_eq!(san(back_rank, "Re8#").as_deref(), Some("Re8#"));
+        assert_eq!(san(back_rank, "Re7").as_deref(), Some("Re7"));
+        let capture = "3r3k/4P3/8/8/8/8/8/K7 w - - 0 1";
+        assert_eq!(san(capture, "exd8=Q").as_deref(), Some("exd8=Q+"));
+    }
+
+    #[test]
+    fn castling_in_both_spellings() {
+        let fen = "r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1";
+        for (text, expected) in [
+            ("O-O", "O-O"),
+            ("0-0", "O-O"),
+            ("O-O-O", "O-O-O"),
+            ("0-0-0", "O-O-O"),
+        ] {
+            assert_eq!(san(fen, text).as_deref(), Some(expected), "{}", text);
+        }
+        assert_eq!(san(START_FEN, "O-O"), None);
+    }
+
+    #[test]
+    fn rejects_ambiguous_and_illegal_moves() {
+        assert_eq!(san("4k3/8/8/8/8/5N2/8/1N2K3 w - - 0 1", "Nd2"), None);
+        assert_eq!(san("7k/8/8/8/8/4R3/8/K3R3 w - - 0 1", "Re2"), None);
+        for text in ["e5", "Qh5", "Ke2", "Nc3c4", "exd5", "Zz9", "", "e"] {
+            assert_eq!(san(START_FEN, text), None, "{}", text);
+        }
+    }
+
+    #[test]
+    fn every_legal_move_round_trips() {
+        for fen in [
+            START_FEN,
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+            "8/8/1k6/8/4Q2Q/8/8/K6Q w - - 0 1",
+        ] {
+            let game_state = GameState::from_fen(fen).unwrap();
+            for chess_move in game_state.generate_legal_moves() {
+                let san = move_to_san(&game_state, &chess_move);
+                assert_eq!(
+                    move_from_san(&game_state, &san),
+                    Some(chess_move),
+                    "{}",
+                    san
+                );
+            }
+        }
+    }
+}
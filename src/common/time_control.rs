@@ -0,0 +1,249 @@
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+/// how much time the players get , made of stages that follow each other
+///
+/// written like the pgn TimeControl tag , e.g. `180+2` or `40/5400:1800+30` , with a `d` or
+/// `b` suffix for a simple or Bronstein delay , as in `300d5`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TimeControl {
+    /// never empty , the last stage repeats if it has a move count
+    pub stages: Vec<TimeStage>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TimeStage {
+    /// moves to play in the stage , None for the rest of the game
+    pub moves: Option<u32>,
+    /// added to the clock when the stage starts
+    pub time: Duration,
+    /// added after every move
+    pub increment: Duration,
+    pub delay: Option<Delay>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Delay {
+    /// the clock only starts running once the delay has passed
+    Simple(Duration),
+    /// the time used is given back after the move , up to the delay
+    Bronstein(Duration),
+}
+
+impl TimeControl {
+    /// one stage for the whole game , without increment or delay
+    pub fn sudden_death(time: Duration) -> Self {
+        Self {
+            stages: vec![TimeStage {
+                moves: None,
+                time,
+                increment: Duration::ZERO,
+                delay: None,
+            }],
+        }
+    }
+    /// parses a pgn TimeControl value , None for `?` , `-` , sandclock or anything else
+    pub fn parse(text: &str) -> Option<Self> {
+        let stages = text
+            .trim()
+            .split(':')
+            .map(parse_stage)
+            .collect::<Option<Vec<_>>>()?;
+        // only the last stage may go on for the rest of the game
+        if stages[..stages.len() - 1]
+            .iter()
+            .any(|stage| stage.moves.is_none())
+        {
+            return None;
+        }
+        Some(Self { stages })
+    }
+    /// time on each clock before the first move
+    pub fn initial_time(&self) -> Duration {
+        self.stages[0].time
+    }
+    /// the stage a player is in after making `moves_made` moves
+    pub fn stage(&self, moves_made: u32) -> &TimeStage {
+        &self.stages[self.locate(moves_made).0]
+    }
+    /// time to add once a player has made `moves_made` moves , when a new stage starts there
+    pub fn stage_bonus(&self, moves_made: u32) -> Option<Duration> {
+        match self.locate(moves_made) {
+            (index, 0) if moves_made > 0 => Some(self.stages[index].time),
+            _ => None,
+        }
+    }
+    /// index of the stage and the moves already made in it
+    fn locate(&self, moves_made: u32) -> (usize, u32) {
+        let last = self.stages.len() - 1;
+        let mut remaining = moves_made;
+        for (index, stage) in self.stages.iter().enumerate() {
+            match stage.moves {
+                Some(moves) if index == last => return (index, remaining % moves),
+                Some(moves) if remaining >= moves => remaining -= moves,
+                _ => return (index, remaining),
+            }
+        }
+        unreachable!("the last stage always returns")
+    }
+}
+
+impl ToString for TimeControl {
+    fn to_string(&self) -> String {
+        self.stages
+            .iter()
+            .map(|stage| {
+                let mut text = String::new();
+                if let Some(moves) = stage.moves {
+                    text.push_str(&format!("{}/", moves));
+                }
+                text.push_str(&seconds_to_string(stage.time));
+                if !stage.increment.is_zero() {
+                    text.push_str(&format!("+{}", seconds_to_string(stage.increment)));
+                }
+                match stage.delay {
+                    Some(Delay::Simple(delay)) => {
+                        text.push_str(&format!("d{}", seconds_to_string(delay)))
+                    }
+                    Some(Delay::Bronstein(delay)) => {
+                        text.push_str(&format!("b{}", seconds_to_string(delay)))
+                    }
+                    None => {}
+                }
+                text
+            })
+            .collect::<Vec<_>>()
+            .join(":")
+    }
+}
+
+fn parse_stage(text: &str) -> Option<TimeStage> {
+    let (moves, rest) = match text.split_once('/') {
+        Some((moves, rest)) => (Some(moves.parse::<u32>().ok().filter(|m| *m > 0)?), rest),
+        None => (None, text),
+    };
+    let (rest, delay) = if let Some((rest, delay)) = rest.split_once('d') {
+        (rest, Some(Delay::Simple(parse_seconds(delay)?)))
+    } else if let Some((rest, delay)) = rest.split_once('b') {
+        (rest, Some(Delay::Bronstein(parse_seconds(delay)?)))
+    } else {
+        (rest, None)
+    };
+    let (time, increment) = match rest.split_once('+') {
+        Some((time, increment)) => (parse_seconds(time)?, parse_seconds(increment)?),
+        None => (parse_seconds(rest)?, Duration::ZERO),
+    };
+    Some(TimeStage {
+        moves,
+        time,
+        increment,
+        delay,
+    })
+}
+
+/// whole or fractional seconds
+fn parse_seconds(text: &str) -> Option<Duration> {
+    // f64 would also take things like "inf" or "1e3"
+    if text.is_empty() || !text.chars().all(|c| c.is_ascii_digit() || c == '.') {
+        return None;
+    }
+    Duration::try_from_secs_f64(text.parse().ok()?).ok()
+}
+
+fn seconds_to_string(duration: Duration) -> String {
+    if duration.subsec_millis() == 0 {
+        duration.as_secs().to_string()
+    } else {
+        format!("{}", duration.as_secs_f64())
+    }
+}
+
+/// `h:mm:ss` , `mm:ss` , or `mm:ss.t` under ten seconds
+pub fn format_time(duration: Duration) -> String {
+    let secs = duration.as_secs();
+    if secs >= 3600 {
+        format!("{}:{:02}:{:02}", secs / 3600, secs / 60 % 60, secs % 60)
+    } else if secs >= 10 {
+        format!("{:02}:{:02}", secs / 60, secs % 60)
+    } else {
+        format!("00:{:02}.{}", secs, duration.subsec_millis() / 100)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{format_time, Delay, TimeControl};
+    use std::time::Duration;
+
+    #[test]
+    fn parses_pgn_time_controls() {
+        let blitz = TimeControl::parse("180+2").unwrap();
+        assert_eq!(blitz.stages.len(), 1);
+        assert_eq!(blitz.initial_time(), Duration::from_secs(180));
+        assert_eq!(blitz.stages[0].increment, Duration::from_secs(2));
+
+        let classical = TimeControl::parse("40/5400:1800+30").unwrap();
+        assert_eq!(classical.stages[0].moves, Some(40));
+        assert_eq!(classical.stages[1].moves, None);
+        assert_eq!(classical.stages[1].time, Duration::from_secs(1800));
+        assert_eq!(classical.stages[1].increment, Duration::from_secs(30));
+
+        let delayed = TimeControl::parse("300d5").unwrap();
+        assert_eq!(
+            delayed.stages[0].delay,
+            Some(Delay::Simple(Duration::from_secs(5)))
+        );
+        let bronstein = TimeControl::parse("300b2.5").unwrap();
+        assert_eq!(
+            bronstein.stages[0].delay,
+            Some(Delay::Bronstein(Duration::from_millis(2500)))
+        );
+
+        for text in [
+            "180+2",
+            "40/5400:1800+30",
+            "300d5",
+            "300b2.5",
+            "40/7200:20/3600:900+30",
+        ] {
+            assert_eq!(TimeControl::parse(text).unwrap().to_string(), text);
+        }
+        for text in [
+            "",
+            "?",
+            "-",
+            "*180",
+            "300:40/60",
+            "0/60",
+            "abc",
+            "60+",
+            "inf",
+        ] {
+            assert_eq!(TimeControl::parse(text), None, "{}", text);
+        }
+    }
+
+    #[test]
+    fn stages_follow_each_other() {
+        let classical = TimeControl::parse("40/5400:1800+30").unwrap();
+        assert_eq!(classical.stage(39).moves, Some(40));
+        assert_eq!(classical.stage(40).moves, None);
+        assert_eq!(classical.stage_bonus(39), None);
+        assert_eq!(classical.stage_bonus(40), Some(Duration::from_secs(1800)));
+        assert_eq!(classical.stage_bonus(80), None);
+        // a last stage with a move count starts over
+        let repeating = TimeControl::parse("40/7200").unwrap();
+        assert_eq!(repeating.stage_bonus(0), None);
+        assert_eq!(repeating.stage_bonus(40), Some(Duration::from_secs(7200)));
+        assert_eq!(repeating.stage_bonus(80), Some(Duration::from_secs(7200)));
+    }
+
+    #[test]
+    fn formats_times() {
+        assert_eq!(format_time(Duration::from_secs(3725)), "1:02:05");
+        assert_eq!(format_time(Duration::from_secs(300)), "05:00");
+        assert_eq!(format_time(Duration::from_millis(10_900)), "00:10");
+        assert_eq!(format_time(Duration::from_millis(9_460)), "00:09.4");
+        assert_eq!(format_time(Duration::ZERO), "00:00.0");
+    }
+}
@@ -0,0 +1,137 @@
+use crate::common::move_history::ChessMove;
+
+/// size a table gets unless asked otherwise
+pub const DEFAULT_SIZE_MB: usize = 16;
+
+/// how the stored score relates to the real one
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Bound {
+    Exact,
+    /// the real score is at least this , the search failed high
+    Lower,
+    /// the real score is at most this , no move raised alpha
+    Upper,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TableEntry {
+    /// the full zobrist key , slots are shared by many positions
+    pub key: u64,
+    pub depth: u32,
+    pub score: i32,
+    pub bound: Bound,
+    pub best_move: Option<ChessMove>,
+    /// search the entry was stored in
+    age: u8,
+}
+
+/// search results by position key , one entry per slot
+pub struct TranspositionTable {
+    slots: Vec<Option<TableEntry>>,
+    age: u8,
+}
+
+impl Default for TranspositionTable {
+    fn default() -> Self {
+        Self::new(DEFAULT_SIZE_MB)
+    }
+}
+
+impl TranspositionTable {
+    /// the slot count is rounded down to a power of two that fits in `size_mb`
+    pub fn new(size_mb: usize) -> Self {
+        let slot_size = std::mem::size_of::<Option<TableEntry>>();
+        let max_slots = (size_mb.max(1) * 1024 * 1024 / slot_size).max(1);
+        let slot_count = 1 << max_slots.ilog2();
+        Self {
+            slots: vec![None; slot_count],
+            age: 0,
+        }
+    }
+    /// drops everything stored
+    pub fn resize(&mut self, size_mb: usize) {
+        *self = Self::new(size_mb);
+    }
+    /// forgets all positions , as for `ucinewgame`
+    pub fn clear(&mut self) {
+        self.slots.fill(None);
+        self.age = 0;
+    }
+    /// entries of earlier searches are replaced first
+    pub fn new_search(&mut self) {
+        self.age = self.age.wrapping_add(1);
+    }
+    fn index(&self, key: u64) -> usize {
+        (key as usize) & (self.slots.len() - 1)
+    }
+    pub fn probe(&self, key: u64) -> Option<&TableEntry> {
+        self.slots[self.index(key)]
+            .as_ref()
+            .filter(|entry| entry.key == key)
+    }
+    /// an entry of this search only gives way to one searched at least as deep
+    pub fn store(
+        &mut self,
+        key: u64,
+        depth: u32,
+        score: i32,
+        bound: Bound,
+        best_move: Option<ChessMove>,
+    ) {
+        let index = self.index(key);
+        let age = self.age;
+        if let Some(old) = &self.slots[index] {
+            if old.age == age && old.key != key && old.depth > depth {
+                return;
+            }
+        }
+        // a move from an earlier search of the same position beats none at all
+        let best_move = best_move.or_else(|| {
+            self.slots[index]
+                .filter(|old| old.key == key)
+                .and_then(|old| old.best_move)
+        });
+        self.slots[index] = Some(TableEntry {
+            key,
+            depth,
+            score,
+            bound,
+            best_move,
+            age,
+        });
+    }
+    /// per mille of slots used in the current search , for uci `info hashfull`
+    pub fn hashfull(&self) -> u32 {
+        let sample = self.slots.len().min(1000);
+        let used = self.slots[..sample]
+            .iter()
+            .filter(|slot| slot.is_some_and(|entry| entry.age == self.age))
+            .count();
+        (used * 1000 / sample) as u32
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Bound, TranspositionTable};
+
+    #[test]
+    fn stores_and_replaces_entries() {
+        let mut table = TranspositionTable::new(1);
+        let slot_count = table.slots.len() as u64;
+        assert!(slot_count.is_power_of_two());
+        table.store(5, 4, 30, Bound::Exact, None);
+        assert_eq!(table.probe(5).unwrap().score, 30);
+        // another key in the same slot
+        assert_eq!(table.probe(5 + slot_count), None);
+        table.store(5 + slot_count, 2, 10, Bound::Lower, None);
+        assert_eq!(table.probe(5).unwrap().depth, 4);
+        // a later search may overwrite anything
+        table.new_search();
+        table.store(5 + slot_count, 1, 10, Bound::Upper, None);
+        assert_eq!(table.probe(5), None);
+        assert_eq!(table.probe(5 + slot_count).unwrap().bound, Bound::Upper);
+        table.clear();
+        assert_eq!(table.probe(5 + slot_count), None);
+    }
+}
@@ -0,0 +1,457 @@
+use std::time::Duration;
+
+use crate::common::engine::{self, SearchLimits, SearchResult, MAX_DEPTH};
+use crate::common::logic::{GameState, PlayerType, START_FEN};
+use crate::common::move_history::ChessMove;
+use crate::common::variant::Variant;
+
+/// a line sent by the gui to the engine
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum UciCommand {
+    Uci,
+    Debug(bool),
+    IsReady,
+    SetOption {
+        name: String,
+        value: Option<String>,
+    },
+    UciNewGame,
+    /// None starts from the initial position
+    Position {
+        fen: Option<String>,
+        moves: Vec<String>,
+    },
+    Go(GoParams),
+    Stop,
+    PonderHit,
+    Quit,
+}
+
+/// arguments of `go` , times are in milliseconds
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct GoParams {
+    pub depth: Option<u32>,
+    pub move_time: Option<u64>,
+    pub white_time: Option<u64>,
+    pub black_time: Option<u64>,
+    pub white_increment: Option<u64>,
+    pub black_increment: Option<u64>,
+    pub moves_to_go: Option<u64>,
+    pub infinite: bool,
+}
+
+impl GoParams {
+    /// how long and how deep to search , `overhead` is kept back from the clock for the gui
+    pub fn limits(&self, player_kind: PlayerType, overhead: Duration) -> SearchLimits {
+        let max_depth = self.depth.unwrap_or(MAX_DEPTH);
+        let (time, increment) = match player_kind {
+            PlayerType::White => (self.white_time, self.white_increment),
+            PlayerType::Black => (self.black_time, self.black_increment),
+        };
+        let time_limit = if self.infinite {
+            // only `stop` ends the search
+            Duration::from_secs(60 * 60 * 24)
+        } else if let Some(move_time) = self.move_time {
+            Duration::from_millis(move_time).saturating_sub(overhead)
+        } else if let Some(time) = time {
+            // an even share of what is left , plus most of the increment
+            let share =
+                time / self.moves_to_go.unwrap_or(30).max(1) + increment.unwrap_or(0) * 3 / 4;
+            Duration::from_millis(share.min(time / 2)).saturating_sub(overhead)
+        } else if self.depth.is_some() {
+            Duration::from_secs(60 * 60 * 24)
+        } else {
+            Duration::from_secs(5)
+        };
+        SearchLimits {
+            max_depth,
+            time_limit,
+        }
+    }
+}
+
+/// reads a line of the gui , None for empty lines and unknown commands
+pub fn parse_command(line: &str) -> Option<UciCommand> {
+    let mut tokens = line.split_whitespace();
+    let command = match tokens.next()? {
+        "uci" => UciCommand::Uci,
+        "debug" => UciCommand::Debug(tokens.next() == Some("on")),
+        "isready" => UciCommand::IsReady,
+        "ucinewgame" => UciCommand::UciNewGame,
+        "stop" => UciCommand::Stop,
+        "ponderhit" => UciCommand::PonderHit,
+        "quit" => UciCommand::Quit,
+        "setoption" => {
+            // names and values may contain spaces
+            let rest: Vec<&str> = tokens.collect();
+            let name_start = rest.iter().position(|token| *token == "name")? + 1;
+            let value_start = rest.iter().position(|token| *token == "value");
+            let name = rest[name_start..value_start.unwrap_or(rest.len())].join(" ");
+            let value = value_start.map(|start| rest[start + 1..].join(" "));
+            UciCommand::SetOption { name, value }
+        }
+        "position" => {
+            let rest: Vec<&str> = tokens.collect();
+            let moves_start = rest.iter().position(|token| *token == "moves");
+            let setup = &rest[..moves_start.unwrap_or(rest.len())];
+            let fen = match setup.first() {
+                Some(&"startpos") => None,
+                Some(&"fen") => Some(setup[1..].join(" ")),
+                _ => return None,
+            };
+            let moves = moves_start.map_or(vec![], |start| {
+                rest[start + 1..].iter().map(|m| m.to_string()).collect()
+            });
+            UciCommand::Position { fen, moves }
+        }
+        "go" => {
+            let mut params = GoParams::default();
+            while let Some(token) = tokens.next() {
+                let mut number = || tokens.next().and_then(|value| value.parse::<u64>().ok());
+                match token {
+                    "depth" => params.depth = number().map(|depth| depth as u32),
+                    "movetime" => params.move_time = number(),
+                    "wtime" => params.white_time = number(),
+                    "btime" => params.black_time = number(),
+                    "winc" => params.white_increment = number(),
+                    "binc" => params.black_increment = number(),
+                    "movestogo" => params.moves_to_go = number(),
+                    "infinite" => params.infinite = true,
+                    _ => {}
+                }
+            }
+            UciCommand::Go(params)
+        }
+        _ => return None,
+    };
+    Some(command)
+}
+
+/// the position the gui set up , None if the fen or one of the moves is not legal
+///
+/// with `chess960` castling is written as the king taking its own rook , as `UCI_Chess960` asks ,
+/// `startpos` is the start position of the variant
+pub fn setup_position(
+    fen: Option<&str>,
+    moves: &[String],
+    variant: Variant,
+    chess960: bool,
+) -> Option<GameState> {
+    let fen = fen.unwrap_or(variant.start_fen());
+    let mut game_state = GameState::from_fen_variant(fen, variant, chess960).ok()?;
+    for uci_move in moves {
+        let chess_move = move_from_uci(&game_state, uci_move)?;
+        // the history is not needed , so the move is done without working out its SAN , the
+        // positions are kept to spot repetitions
+        game_state.apply_move(&chess_move);
+        game_state.record_position();
+    }
+    Some(game_state)
+}
+
+/// the legal move in long algebraic notation , e.g. "e2e4" or "e7e8q"
+pub fn move_from_uci(game_state: &GameState, uci_move: &str) -> Option<ChessMove> {
+    game_state
+        .generate_legal_moves()
+        .into_iter()
+        .find(|chess_move| chess_move.to_string() == uci_move)
+}
+
+/// `info` line for a finished search iteration
+pub fn info_line(result: &SearchResult, elapsed: Duration) -> String {
+    let score = match engine::mate_in(result.score) {
+        Some(moves) => format!("mate {}", moves),
+        None => format!("cp {}", result.score),
+    };
+    let millis = elapsed.as_millis().max(1) as u64;
+    let mut line = format!(
+        "info depth {} score {} nodes {} time {} nps {}",
+        result.depth,
+        score,
+        result.nodes,
+        millis,
+        result.nodes * 1000 / millis
+    );
+    if let Some(best_move) = result.best_move {
+        line.push_str(&format!(" pv {}", best_move.to_string()));
+    }
+    line
+}
+
+/// `bestmove` line , "0000" when there is no legal move
+pub fn best_move_line(best_move: Option<ChessMove>) -> String {
+    format!(
+        "bestmove {}",
+        best_move.map_or("0000".to_string(), |chess_move| chess_move.to_string())
+    )
+}
+
+/// `position` command for the game , the start position followed by every move played
+pub fn position_command(game_state: &GameState) -> String {
+    let start_fen = game_state.move_history.start_fen();
+    let mut command = if start_fen == START_FEN {
+        "position startpos".to_string()
+    } else {
+        format!("position fen {}", start_fen)
+    };
+    let entries = game_state.move_history.entries();
+    if !entries.is_empty() {
+        command.push_str(" moves");
+        for entry in entries {
+            command.push(' ');
+            command.push_str(&entry.chess_move.to_string());
+        }
+    }
+    command
+}
+
+/// a line sent by an engine , as far as the app cares
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EngineMessage {
+    Id {
+        name: String,
+    },
+    UciOk,
+    ReadyOk,
+    Info(EngineInfo),
+    BestMove {
+        best_move: String,
+        ponder: Option<String>,
+    },
+}
+
+/// the parts of an `info` line that are shown
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct EngineInfo {
+    pub depth: Option<u32>,
+    pub score: Option<EngineScore>,
+    pub nodes: Option<u64>,
+    /// moves in long algebraic notation , starting in the searched position
+    pub pv: Vec<String>,
+}
+
+/// score for the player to move
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EngineScore {
+    Centipawns(i32),
+    /// moves until mate , negative when the player to move gets mated
+    Mate(i32),
+}
+
+impl EngineScore {
+    /// the same score seen from white's side
+    pub fn for_white(self, active_color: PlayerType) -> Self {
+        match (active_color, self) {
+            (PlayerType::White, _) => self,
+            (PlayerType::Black, Self::Centipawns(cp)) => Self::Centipawns(-cp),
+            (PlayerType::Black, Self::Mate(moves)) => Self::Mate(-moves),
+        }
+    }
+}
+
+/// e.g. "+0.35" or "#-2"
+impl ToString for EngineScore {
+    fn to_string(&self) -> String {
+        match self {
+            Self::Centipawns(cp) => format!("{:+.2}", *cp as f32 / 100.),
+            Self::Mate(moves) => format!("#{}", moves),
+        }
+    }
+}
+
+/// reads a line of an engine , None for lines the app does not use
+pub fn parse_engine_line(line: &str) -> Option<EngineMessage> {
+    let mut tokens = line.split_whitespace();
+    let message = match tokens.next()? {
+        "id" => match tokens.next()? {
+            "name" => EngineMessage::Id {
+                name: tokens.collect::<Vec<_>>().join(" "),
+            },
+            _ => return None,
+        },
+        "uciok" => EngineMessage::UciOk,
+        "readyok" => EngineMessage::ReadyOk,
+        "bestmove" => {
+            let best_move = tokens.next()?.to_string();
+            let ponder = match tokens.next() {
+                Some("ponder") => tokens.next().map(str::to_string),
+                _ => None,
+            };
+            EngineMessage::BestMove { best_move, ponder }
+        }
+        "info" => {
+            let mut info = EngineInfo::default();
+            while let Some(token) = tokens.next() {
+                match token {
+                    "depth" => info.depth = tokens.next().and_then(|depth| depth.parse().ok()),
+                    "nodes" => info.nodes = tokens.next().and_then(|nodes| nodes.parse().ok()),
+                    "score" => {
+                        let kind = tokens.next();
+                        let value = tokens.next().and_then(|value| value.parse().ok());
+                        info.score = match (kind, value) {
+                            (Some("cp"), Some(cp)) => Some(EngineScore::Centipawns(cp)),
+                            (Some("mate"), Some(moves)) => Some(EngineScore::Mate(moves)),
+                            _ => None,
+                        };
+                    }
+                    "pv" => {
+                        info.pv = tokens.by_ref().map(str::to_string).collect();
+                    }
+                    // the rest of the line is free text
+                    "string" => break,
+                    "seldepth" | "time" | "nps" | "multipv" | "currmove" | "currmovenumber"
+                    | "hashfull" | "tbhits" | "sbhits" | "cpuload" => {
+                        tokens.next();
+                    }
+                    _ => {}
+                }
+            }
+            EngineMessage::Info(info)
+        }
+        _ => return None,
+    };
+    Some(message)
+}
+
+/// the principal variation in SAN , cut short at the first move that is not legal
+pub fn pv_to_san(game_state: &GameState, pv: &[String]) -> Vec<String> {
+    let mut game_state = game_state.scratch_copy();
+    let mut sans = vec![];
+    for uci_move in pv {
+        let Some(chess_move) = move_from_uci(&game_state, uci_move) else {
+            break;
+        };
+        sans.push(game_state.to_san(&chess_move));
+        game_state.apply_move(&chess_move);
+    }
+    sans
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn position_commands_are_parsed() {
+        assert_eq!(
+            parse_command("position startpos moves e2e4 e7e5"),
+            Some(UciCommand::Position {
+                fen: None,
+                moves: vec!["e2e4".to_string(), "e7e5".to_string()],
+            })
+        );
+        assert_eq!(
+            parse_command("position fen 8/8/8/8/8/8/8/K6k w - - 0 1"),
+            Some(UciCommand::Position {
+                fen: Some("8/8/8/8/8/8/8/K6k w - - 0 1".to_string()),
+                moves: vec![],
+            })
+        );
+    }
+
+    #[test]
+    fn go_and_setoption_are_parsed() {
+        assert_eq!(
+            parse_command("go wtime 1000 btime 2000 winc 10 binc 20 movestogo 5"),
+            Some(UciCommand::Go(GoParams {
+                white_time: Some(1000),
+                black_time: Some(2000),
+                white_increment: Some(10),
+                black_increment: Some(20),
+                moves_to_go: Some(5),
+                ..GoParams::default()
+            }))
+        );
+        assert_eq!(
+            parse_command("setoption name Move Overhead value 50"),
+            Some(UciCommand::SetOption {
+                name: "Move Overhead".to_string(),
+                value: Some("50".to_string()),
+            })
+        );
+        assert_eq!(parse_command("   "), None);
+    }
+
+    #[test]
+    fn moves_are_played_in_long_algebraic_notation() {
+        let moves: Vec<String> = ["e2e4", "e7e5", "g1f3", "b8c6", "f1c4", "g8f6", "e1g1"]
+            .iter()
+            .map(|m| m.to_string())
+            .collect();
+        let game_state = setup_position(None, &moves, Variant::Standard, false).unwrap();
+        assert!(game_state
+            .to_fen()
+            .starts_with("r1bqkb1r/pppp1ppp/2n2n2/4p3/2B1P3/5N2/PPPP1PPP/RNBQ1RK1 b kq - "));
+        // the start position and the one after each move
+        assert_eq!(game_state.position_keys().len(), moves.len() + 1);
+        assert!(setup_position(None, &["e2e5".to_string()], Variant::Standard, false).is_none());
+    }
+
+    #[test]
+    fn chess960_castling_takes_the_rook() {
+        let fen = "4k3/8/8/8/8/8/8/R5KR w HA - 0 1";
+        // the king stays on g1 , so only the rook square tells the move apart
+        let game_state =
+            setup_position(Some(fen), &["g1h1".to_string()], Variant::Standard, true).unwrap();
+        assert!(game_state
+            .to_fen()
+            .starts_with("4k3/8/8/8/8/8/8/R4RK1 b - - "));
+        // a position that looks like standard chess only castles this way with the option on
+        let fen = "r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1";
+        let game_state =
+            setup_position(Some(fen), &["e1h1".to_string()], Variant::Standard, true).unwrap();
+        assert!(game_state
+            .to_fen()
+            .starts_with("r3k2r/8/8/8/8/8/8/R4RK1 b kq - "));
+        assert!(
+            setup_position(Some(fen), &["e1h1".to_string()], Variant::Standard, false).is_none()
+        );
+    }
+
+    #[test]
+    fn engine_lines_are_parsed() {
+        assert_eq!(
+            parse_engine_line(
+                "info depth 12 seldepth 18 multipv 1 score cp -35 nodes 4096 nps 100 pv e7e5 g1f3"
+            ),
+            Some(EngineMessage::Info(EngineInfo {
+                depth: Some(12),
+                score: Some(EngineScore::Centipawns(-35)),
+                nodes: Some(4096),
+                pv: vec!["e7e5".to_string(), "g1f3".to_string()],
+            }))
+        );
+        assert_eq!(
+            parse_engine_line("bestmove e2e4 ponder e7e5"),
+            Some(EngineMessage::BestMove {
+                best_move: "e2e4".to_string(),
+                ponder: Some("e7e5".to_string()),
+            })
+        );
+        assert_eq!(
+            parse_engine_line("id name Some Engine 1.0"),
+            Some(EngineMessage::Id {
+                name: "Some Engine 1.0".to_string()
+            })
+        );
+        assert_eq!(parse_engine_line("option name Hash type spin"), None);
+    }
+
+    #[test]
+    fn game_is_sent_as_start_position_and_moves() {
+        let mut game_state = GameState::default();
+        assert_eq!(position_command(&game_state), "position startpos");
+        for san in ["e4", "e5", "Nf3"] {
+            let chess_move = game_state.move_from_san(san).unwrap();
+            game_state.do_move(&chess_move);
+        }
+        assert_eq!(
+            position_command(&game_state),
+            "position startpos moves e2e4 e7e5 g1f3"
+        );
+        assert_eq!(
+            pv_to_san(&game_state, &["b8c6".to_string(), "f1b5".to_string()]),
+            vec!["Nc6", "Bb5"]
+        );
+    }
+}
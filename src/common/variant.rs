@@ -0,0 +1,357 @@
+use serde::{Deserialize, Serialize};
+
+use crate::common::logic::{
+    DrawReason, GameEnd, GameState, PieceType, PlayerType, WinReason, PROMOTION_PIECES, START_FEN,
+};
+use crate::common::move_history::{ChessMove, ChessMoveKind};
+
+/// the rule set a game is played with , chess960 is a start position on top of any of them
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+pub enum Variant {
+    #[default]
+    Standard,
+    /// a king reaching one of the four centre squares wins
+    KingOfTheHill,
+    /// giving the third check wins
+    ThreeCheck,
+    /// captures are forced , kings are ordinary pieces and losing everything wins
+    Antichess,
+    /// captures blow up every piece but pawns around the square , blowing up the king wins
+    Atomic,
+}
+
+/// every variant , in the order they are offered
+pub const VARIANTS: [Variant; 5] = [
+    Variant::Standard,
+    Variant::KingOfTheHill,
+    Variant::ThreeCheck,
+    Variant::Antichess,
+    Variant::Atomic,
+];
+
+/// checks that win a three-check game
+pub const CHECKS_TO_WIN: u32 = 3;
+
+/// kings may also be promoted to in antichess
+const ANTICHESS_PROMOTION_PIECES: [PieceType; 5] = [
+    PieceType::Queen,
+    PieceType::Rook,
+    PieceType::Bishop,
+    PieceType::Knight,
+    PieceType::King,
+];
+
+impl Variant {
+    /// name used in the pgn Variant tag
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Standard => "Standard",
+            Self::KingOfTheHill => "King of the Hill",
+            Self::ThreeCheck => "Three-check",
+            Self::Antichess => "Antichess",
+            Self::Atomic => "Atomic",
+        }
+    }
+    /// value of the `UCI_Variant` option engines like Fairy-Stockfish know the variant by
+    pub fn uci_name(self) -> &'static str {
+        match self {
+            Self::Standard => "chess",
+            Self::KingOfTheHill => "kingofthehill",
+            Self::ThreeCheck => "3check",
+            Self::Antichess => "antichess",
+            Self::Atomic => "atomic",
+        }
+    }
+    /// reads a pgn Variant tag , case , spaces and dashes do not matter
+    pub fn from_name(name: &str) -> Option<Self> {
+        let name: String = name
+            .chars()
+            .filter(|ch| !matches!(ch, ' ' | '-' | '_'))
+            .collect::<String>()
+            .to_lowercase();
+        match name.as_str() {
+            "standard" | "chess" | "normal" => Some(Self::Standard),
+            "kingofthehill" | "koth" => Some(Self::KingOfTheHill),
+            "threecheck" | "3check" => Some(Self::ThreeCheck),
+            "antichess" | "giveaway" | "suicide" => Some(Self::Antichess),
+            "atomic" => Some(Self::Atomic),
+            _ => None,
+        }
+    }
+    pub fn start_fen(self) -> &'static str {
+        match self {
+            Self::ThreeCheck => "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 3+3 0 1",
+            Self::Antichess => "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w - - 0 1",
+            Self::Standard | Self::KingOfTheHill | Self::Atomic => START_FEN,
+        }
+    }
+    /// whether each player needs exactly one king , kings can be taken or blown up otherwise
+    pub fn needs_kings(self) -> bool {
+        !matches!(self, Self::Antichess | Self::Atomic)
+    }
+    /// whether kings can be in check at all
+    pub fn has_check(self) -> bool {
+        self != Self::Antichess
+    }
+    pub fn promotion_pieces(self) -> &'static [PieceType] {
+        match self {
+            Self::Antichess => &ANTICHESS_PROMOTION_PIECES,
+            _ => &PROMOTION_PIECES,
+        }
+    }
+    /// the legal moves when the variant changes them , None when the usual ones apply
+    pub fn legal_moves(self, game_state: &GameState) -> Option<Vec<ChessMove>> {
+        match self {
+            Self::Antichess => Some(antichess_moves(game_state)),
+            Self::Atomic => Some(atomic_moves(game_state)),
+            Self::Standard | Self::KingOfTheHill | Self::ThreeCheck => None,
+        }
+    }
+    /// a win the variant adds , looked at after every move before anything else
+    pub fn game_end(self, game_state: &GameState) -> Option<GameEnd> {
+        let mover = game_state.active_color.opponent();
+        let win = |winner, reason| Some(GameEnd::Win { winner, reason });
+        match self {
+            Self::KingOfTheHill => {
+                let king_pos = game_state.king_pos(mover)?;
+                // d4 , e4 , d5 and e5 , files count from the h file
+                if (3..=4).contains(&king_pos.file) && (3..=4).contains(&king_pos.rank) {
+                    win(mover, WinReason::KingOfTheHill)
+                } else {
+                    None
+                }
+            }
+            Self::ThreeCheck if game_state.checks_given(mover) >= CHECKS_TO_WIN => {
+                win(mover, WinReason::ThreeChecks)
+            }
+            Self::Atomic if game_state.king_pos(game_state.active_color).is_none() => {
+                win(mover, WinReason::KingExploded)
+            }
+            _ => None,
+        }
+    }
+    /// how the game ends when the player to move has no legal move
+    pub fn no_moves_end(self, game_state: &GameState) -> GameEnd {
+        if self == Self::Antichess {
+            GameEnd::Win {
+                winner: game_state.active_color,
+                reason: WinReason::NoMovesLeft,
+            }
+        } else if game_state.is_check() {
+            GameEnd::Win {
+                winner: game_state.active_color.opponent(),
+                reason: WinReason::Checkmate,
+            }
+        } else {
+            GameEnd::Draw(DrawReason::Stalemate)
+        }
+    }
+    /// whether the usual draws for insufficient material apply , the other variants can still
+    /// be won with pieces that could never mate
+    pub fn has_material_draws(self) -> bool {
+        self == Self::Standard
+    }
+}
+
+impl ToString for Variant {
+    fn to_string(&self) -> String {
+        self.name().to_string()
+    }
+}
+
+/// no castling , a king can be promoted to , and a capture has to be made if there is one
+fn antichess_moves(game_state: &GameState) -> Vec<ChessMove> {
+    let mut moves = vec![];
+    for chess_move in game_state.generate_pseudo_legal_moves() {
+        match chess_move.move_kind {
+            ChessMoveKind::Castle(_) => continue,
+            ChessMoveKind::Promotion(PieceType::Queen) => moves.push(ChessMove::new(
+                ChessMoveKind::Promotion(PieceType::King),
+                chess_move.move_vector,
+                chess_move.moved_piece,
+            )),
+            _ => {}
+        }
+        moves.push(chess_move);
+    }
+    if moves
+        .iter()
+        .any(|chess_move| game_state.is_capture(chess_move))
+    {
+        moves.retain(|chess_move| game_state.is_capture(chess_move));
+    }
+    moves
+}
+
+/// kings never capture , and a move may not blow up the own king or leave it in check unless
+/// it blows up the other one
+fn atomic_moves(game_state: &GameState) -> Vec<ChessMove> {
+    let player = game_state.active_color;
+    let mut scratch = game_state.scratch_copy();
+    game_state
+        .generate_pseudo_legal_moves()
+        .into_iter()
+        .filter(|chess_move| {
+            !(chess_move.moved_piece.piece_kind == PieceType::King
+                && game_state.is_capture(chess_move))
+        })
+        .filter(|chess_move| {
+            scratch.apply_move(chess_move);
+            let legal = scratch.king_pos(player).is_some()
+                && (scratch.king_pos(player.opponent()).is_none()
+                    || !scratch.is_king_attacked(player));
+            scratch.unapply_move();
+            legal
+        })
+        .collect()
+}
+
+/// index of the player in per player arrays , white first
+pub fn player_index(player: PlayerType) -> usize {
+    match player {
+        PlayerType::White => 0,
+        PlayerType::Black => 1,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Variant, VARIANTS};
+    use crate::common::logic::{GameEnd, GameState, PieceType, PlayerType, WinReason};
+    use crate::common::move_history::ChessMoveKind;
+
+    fn play(variant: Variant, fen: &str, sans: &[&str]) -> GameState {
+        let mut game_state = GameState::from_fen_variant(fen, variant, false).unwrap();
+        for san in sans {
+            let chess_move = game_state
+                .move_from_san(san)
+                .unwrap_or_else(|| panic!("{} is not legal", san));
+            game_state.do_move(&chess_move);
+        }
+        game_state
+    }
+
+    #[test]
+    fn names_round_trip() {
+        for variant in VARIANTS {
+            assert_eq!(Variant::from_name(variant.name()), Some(variant));
+            let game_state =
+                GameState::from_fen_variant(variant.start_fen(), variant, false).unwrap();
+            assert_eq!(game_state.to_fen(), variant.start_fen());
+        }
+        assert_eq!(Variant::from_name("three check"), Some(Variant::ThreeCheck));
+        assert_eq!(Variant::from_name("crazyhouse"), None);
+    }
+
+    #[test]
+    fn king_of_the_hill_is_won_in_the_centre() {
+        let game_state = play(
+            Variant::KingOfTheHill,
+            "4k3/8/8/8/8/8/8/4K3 w - - 0 1",
+            &["Kd2", "Kd7", "Kd3", "Kd6", "Ke4"],
+        );
+        assert_eq!(
+            game_state.game_end(),
+            Some(GameEnd::Win {
+                winner: PlayerType::White,
+                reason: WinReason::KingOfTheHill
+            })
+        );
+    }
+
+    #[test]
+    fn three_checks_win() {
+        let mut game_state = play(
+            Variant::ThreeCheck,
+            "4k3/8/8/8/8/8/8/R3K3 w - - 3+3 0 1",
+            &["Ra8+", "Kd7", "Ra7+"],
+        );
+        assert_eq!(game_state.checks_given(PlayerType::White), 2);
+        assert!(game_state.to_fen().contains(" 1+3 "));
+        for san in ["Kc6", "Ra6+"] {
+            let chess_move = game_state.move_from_san(san).unwrap();
+            game_state.do_move(&chess_move);
+        }
+        assert_eq!(
+            game_state.game_end(),
+            Some(GameEnd::Win {
+                winner: PlayerType::White,
+                reason: WinReason::ThreeChecks
+            })
+        );
+        game_state.undo_move();
+        game_state.undo_move();
+        assert_eq!(game_state.checks_given(PlayerType::White), 2);
+        assert_eq!(game_state.game_end(), None);
+    }
+
+    #[test]
+    fn antichess_captures_are_forced() {
+        let game_state = play(
+            Variant::Antichess,
+            Variant::Antichess.start_fen(),
+            &["e3", "b5"],
+        );
+        let moves = game_state.generate_legal_moves();
+        assert_eq!(moves.len(), 1);
+        assert_eq!(game_state.to_san(&moves[0]), "Bxb5");
+        // kings can be taken and promoted to
+        let game_state =
+            GameState::from_fen_variant("8/P7/8/8/8/8/8/k7 w - - 0 1", Variant::Antichess, false)
+                .unwrap();
+        assert!(game_state
+            .generate_legal_moves()
+            .iter()
+            .any(|m| m.move_kind == ChessMoveKind::Promotion(PieceType::King)));
+        // the player who runs out of pieces wins
+        let game_state = play(
+            Variant::Antichess,
+            "8/8/8/8/8/8/1p6/R7 b - - 0 1",
+            &["bxa1=Q"],
+        );
+        assert_eq!(
+            game_state.game_end(),
+            Some(GameEnd::Win {
+                winner: PlayerType::White,
+                reason: WinReason::NoMovesLeft
+            })
+        );
+    }
+
+    #[test]
+    fn atomic_captures_explode() {
+        let mut game_state = play(
+            Variant::Atomic,
+            Variant::Atomic.start_fen(),
+            &["Nf3", "d5", "Ne5", "e6"],
+        );
+        let before = game_state.to_fen();
+        let chess_move = game_state.move_from_san("Nxf7").unwrap();
+        game_state.do_move(&chess_move);
+        // the knight , the pawn and every piece around f7 but the pawns are gone
+        assert_eq!(
+            game_state.to_fen(),
+            "rnbq3r/ppp3pp/4p3/3p4/8/8/PPPPPPPP/RNBQKB1R b KQ - 0 3"
+        );
+        assert_eq!(
+            game_state.game_end(),
+            Some(GameEnd::Win {
+                winner: PlayerType::White,
+                reason: WinReason::KingExploded
+            })
+        );
+        game_state.undo_move();
+        assert_eq!(game_state.to_fen(), before);
+        // a king may not take , nor blow itself up
+        let game_state =
+            GameState::from_fen_variant("4k3/8/8/8/8/8/3q4/3RK3 w - - 0 1", Variant::Atomic, false)
+                .unwrap();
+        let sans: Vec<String> = game_state
+            .generate_legal_moves()
+            .iter()
+            .map(|m| game_state.to_san(m))
+            .collect();
+        assert!(!sans.contains(&"Kxd2".to_string()));
+        assert!(!sans.contains(&"Rxd2".to_string()));
+    }
+}
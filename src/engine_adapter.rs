@@ -0,0 +1,257 @@
+use std::io::{self, BufRead, BufReader, Write};
+use std::process::{Child, ChildStdin, Command, Stdio};
+use std::sync::mpsc;
+use std::time::Duration;
+
+use crate::common::logic::GameState;
+use crate::common::uci::{self, EngineInfo, EngineMessage};
+use crate::common::variant::Variant;
+
+/// how long an engine gets to answer `uci` and `isready`
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// a UCI engine program running next to the app , its output is read on a thread of its own
+pub struct ExternalEngine {
+    /// from `id name` , the path until the engine tells
+    pub name: String,
+    child: Child,
+    stdin: ChildStdin,
+    messages: mpsc::Receiver<EngineMessage>,
+    /// what `UCI_Chess960` was last set to
+    chess960: bool,
+    /// what `UCI_Variant` was last set to
+    variant: Variant,
+}
+
+/// an engine whose handshake runs on a thread of its own , so the app is not held up by a slow
+/// or wrong program
+pub struct StartingEngine {
+    receiver: mpsc::Receiver<io::Result<ExternalEngine>>,
+}
+
+impl StartingEngine {
+    /// the engine once it has answered , None until then
+    pub fn poll(&self) -> io::Result<Option<ExternalEngine>> {
+        match self.receiver.try_recv() {
+            Ok(result) => result.map(Some),
+            Err(mpsc::TryRecvError::Empty) => Ok(None),
+            Err(mpsc::TryRecvError::Disconnected) => Err(engine_quit()),
+        }
+    }
+}
+
+fn engine_quit() -> io::Error {
+    io::Error::new(io::ErrorKind::BrokenPipe, "the engine quit")
+}
+
+impl ExternalEngine {
+    /// `spawn` without waiting for it
+    pub fn start(path: &str) -> StartingEngine {
+        let (sender, receiver) = mpsc::channel();
+        let path = path.to_string();
+        // an engine nobody waits for any longer is dropped , which quits it
+        let handshake = move || {
+            let _ = sender.send(Self::spawn(&path));
+        };
+        // there are no threads on the web , no program can be started there either
+        #[cfg(not(target_arch = "wasm32"))]
+        std::thread::spawn(handshake);
+        #[cfg(target_arch = "wasm32")]
+        handshake();
+        StartingEngine { receiver }
+    }
+    /// starts the program and waits until it has answered `uci` and `isready`
+    pub fn spawn(path: &str) -> io::Result<Self> {
+        let mut child = Command::new(path)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()?;
+        let stdin = child.stdin.take().unwrap();
+        let stdout = child.stdout.take().unwrap();
+        let (sender, messages) = mpsc::channel();
+        std::thread::spawn(move || {
+            for line in BufReader::new(stdout).lines() {
+                let Ok(line) = line else {
+                    break;
+                };
+                if let Some(message) = uci::parse_engine_line(&line) {
+                    if sender.send(message).is_err() {
+                        break;
+                    }
+                }
+            }
+        });
+        let mut engine = Self {
+            name: path.to_string(),
+            child,
+            stdin,
+            messages,
+            chess960: false,
+            variant: Variant::Standard,
+        };
+        engine.send("uci")?;
+        loop {
+            match engine.messages.recv_timeout(HANDSHAKE_TIMEOUT) {
+                Ok(EngineMessage::Id { name }) => engine.name = name,
+                Ok(EngineMessage::UciOk) => break,
+                Ok(_) => {}
+                Err(_) => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        "the program did not answer uci",
+                    ))
+                }
+            }
+        }
+        engine.wait_ready()?;
+        Ok(engine)
+    }
+    pub fn send(&mut self, command: &str) -> io::Result<()> {
+        writeln!(self.stdin, "{}", command)?;
+        self.stdin.flush()
+    }
+    /// sends `isready` and drops everything the engine says before `readyok`
+    pub fn wait_ready(&mut self) -> io::Result<()> {
+        self.send("isready")?;
+        loop {
+            match self.messages.recv_timeout(HANDSHAKE_TIMEOUT) {
+                Ok(EngineMessage::ReadyOk) => return Ok(()),
+                Ok(_) => {}
+                Err(_) => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::TimedOut,
+                        "the engine did not answer isready",
+                    ))
+                }
+            }
+        }
+    }
+    /// sends the game and starts searching it , `go` is the whole go command
+    pub fn go(&mut self, game_state: &GameState, go: &str) -> io::Result<()> {
+        // chess960 castling moves are only understood with the option on
+        if game_state.is_chess960() != self.chess960 {
+            self.chess960 = game_state.is_chess960();
+            self.send(&format!(
+                "setoption name UCI_Chess960 value {}",
+                self.chess960
+            ))?;
+        }
+        // only engines that play variants have the option , others go on with standard rules
+        if game_state.variant() != self.variant {
+            self.variant = game_state.variant();
+            self.send(&format!(
+                "setoption name UCI_Variant value {}",
+                self.variant.uci_name()
+            ))?;
+        }
+        self.send(&uci::position_command(game_state))?;
+        self.send(go)
+    }
+    pub fn stop(&mut self) -> io::Result<()> {
+        self.send("stop")
+    }
+    /// the next thing the engine said , if anything , an error once the engine has quit
+    pub fn try_recv(&self) -> io::Result<Option<EngineMessage>> {
+        match self.messages.try_recv() {
+            Ok(message) => Ok(Some(message)),
+            Err(mpsc::TryRecvError::Empty) => Ok(None),
+            Err(mpsc::TryRecvError::Disconnected) => Err(engine_quit()),
+        }
+    }
+}
+
+impl Drop for ExternalEngine {
+    fn drop(&mut self) {
+        let _ = self.send("quit");
+        // engines get a moment to quit on their own before they are killed
+        for _ in 0..20 {
+            if self.child.try_wait().is_ok_and(|status| status.is_some()) {
+                return;
+            }
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+/// an external engine analysing whatever position it is given , one search at a time
+pub struct Analysis {
+    starting: Option<StartingEngine>,
+    /// None until the engine has started
+    engine: Option<ExternalEngine>,
+    /// position command of the last search
+    searching: Option<String>,
+    /// whether the last search has not sent its bestmove yet
+    running: bool,
+    /// a stopped search still has to send its bestmove before the next one starts
+    stopping: bool,
+    pub info: Option<EngineInfo>,
+    /// the pv of info in SAN
+    pub pv_san: Vec<String>,
+}
+
+impl Analysis {
+    /// the engine is started meanwhile , `update` tells when that failed
+    pub fn start(path: &str) -> Self {
+        Self {
+            starting: Some(ExternalEngine::start(path)),
+            engine: None,
+            searching: None,
+            running: false,
+            stopping: false,
+            info: None,
+            pv_san: vec![],
+        }
+    }
+    pub fn engine_name(&self) -> &str {
+        self.engine
+            .as_ref()
+            .map_or("starting the engine", |engine| &engine.name)
+    }
+    /// follows the position , called every frame
+    pub fn update(&mut self, game_state: &GameState) -> io::Result<()> {
+        if let Some(starting) = &self.starting {
+            let Some(engine) = starting.poll()? else {
+                return Ok(());
+            };
+            self.engine = Some(engine);
+            self.starting = None;
+        }
+        let Some(engine) = &mut self.engine else {
+            return Ok(());
+        };
+        let position = uci::position_command(game_state);
+        let is_current = self.searching.as_ref() == Some(&position);
+        while let Some(message) = engine.try_recv()? {
+            match message {
+                EngineMessage::Info(info) if is_current && !self.stopping => {
+                    if !info.pv.is_empty() {
+                        self.pv_san = uci::pv_to_san(game_state, &info.pv);
+                    }
+                    self.info = Some(info);
+                }
+                EngineMessage::BestMove { .. } => {
+                    self.running = false;
+                    self.stopping = false;
+                }
+                _ => {}
+            }
+        }
+        if is_current || self.stopping {
+            return Ok(());
+        }
+        if self.running {
+            engine.stop()?;
+            self.stopping = true;
+            return Ok(());
+        }
+        engine.go(game_state, "go infinite")?;
+        self.searching = Some(position);
+        self.running = true;
+        self.info = None;
+        self.pv_san.clear();
+        Ok(())
+    }
+}
@@ -1,34 +1,633 @@
 use crate::app::ChessGame;
-use crate::common::logic::{ChessPosition, MoveResult};
-use crate::common::move_history::ChessMove;
+use crate::client::OnlineGame;
+use crate::common::clock::ClockState;
+use crate::common::game_modes::GameMode;
+use crate::common::logic::{ChessPosition, GameState, MoveResult, Piece, PlayerType};
+use crate::common::move_history::ChessMoveKind;
+use crate::common::protocol::MAX_CHAT_LEN;
+use crate::common::time_control;
+use crate::common::variant::Variant;
+use crate::engine_adapter::Analysis;
 use egui::Vec2;
 use egui::{Pos2, Rect};
+use std::time::Duration;
 
 pub fn full(chess_game: &mut ChessGame, ctx: &egui::Context, _frame: &mut eframe::Frame) {
     chess_game.mouse_pos = ctx.input(|i| i.pointer.latest_pos());
+    if chess_game
+        .client
+        .game_mode
+        .is_some_and(|game_mode| matches!(game_mode, GameMode::Local | GameMode::VsComputer))
+    {
+        if let Some(move_result) = chess_game.client.update_clock() {
+            chess_game.pos_held = None;
+            chess_game.pending_promotion = None;
+            show_move_result(chess_game, move_result);
+        }
+    }
+    if let Some(move_result) = chess_game.client.update_computer() {
+        chess_game.viewed_ply = None;
+        show_move_result(chess_game, move_result);
+    }
+    if let Some(move_result) = chess_game.client.update_online() {
+        chess_game.viewed_ply = None;
+        show_move_result(chess_game, move_result);
+    }
+    if chess_game.client.online.is_some() {
+        // moves of the opponent come in without any input
+        ctx.request_repaint_after(Duration::from_millis(100));
+    }
+    let computer_thinking = chess_game
+        .client
+        .computer
+        .as_ref()
+        .is_some_and(|computer| computer.is_thinking());
+    if computer_thinking {
+        // nothing else wakes the ui up when the move comes in
+        ctx.request_repaint_after(Duration::from_millis(50));
+    }
+    if chess_game.client.clock.state == ClockState::Running {
+        // the clocks show tenths of a second near the end
+        ctx.request_repaint_after(Duration::from_millis(100));
+    }
     egui::SidePanel::left("previous_moves_and_time").show(ctx, |ui| {
         ui.heading("Time ");
         egui::Grid::new("time_grid_clock").show(ui, |ui| {
             ui.label("White");
             ui.label("Black");
             ui.end_row();
-            ui.label(chess_game.client.clock.white_time.to_string());
-            ui.label(chess_game.client.clock.black_time.to_string());
+            ui.label(time_control::format_time(
+                chess_game.client.clock.white_time,
+            ));
+            ui.label(time_control::format_time(
+                chess_game.client.clock.black_time,
+            ));
             ui.end_row();
+            let game_state = &chess_game.client.game_state;
+            if game_state.variant() == Variant::ThreeCheck {
+                for player in [PlayerType::White, PlayerType::Black] {
+                    ui.label(format!("{} checks", game_state.checks_given(player)));
+                }
+                ui.end_row();
+            }
         });
+        let variant = chess_game.client.game_state.variant();
+        if variant != Variant::Standard {
+            ui.label(variant.name());
+        }
+        if chess_game
+            .client
+            .game_mode
+            .is_some_and(|game_mode| matches!(game_mode, GameMode::Local | GameMode::VsComputer))
+        {
+            pause_button(chess_game, ui);
+        }
+        if chess_game
+            .client
+            .game_mode
+            .is_some_and(|game_mode| game_mode == GameMode::Local)
+        {
+            undo_redo_buttons(chess_game, ui);
+        }
+        if chess_game
+            .client
+            .game_mode
+            .is_some_and(|game_mode| matches!(game_mode, GameMode::Local | GameMode::VsComputer))
+        {
+            move_entry(chess_game, ui);
+            claim_draw_button(chess_game, ui);
+            resign_and_draw_buttons(chess_game, ui);
+        }
+        online_status(chess_game, ui);
+        online_draw_buttons(chess_game, ui);
+        online_chat(chess_game, ui);
+        if computer_thinking {
+            ui.label("computer is thinking ...");
+        }
+        if let Some(engine_error) = chess_game
+            .client
+            .computer
+            .as_ref()
+            .and_then(|computer| computer.engine_error.as_ref())
+        {
+            ui.colored_label(
+                egui::Color32::RED,
+                format!("engine failed , built in engine plays : {}", engine_error),
+            );
+        }
+        if ui
+            .button("Copy PGN")
+            .on_hover_text("copy the game so far as pgn")
+            .clicked()
+        {
+            ctx.output_mut(|o| o.copied_text = chess_game.client.to_pgn());
+        }
+        if ui
+            .button("Copy FEN")
+            .on_hover_text("copy the position on the board as fen")
+            .clicked()
+        {
+            let game_state = &chess_game.client.game_state;
+            let fen = match chess_game.viewed_ply {
+                Some(ply) => game_state.move_history.fen_at_ply(ply).map(str::to_string),
+                None => None,
+            }
+            .unwrap_or_else(|| game_state.to_fen());
+            ctx.output_mut(|o| o.copied_text = fen);
+        }
+        analysis_panel(chess_game, ui, ctx);
+        opening_panel(chess_game, ui);
         ui.heading("Previous Moves");
-        egui::ScrollArea::vertical().show(ui, |ui| {
-            egui::Grid::new("prev_move_grid").show(ui, |ui| {
-                for (i, chess_move) in chess_game.client.game_state.prev_moves.iter().enumerate() {
-                    ui.label(i.to_string());
-                    ui.label(chess_move.to_string());
-                    ui.end_row();
+        move_navigation(chess_game, ui);
+        move_list(chess_game, ui);
+    });
+    egui::CentralPanel::default().show(ctx, |ui| {
+        chess_board(chess_game, ui, ctx);
+    });
+}
+
+/// who plays whom in an Online game , and what went wrong with the server
+fn online_status(chess_game: &ChessGame, ui: &mut egui::Ui) {
+    let Some(online) = &chess_game.client.online else {
+        return;
+    };
+    if online.spectating {
+        let name = |index: usize| online.players[index].as_deref().unwrap_or("?");
+        ui.label(format!(
+            "watching {} , {} against {}",
+            online.game_id,
+            name(0),
+            name(1)
+        ));
+        if !online.spectator_delay.is_zero() {
+            ui.label(format!(
+                "moves are shown {:.1} seconds late",
+                online.spectator_delay.as_secs_f32()
+            ));
+        }
+        if !online.is_connected() {
+            ui.label("reconnecting ...");
+        }
+    } else {
+        player_status(online, ui);
+    }
+    if let Some(error) = &online.error {
+        ui.colored_label(egui::Color32::RED, error);
+    }
+}
+fn player_status(online: &OnlineGame, ui: &mut egui::Ui) {
+    ui.label(format!("game {} as {}", online.game_id, online.user_id));
+    match (online.color, &online.opponent) {
+        (None, _) => ui.label("joining ..."),
+        (Some(color), None) => ui.label(format!(
+            "playing {} , waiting for an opponent",
+            color.to_string()
+        )),
+        (Some(color), Some(opponent)) => ui.label(format!(
+            "playing {} against {}",
+            color.to_string(),
+            opponent
+        )),
+    };
+    if online.color.is_some() && !online.is_connected() {
+        ui.label("reconnecting ...");
+    }
+}
+/// resigning and draw offers in an Online game , the server ends the game
+fn online_draw_buttons(chess_game: &mut ChessGame, ui: &mut egui::Ui) {
+    let client = &mut chess_game.client;
+    let Some(online) = &client.online else {
+        return;
+    };
+    let Some(color) = online.color else {
+        return;
+    };
+    if client.game_state.is_game_over() || online.version < 2 {
+        return;
+    }
+    match client.game_state.draw_offer {
+        Some(offerer) if offerer != color => {
+            ui.label(format!("{} offers a draw", offerer.to_string()));
+            ui.horizontal(|ui| {
+                if ui.button("Accept").clicked() {
+                    client.offer_draw_online();
+                }
+                if ui.button("Decline").clicked() {
+                    client.decline_draw_online();
+                }
+            });
+        }
+        Some(_) => {
+            ui.label("draw offered");
+        }
+        None => {}
+    }
+    ui.horizontal(|ui| {
+        if ui
+            .add_enabled(
+                client.game_state.draw_offer.is_none(),
+                egui::Button::new("Offer draw"),
+            )
+            .clicked()
+        {
+            client.offer_draw_online();
+        }
+        if ui.button("Resign").clicked() {
+            client.resign_online();
+        }
+    });
+}
+/// the chat of an Online game , newest at the bottom
+fn online_chat(chess_game: &mut ChessGame, ui: &mut egui::Ui) {
+    let Some(online) = &chess_game.client.online else {
+        return;
+    };
+    // the chat is between the players
+    if online.version < 2 || online.spectating {
+        return;
+    }
+    ui.label("chat");
+    egui::ScrollArea::vertical()
+        .id_source("chat")
+        .max_height(120.)
+        .stick_to_bottom(true)
+        .show(ui, |ui| {
+            for (from, text) in &online.chat {
+                ui.label(format!("{} : {}", from, text));
+            }
+        });
+    let response = ui.add(
+        egui::TextEdit::singleline(&mut chess_game.chat_input)
+            .char_limit(MAX_CHAT_LEN)
+            .hint_text("say something"),
+    );
+    if response.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter)) {
+        let text = chess_game.chat_input.trim().to_string();
+        if !text.is_empty() {
+            chess_game.client.send_chat(&text);
+        }
+        chess_game.chat_input.clear();
+    }
+}
+/// an external engine analysing the position on the board
+fn analysis_panel(chess_game: &mut ChessGame, ui: &mut egui::Ui, ctx: &egui::Context) {
+    ui.heading("Analysis");
+    match &mut chess_game.analysis {
+        None => {
+            ui.horizontal(|ui| {
+                ui.add(
+                    egui::TextEdit::singleline(&mut chess_game.engine_path)
+                        .hint_text("UCI engine path")
+                        .desired_width(120.),
+                );
+                if ui.button("Analyse").clicked() {
+                    chess_game.analysis = Some(Analysis::start(chess_game.engine_path.trim()));
+                    chess_game.analysis_error = None;
                 }
             });
+            if let Some(analysis_error) = &chess_game.analysis_error {
+                ui.colored_label(egui::Color32::RED, analysis_error);
+            }
+        }
+        Some(analysis) => {
+            let game_state = &chess_game.client.game_state;
+            let viewed_state = chess_game
+                .viewed_ply
+                .and_then(|ply| game_state.position_at_ply(ply));
+            let shown_state = viewed_state.as_ref().unwrap_or(game_state);
+            let mut stop = false;
+            if let Err(error) = analysis.update(shown_state) {
+                chess_game.analysis_error = Some(error.to_string());
+                stop = true;
+            }
+            ui.horizontal(|ui| {
+                ui.label(analysis.engine_name());
+                if ui.button("Stop").clicked() {
+                    stop = true;
+                }
+            });
+            if let Some(info) = &analysis.info {
+                let score = info.score.map_or("?".to_string(), |score| {
+                    score.for_white(shown_state.active_color).to_string()
+                });
+                ui.label(format!(
+                    "depth {}  {}",
+                    info.depth
+                        .map_or("?".to_string(), |depth| depth.to_string()),
+                    score
+                ));
+                ui.label(analysis.pv_san.join(" "));
+            }
+            // engine output comes in without any input
+            ctx.request_repaint_after(Duration::from_millis(100));
+            if stop {
+                chess_game.analysis = None;
+            }
+        }
+    }
+}
+/// the book moves of the position on the board , clicking one plays it
+fn opening_panel(chess_game: &mut ChessGame, ui: &mut egui::Ui) {
+    ui.heading("Opening");
+    let Some(book) = chess_game.book.clone() else {
+        ui.horizontal(|ui| {
+            ui.add(
+                egui::TextEdit::singleline(&mut chess_game.book_path)
+                    .hint_text("polyglot book path")
+                    .desired_width(120.),
+            );
+            if ui.button("Load").clicked() {
+                chess_game.load_book();
+            }
         });
+        if let Some(book_error) = &chess_game.book_error {
+            ui.colored_label(egui::Color32::RED, book_error);
+        }
+        return;
+    };
+    let game_state = &chess_game.client.game_state;
+    let viewed_state = chess_game
+        .viewed_ply
+        .and_then(|ply| game_state.position_at_ply(ply));
+    let shown_state = viewed_state.as_ref().unwrap_or(game_state);
+    let book_moves = book.moves(shown_state);
+    let total: u32 = book_moves.iter().map(|m| m.weight as u32).sum();
+    // moves can only be played on the latest position
+    let can_play = viewed_state.is_none() && chess_game.client.is_human_turn();
+    let mut picked = None;
+    if book_moves.is_empty() {
+        ui.label("out of book");
+    }
+    egui::Grid::new("book_move_grid").show(ui, |ui| {
+        for book_move in &book_moves {
+            let san = shown_state.to_san(&book_move.chess_move);
+            if ui.add_enabled(can_play, egui::Button::new(san)).clicked() {
+                picked = Some(book_move.chess_move);
+            }
+            ui.label(format!(
+                "{:.1}%",
+                book_move.weight as f32 * 100. / total.max(1) as f32
+            ));
+            ui.label(book_move.weight.to_string());
+            ui.end_row();
+        }
     });
-    egui::CentralPanel::default().show(ctx, |ui| {
-        chess_board(chess_game, ui, ctx);
+    if ui.button("Close book").clicked() {
+        chess_game.book = None;
+        if let Some(computer) = &mut chess_game.client.computer {
+            computer.book = None;
+        }
+    }
+    if let Some(chess_move) = picked {
+        chess_game.pos_held = None;
+        chess_game.pending_promotion = None;
+        let move_result = chess_game.client.game_state.do_move(&chess_move);
+        show_move_result(chess_game, move_result);
+    }
+}
+/// buttons to step through the played positions
+fn move_navigation(chess_game: &mut ChessGame, ui: &mut egui::Ui) {
+    let last_ply = chess_game.client.game_state.move_history.len();
+    let cur_ply = chess_game.viewed_ply.unwrap_or(last_ply);
+    let mut new_ply = cur_ply;
+    ui.horizontal(|ui| {
+        if ui
+            .add_enabled(cur_ply > 0, egui::Button::new("|<"))
+            .clicked()
+        {
+            new_ply = 0;
+        }
+        if ui
+            .add_enabled(cur_ply > 0, egui::Button::new("<"))
+            .clicked()
+        {
+            new_ply = cur_ply - 1;
+        }
+        if ui
+            .add_enabled(cur_ply < last_ply, egui::Button::new(">"))
+            .clicked()
+        {
+            new_ply = cur_ply + 1;
+        }
+        if ui
+            .add_enabled(cur_ply < last_ply, egui::Button::new(">|"))
+            .clicked()
+        {
+            new_ply = last_ply;
+        }
+    });
+    if new_ply != cur_ply {
+        view_ply(chess_game, new_ply);
+    }
+}
+/// the played moves in two columns , clicking one shows the position after it
+fn move_list(chess_game: &mut ChessGame, ui: &mut egui::Ui) {
+    let last_ply = chess_game.client.game_state.move_history.len();
+    let cur_ply = chess_game.viewed_ply.unwrap_or(last_ply);
+    let mut clicked_ply = None;
+    egui::ScrollArea::vertical().show(ui, |ui| {
+        egui::Grid::new("prev_move_grid")
+            .num_columns(3)
+            .striped(true)
+            .show(ui, |ui| {
+                ui.label("");
+                ui.label("White");
+                ui.label("Black");
+                ui.end_row();
+                for (move_no, white_move, black_move) in
+                    chess_game.client.game_state.move_history.full_moves()
+                {
+                    ui.label(format!("{}.", move_no));
+                    for ply_entry in [white_move, black_move] {
+                        match ply_entry {
+                            Some((ply, entry)) => {
+                                if ui
+                                    .selectable_label(ply == cur_ply, &entry.notation)
+                                    .clicked()
+                                {
+                                    clicked_ply = Some(ply);
+                                }
+                            }
+                            None => {
+                                ui.label("...");
+                            }
+                        }
+                    }
+                    ui.end_row();
+                }
+            });
+    });
+    if let Some(ply) = clicked_ply {
+        view_ply(chess_game, ply);
+    }
+}
+fn view_ply(chess_game: &mut ChessGame, ply: usize) {
+    chess_game.viewed_ply = if ply >= chess_game.client.game_state.move_history.len() {
+        None
+    } else {
+        Some(ply)
+    };
+    chess_game.pos_held = None;
+    chess_game.pending_promotion = None;
+}
+/// text box for typing a move in SAN
+fn move_entry(chess_game: &mut ChessGame, ui: &mut egui::Ui) {
+    ui.horizontal(|ui| {
+        let response = ui.add(
+            egui::TextEdit::singleline(&mut chess_game.move_input)
+                .hint_text("move , e.g. Nf3")
+                .desired_width(80.),
+        );
+        let entered = response.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter));
+        if (ui.button("Play").clicked() || entered)
+            && !chess_game.move_input.is_empty()
+            && chess_game.client.is_human_turn()
+        {
+            match chess_game
+                .client
+                .game_state
+                .move_from_san(&chess_game.move_input)
+            {
+                Some(chess_move) => {
+                    chess_game.move_input.clear();
+                    chess_game.move_input_error = false;
+                    chess_game.viewed_ply = None;
+                    chess_game.pos_held = None;
+                    chess_game.pending_promotion = None;
+                    let move_result = chess_game.client.game_state.do_move(&chess_move);
+                    show_move_result(chess_game, move_result);
+                }
+                None => chess_game.move_input_error = true,
+            }
+        }
+    });
+    if chess_game.move_input_error {
+        ui.colored_label(egui::Color32::RED, "not a legal move");
+    }
+}
+/// stops both clocks , nobody can move until the game is resumed
+fn pause_button(chess_game: &mut ChessGame, ui: &mut egui::Ui) {
+    let clock = &mut chess_game.client.clock;
+    match clock.state {
+        ClockState::Running => {
+            if ui.button("Pause").clicked() {
+                clock.pause();
+                chess_game.pos_held = None;
+            }
+        }
+        ClockState::Paused if !chess_game.client.game_state.is_game_over() => {
+            ui.label("paused");
+            if ui.button("Resume").clicked() {
+                clock.resume();
+            }
+        }
+        _ => {}
+    }
+}
+/// lets the player to move claim a threefold repetition or fifty move draw
+fn claim_draw_button(chess_game: &mut ChessGame, ui: &mut egui::Ui) {
+    if !chess_game.client.is_human_turn() {
+        return;
+    }
+    let Some(draw_reason) = chess_game.client.game_state.claimable_draw() else {
+        return;
+    };
+    if ui
+        .button("Claim draw")
+        .on_hover_text(format!("draw by {}", draw_reason.to_string()))
+        .clicked()
+    {
+        if let Some(move_result) = chess_game.client.game_state.claim_draw() {
+            chess_game.viewed_ply = None;
+            chess_game.pos_held = None;
+            chess_game.pending_promotion = None;
+            show_move_result(chess_game, move_result);
+        }
+    }
+}
+/// resigning and offering draws for the player at the screen , and answering offers in local games
+fn resign_and_draw_buttons(chess_game: &mut ChessGame, ui: &mut egui::Ui) {
+    let game_state = &chess_game.client.game_state;
+    if game_state.is_game_over() {
+        return;
+    }
+    let player = chess_game.client.perspective(game_state);
+    let mut move_result = None;
+    match game_state.draw_offer {
+        Some(offerer)
+            if chess_game.client.computer.is_none() && offerer != game_state.active_color =>
+        {
+            ui.label(format!("{} offers a draw", offerer.to_string()));
+            ui.horizontal(|ui| {
+                if ui.button("Accept").clicked() {
+                    move_result = chess_game.client.game_state.accept_draw();
+                }
+                if ui.button("Decline").clicked() {
+                    chess_game.client.game_state.decline_draw();
+                }
+            });
+        }
+        Some(_) => {
+            ui.label("draw offered");
+        }
+        None => {
+            let declined = chess_game.client.computer.as_ref().is_some_and(|computer| {
+                computer
+                    .declined_draw_ply
+                    .is_some_and(|ply| game_state.move_history.len() <= ply + 1)
+            });
+            if declined {
+                ui.label("the computer declined the draw");
+            }
+        }
+    }
+    ui.horizontal(|ui| {
+        let can_offer = chess_game.client.game_state.draw_offer.is_none()
+            && (chess_game.client.computer.is_none() || chess_game.client.is_human_turn());
+        if ui
+            .add_enabled(can_offer, egui::Button::new("Offer draw"))
+            .clicked()
+        {
+            chess_game.client.game_state.offer_draw(player);
+        }
+        if ui
+            .button("Resign")
+            .on_hover_text(format!("{} resigns", player.to_string()))
+            .clicked()
+        {
+            move_result = chess_game.client.game_state.resign(player);
+        }
+    });
+    if let Some(move_result) = move_result {
+        chess_game.pos_held = None;
+        chess_game.pending_promotion = None;
+        show_move_result(chess_game, move_result);
+    }
+}
+fn undo_redo_buttons(chess_game: &mut ChessGame, ui: &mut egui::Ui) {
+    ui.horizontal(|ui| {
+        let game_state = &mut chess_game.client.game_state;
+        if ui
+            .add_enabled(game_state.can_undo(), egui::Button::new("Undo"))
+            .clicked()
+        {
+            game_state.undo_move();
+            chess_game.viewed_ply = None;
+            chess_game.pos_held = None;
+            chess_game.pending_promotion = None;
+            chess_game.window_open = false;
+        }
+        if ui
+            .add_enabled(game_state.can_redo(), egui::Button::new("Redo"))
+            .clicked()
+        {
+            if let Some(move_result) = game_state.redo_move() {
+                chess_game.viewed_ply = None;
+                chess_game.pos_held = None;
+                chess_game.pending_promotion = None;
+                show_move_result(chess_game, move_result);
+            }
+        }
     });
 }
 
@@ -39,6 +638,15 @@ fn chess_board(chess_game: &mut ChessGame, ui: &mut egui::Ui, ctx: &egui::Contex
     let rct = Rect::from_two_pos(start_pos, end_pos);
     let tile_side = rct.width().min(rct.height()) / 8.;
     chess_game.tile_width = tile_side;
+    if let Some(viewed_state) = chess_game
+        .viewed_ply
+        .and_then(|ply| chess_game.client.game_state.position_at_ply(ply))
+    {
+        // earlier positions are only shown , moves are made on the latest one
+        chess_game.pos_held = None;
+        paint_board(chess_game, &viewed_state, ui, start_pos, tile_side);
+        return;
+    }
     let mut cur_chess_pos = match chess_game.mouse_pos {
         Some(cur_mouse_pos) => {
             let x = cur_mouse_pos - start_pos;
@@ -51,34 +659,41 @@ fn chess_board(chess_game: &mut ChessGame, ui: &mut egui::Ui, ctx: &egui::Contex
         None => None,
     };
     cur_chess_pos = if let Some(ccp) = cur_chess_pos {
-        Some(ccp.adjust_for_current_player(chess_game.client.game_state.active_color))
+        Some(ccp.adjust_for_current_player(
+            chess_game.client.perspective(&chess_game.client.game_state),
+        ))
     } else {
         None
     };
-    if ctx.input(|i| i.pointer.primary_pressed()) {
-        if chess_game.pos_held.is_none() {
-            if cur_chess_pos.is_some_and(|chess_pos| {
-                chess_game
-                    .client
-                    .game_state
-                    .board
-                    .get_piece_at_pos(&chess_pos)
-                    .is_some_and(|piece| {
-                        piece.player_kind == chess_game.client.game_state.active_color
-                    })
-            }) {
-                chess_game.pos_held = cur_chess_pos;
-            }
-        }
-    }
-    let move_result = if ctx.input(|i| i.pointer.primary_released()) {
-        if let (Some(from_pos), Some(to_pos)) = (chess_game.pos_held.clone(), cur_chess_pos) {
+    if chess_game.pending_promotion.is_none()
+        && ctx.input(|i| i.pointer.primary_pressed())
+        && chess_game.pos_held.is_none()
+        && chess_game.client.is_human_turn()
+        && cur_chess_pos.is_some_and(|chess_pos| {
+            chess_game
+                .client
+                .game_state
+                .board
+                .get_piece_at_pos(&chess_pos)
+                .is_some_and(|piece| piece.player_kind == chess_game.client.game_state.active_color)
+        })
+    {
+        chess_game.pos_held = cur_chess_pos;
+    }
+    let move_result = if chess_game.pending_promotion.is_some() {
+        promotion_picker(chess_game, ctx)
+    } else if ctx.input(|i| i.pointer.primary_released()) {
+        if let (Some(from_pos), Some(to_pos)) = (chess_game.pos_held, cur_chess_pos) {
             chess_game.pos_held = None;
 
-            if let Some(chess_move) = chess_game.client.game_state.make_move(&from_pos, &to_pos) {
-                Some(chess_game.client.game_state.do_move(&chess_move))
-            } else {
-                None
+            match chess_game.client.game_state.make_move(&from_pos, &to_pos) {
+                Some(chess_move) if matches!(chess_move.move_kind, ChessMoveKind::Promotion(_)) => {
+                    // the piece is chosen in the promotion picker before the move is done
+                    chess_game.pending_promotion = Some(chess_move.move_vector);
+                    None
+                }
+                Some(chess_move) => Some(chess_game.client.game_state.do_move(&chess_move)),
+                None => None,
             }
         } else {
             None
@@ -87,40 +702,89 @@ fn chess_board(chess_game: &mut ChessGame, ui: &mut egui::Ui, ctx: &egui::Contex
         None
     };
     if let Some(some_result) = move_result {
-        dbg!(some_result);
-        chess_game.window_open = true;
-        chess_game.result_text = match some_result {
-            MoveResult::GameEnd(t) => t.to_string(),
-            MoveResult::InvalidMove => "noooooo".to_string(),
-            MoveResult::Normal => {
-                chess_game.window_open = false;
-                "".to_string()
-            }
-        };
+        show_move_result(chess_game, some_result);
     }
 
-    paint_board(chess_game, ui, start_pos, tile_side);
+    paint_board(
+        chess_game,
+        &chess_game.client.game_state,
+        ui,
+        start_pos,
+        tile_side,
+    );
+}
+/// opens the result window when the game has ended , telling who won and why
+pub fn show_move_result(chess_game: &mut ChessGame, move_result: MoveResult) {
+    chess_game.window_open = true;
+    chess_game.result_text = match move_result {
+        MoveResult::GameEnd(t) => t.to_string(),
+        MoveResult::InvalidMove => "noooooo".to_string(),
+        MoveResult::Normal => {
+            chess_game.window_open = false;
+            "".to_string()
+        }
+    };
+}
+/// shows the promotion piece choices and performs the pending move once one is picked
+fn promotion_picker(chess_game: &mut ChessGame, ctx: &egui::Context) -> Option<MoveResult> {
+    let move_vector = chess_game.pending_promotion?;
+    let player_kind = chess_game.client.game_state.active_color;
+    let mut picked = None;
+    let mut cancelled = false;
+    egui::Window::new("Promote to")
+        .collapsible(false)
+        .resizable(false)
+        .anchor(egui::Align2::CENTER_CENTER, Vec2::ZERO)
+        .show(ctx, |ui| {
+            ui.horizontal(|ui| {
+                for &piece_type in chess_game.client.game_state.variant().promotion_pieces() {
+                    let image = chess_game
+                        .images
+                        .get(&Piece::new(piece_type, player_kind))
+                        .clone()
+                        .fit_to_exact_size(chess_game.images.image_size);
+                    if ui.add(egui::ImageButton::new(image)).clicked() {
+                        picked = Some(piece_type);
+                    }
+                }
+            });
+            if ui.button("Cancel").clicked() {
+                cancelled = true;
+            }
+        });
+    if cancelled {
+        chess_game.pending_promotion = None;
+        return None;
+    }
+    let chess_move = chess_game.client.game_state.make_promotion_move(
+        &move_vector.from_pos,
+        &move_vector.to_pos,
+        picked?,
+    );
+    chess_game.pending_promotion = None;
+    Some(chess_game.client.game_state.do_move(&chess_move?))
 }
-fn paint_board(chess_game: &mut ChessGame, ui: &mut egui::Ui, start: Pos2, tile_side: f32) {
+fn paint_board(
+    chess_game: &ChessGame,
+    game_state: &GameState,
+    ui: &mut egui::Ui,
+    start: Pos2,
+    tile_side: f32,
+) {
     // check if piece in hand
     let painter = ui.painter();
     let mut red_squares: Vec<ChessPosition> = vec![];
     let mut o_piece = None;
     let mut o_piece_pos = None;
-    if let Some(mut piece_pos) = chess_game.pos_held {
-        piece_pos = piece_pos;
-        if let Some(piece) = chess_game
-            .client
-            .game_state
-            .board
-            .get_piece_at_pos(&piece_pos)
-        {
+    if let Some(piece_pos) = chess_game.pos_held {
+        if let Some(piece) = game_state.board.get_piece_at_pos(&piece_pos) {
             red_squares.append(&mut chess_game.client.possible_move_ends(&piece_pos));
             o_piece = Some(piece);
             o_piece_pos = Some(piece_pos);
         }
     }
     // draw the board and pieces
+    let perspective = chess_game.client.perspective(game_state);
 
     for pos in ChessPosition::iter() {
         let color = if (pos.file + pos.rank) % 2 == 0 {
@@ -136,19 +800,16 @@ fn paint_board(chess_game: &mut ChessGame, ui: &mut egui::Ui, start: Pos2, tile_
             ),
         )
         .translate(Vec2::new(start.x, start.y));
-        if red_squares
-            .contains(&pos.adjust_for_current_player(chess_game.client.game_state.active_color))
-        {
+        if red_squares.contains(&pos.adjust_for_current_player(perspective)) {
             painter.rect_filled(rect, 0., egui::Color32::RED);
         } else {
             painter.rect_filled(rect, 0., color);
         }
-        if let Some(piece) = chess_game.client.game_state.board.get_piece_at_pos(
-            &pos.adjust_for_current_player(chess_game.client.game_state.active_color),
-        ) {
-            if !o_piece_pos.is_some_and(|pos2| {
-                pos == pos2.adjust_for_current_player(chess_game.client.game_state.active_color)
-            }) {
+        if let Some(piece) = game_state
+            .board
+            .get_piece_at_pos(&pos.adjust_for_current_player(perspective))
+        {
+            if !o_piece_pos.is_some_and(|pos2| pos == pos2.adjust_for_current_player(perspective)) {
                 let image = chess_game.images.get(&piece);
 
                 image.paint_at(ui, rect);
@@ -44,7 +44,7 @@ impl<'a> Default for PieceImages<'a> {
     }
 }
 impl<'a> PieceImages<'a> {
-    pub fn get(self: &Self, piece: &Piece) -> &Image<'a> {
+    pub fn get(&self, piece: &Piece) -> &Image<'a> {
         match piece.player_kind {
             PlayerType::White => match piece.piece_kind {
                 PieceType::Pawn => &self.white_pawn_image,
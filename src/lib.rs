@@ -1,6 +1,9 @@
 pub mod app;
 pub mod client;
+pub mod engine_adapter;
 pub mod images;
+pub mod server;
+pub mod websocket;
 
 pub mod game_components;
 pub mod login_components;
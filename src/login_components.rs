@@ -1,7 +1,12 @@
-use eframe::App;
-
 use crate::app::ChessGame;
-use crate::common::game_modes::GameType;
+use crate::common::chess960;
+use crate::common::engine::MAX_STRENGTH;
+use crate::common::game_modes::{GameMode, GameType};
+use crate::common::logic::{GameState, PlayerType};
+use crate::common::pgn;
+use crate::common::time_control::TimeControl;
+use crate::common::variant::{Variant, VARIANTS};
+use std::time::Duration;
 pub fn local(chess_game: &mut ChessGame, ctx: &egui::Context, _frame: &mut eframe::Frame) {
     egui::SidePanel::left("local_options").show(ctx, |ui| {
         ui.heading("Local Game Options");
@@ -16,19 +21,231 @@ pub fn local(chess_game: &mut ChessGame, ctx: &egui::Context, _frame: &mut efram
             );
             ui.radio_value(&mut chess_game.game_type, Some(GameType::Bullet), "Bullet");
         });
+        ui.add(
+            egui::TextEdit::singleline(&mut chess_game.time_control_string)
+                .hint_text("or e.g. 180+2 , 40/5400:1800+30 , 300d5"),
+        );
+        let time_control_valid = chess_game.time_control_string.trim().is_empty()
+            || TimeControl::parse(&chess_game.time_control_string).is_some();
+        if !time_control_valid {
+            ui.colored_label(egui::Color32::RED, "not a pgn time control");
+        }
+        ui.spacing();
+        ui.label("Opponent");
+        ui.horizontal(|ui| {
+            ui.radio_value(&mut chess_game.game_mode, GameMode::Local, "Local");
+            ui.radio_value(&mut chess_game.game_mode, GameMode::VsComputer, "Computer");
+            ui.radio_value(&mut chess_game.game_mode, GameMode::Online, "Online");
+        });
+        let online = chess_game.game_mode == GameMode::Online;
+        if online {
+            ui.label("server");
+            ui.text_edit_singleline(&mut chess_game.server_address);
+            ui.add(
+                egui::TextEdit::singleline(&mut chess_game.online_game_id)
+                    .hint_text("game id , a new one creates the game"),
+            );
+            ui.add(
+                egui::TextEdit::singleline(&mut chess_game.online_user_id).hint_text("your name"),
+            );
+            ui.checkbox(&mut chess_game.online_json, "JSON messages")
+                .on_hover_text("readable messages for debugging , binary ones are smaller");
+        }
+        if chess_game.game_mode == GameMode::VsComputer {
+            ui.horizontal(|ui| {
+                ui.label("play as");
+                ui.radio_value(&mut chess_game.computer_color, PlayerType::Black, "White");
+                ui.radio_value(&mut chess_game.computer_color, PlayerType::White, "Black");
+            });
+            ui.add(
+                egui::Slider::new(&mut chess_game.computer_strength, 1..=MAX_STRENGTH)
+                    .text("strength"),
+            );
+            ui.label("external UCI engine , leave empty for the built in one");
+            ui.text_edit_singleline(&mut chess_game.engine_path);
+        }
+        ui.spacing();
+        ui.horizontal(|ui| {
+            ui.label("Variant");
+            egui::ComboBox::from_id_source("variant")
+                .selected_text(chess_game.variant.name())
+                .show_ui(ui, |ui| {
+                    for variant in VARIANTS {
+                        ui.selectable_value(&mut chess_game.variant, variant, variant.name());
+                    }
+                });
+        });
+        if !online {
+            ui.checkbox(&mut chess_game.chess960, "Chess960");
+        }
+        let chess960_index = if chess_game.chess960_position.trim().is_empty() {
+            None
+        } else {
+            chess_game
+                .chess960_position
+                .trim()
+                .parse::<u32>()
+                .ok()
+                .filter(|index| *index < chess960::POSITION_COUNT)
+        };
+        let chess960_valid = chess960_index.is_some()
+            || chess_game.chess960_position.trim().is_empty()
+            || !chess_game.chess960
+            || online;
+        if chess_game.chess960 && !online {
+            ui.add(
+                egui::TextEdit::singleline(&mut chess_game.chess960_position)
+                    .hint_text("start position 0 to 959 , random when empty"),
+            );
+            if !chess960_valid {
+                ui.colored_label(egui::Color32::RED, "not a position from 0 to 959");
+            }
+        }
         ui.spacing();
         ui.label("use fen string");
-        ui.text_edit_multiline(&mut chess_game.local_fen_string);
+        let fen_string = if online {
+            &mut chess_game.online_fen_string
+        } else {
+            &mut chess_game.local_fen_string
+        };
+        ui.text_edit_multiline(fen_string);
+        let fen_error = if fen_string.trim().is_empty() {
+            None
+        } else {
+            GameState::from_fen_variant(fen_string, chess_game.variant, false).err()
+        };
+        if let Some(fen_error) = &fen_error {
+            ui.colored_label(egui::Color32::RED, fen_error.to_string());
+        }
+        ui.spacing();
+        if online {
+            let ids_given = !chess_game.online_game_id.trim().is_empty()
+                && !chess_game.online_user_id.trim().is_empty();
+            if ui
+                .add_enabled(
+                    fen_error.is_none() && time_control_valid && ids_given,
+                    egui::Button::new("Join Game"),
+                )
+                .on_hover_text("Join the game on the server , the first to join plays white")
+                .clicked()
+            {
+                chess_game.start_online_game();
+            }
+            ui.horizontal(|ui| {
+                if ui
+                    .add_enabled(
+                        !chess_game.online_game_id.trim().is_empty(),
+                        egui::Button::new("Watch Game"),
+                    )
+                    .on_hover_text("Watch the game without playing in it")
+                    .clicked()
+                {
+                    let game_id = chess_game.online_game_id.trim().to_string();
+                    chess_game.start_spectating(&game_id);
+                }
+                if ui
+                    .button("List Games")
+                    .on_hover_text("The games going on at the server")
+                    .clicked()
+                {
+                    chess_game.list_games();
+                }
+            });
+            if let Some(online_error) = &chess_game.online_error {
+                ui.colored_label(egui::Color32::RED, online_error);
+            }
+            game_list(chess_game, ui, ctx);
+            return;
+        }
+        ui.label("or continue a pgn game");
+        ui.text_edit_multiline(&mut chess_game.local_pgn_string);
+        let pgn_valid = chess_game.local_pgn_string.trim().is_empty()
+            || pgn::parse_pgn(&chess_game.local_pgn_string)
+                .first()
+                .and_then(|game| game.replay())
+                .is_some();
+        if !pgn_valid {
+            ui.colored_label(egui::Color32::RED, "not a game of legal moves");
+        }
         ui.spacing();
         if ui
-            .button("Start Game")
-            .on_hover_text("Start a local game")
+            .add_enabled(
+                fen_error.is_none() && time_control_valid && chess960_valid && pgn_valid,
+                egui::Button::new("Start Game"),
+            )
+            .on_hover_text("Start a local game or one against the computer")
             .clicked()
         {
             chess_game.start_local_game();
-            if !chess_game.local_fen_string.is_empty() {
-                chess_game.client.update_fen(&chess_game.local_fen_string);
+            if !chess_game.local_pgn_string.trim().is_empty() {
+                // the pgn was already checked above
+                let _ = chess_game.client.load_pgn(&chess_game.local_pgn_string);
+            } else if !chess_game.local_fen_string.trim().is_empty() {
+                // the fen was already checked above
+                let _ = chess_game.client.update_fen(
+                    &chess_game.local_fen_string,
+                    chess_game.variant,
+                    chess_game.chess960,
+                );
+            } else if chess_game.chess960 {
+                let index = chess960_index.unwrap_or_else(chess960::random_index);
+                // every index below 960 has a start position
+                let _ = chess_game.client.update_fen(
+                    &chess960::start_fen(index).unwrap(),
+                    chess_game.variant,
+                    true,
+                );
+            } else if chess_game.variant != Variant::Standard {
+                let _ = chess_game.client.update_fen(
+                    chess_game.variant.start_fen(),
+                    chess_game.variant,
+                    false,
+                );
             }
         };
     });
 }
+
+/// the games going on at the server , any of them can be watched
+fn game_list(chess_game: &mut ChessGame, ui: &mut egui::Ui, ctx: &egui::Context) {
+    let Some(game_listing) = &mut chess_game.game_listing else {
+        return;
+    };
+    game_listing.update();
+    let mut watched = None;
+    match (&game_listing.games, &game_listing.error) {
+        (_, Some(error)) => {
+            ui.colored_label(egui::Color32::RED, error);
+        }
+        (None, None) => {
+            ui.label("asking the server ...");
+            // the answer comes in without any input
+            ctx.request_repaint_after(Duration::from_millis(100));
+        }
+        (Some(games), None) if games.is_empty() => {
+            ui.label("no games going on");
+        }
+        (Some(games), None) => {
+            for game in games {
+                let name = |user_id: &Option<String>| user_id.clone().unwrap_or("?".to_string());
+                ui.horizontal(|ui| {
+                    ui.label(format!(
+                        "{} : {} against {} , {} {} , move {}",
+                        game.game_id,
+                        name(&game.white),
+                        name(&game.black),
+                        game.variant.name(),
+                        game.time_control,
+                        game.plies / 2 + 1
+                    ));
+                    if ui.small_button("Watch").clicked() {
+                        watched = Some(game.game_id.clone());
+                    }
+                });
+            }
+        }
+    }
+    if let Some(game_id) = watched {
+        chess_game.start_spectating(&game_id);
+    }
+}
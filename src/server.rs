@@ -0,0 +1,712 @@
+//! the game server of Online games , it keeps the games and their clocks and only passes on
+//! moves that are legal , every connection is served on a thread of its own
+//!
+//! players who lose their connection get their seat back with `Reconnect` or `Join` and the
+//! seat token they were sent in `Game` , and are sent the whole game , a seat is not given
+//! away while its player is connected , a player away for longer than
+//! `ServerConfig::abandon_timeout` loses the game
+//!
+//! spectators watch a game with `Watch` , moves reach them `ServerConfig::spectator_delay` after
+//! they were played , `ListGames` lists the games going on
+//!
+//! games are let go `ServerConfig::keep_finished` after they end or all their players left
+//!
+//! clients speak JSON or binary messages over plain TCP or WebSocket on the same port , see
+//! `common::protocol` , a connection starting with an http request is taken as WebSocket
+use std::collections::hash_map::RandomState;
+use std::collections::{HashMap, VecDeque};
+use std::hash::BuildHasher;
+use std::io::{self, BufRead, BufReader, Write};
+use std::net::{Shutdown, SocketAddr, TcpListener, TcpStream};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{mpsc, Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::common::bitboard;
+use crate::common::clock::{Clock, ClockState};
+use crate::common::game_modes::GameType;
+use crate::common::logic::{GameEnd, GameState, PlayerType};
+use crate::common::protocol::{
+    self, ClockSync, Encoding, Framing, GameSummary, Message, MAX_CHAT_LEN, PROTOCOL_VERSION,
+};
+use crate::common::time_control::TimeControl;
+use crate::common::uci;
+use crate::common::variant::{self, Variant};
+use crate::websocket;
+
+/// how often running clocks are looked at for a fallen flag , and seats for absent players
+const TICK: Duration = Duration::from_millis(50);
+/// a client that does not take its messages for this long is dropped
+const WRITE_TIMEOUT: Duration = Duration::from_secs(5);
+/// messages waiting for a client , one further behind is dropped
+const OUTGOING_QUEUE_LEN: usize = 256;
+
+type Games = Arc<Mutex<HashMap<String, ServerGame>>>;
+
+/// tells the connections apart
+static NEXT_CONNECTION_ID: AtomicU64 = AtomicU64::new(0);
+
+/// how the server runs its games
+#[derive(Debug, Clone, Copy)]
+pub struct ServerConfig {
+    /// a player whose connection is gone for this long loses the game , as long as the
+    /// opponent is still there
+    pub abandon_timeout: Duration,
+    /// how long after being played moves reach spectators , so they can not help the players
+    pub spectator_delay: Duration,
+    /// how long a game is kept once it is over or all its players are gone , so they can come
+    /// back to see how it ended
+    pub keep_finished: Duration,
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        Self {
+            abandon_timeout: Duration::from_secs(60),
+            spectator_delay: Duration::ZERO,
+            keep_finished: Duration::from_secs(300),
+        }
+    }
+}
+
+/// a game as the server keeps it , by game id
+struct ServerGame {
+    game_state: GameState,
+    clock: Clock,
+    /// white and black
+    seats: [Option<Seat>; 2],
+    spectators: Spectators,
+    /// when the game ended
+    ended_at: Option<Instant>,
+}
+
+/// the people watching a game , and what they have been shown of it so far
+struct Spectators {
+    connections: Vec<Connection>,
+    delay: Duration,
+    /// messages waiting for their time , the moves , clocks and the end of the game
+    delayed: VecDeque<(Instant, Message)>,
+    /// moves shown
+    plies: usize,
+    clock: ClockSync,
+    game_end: Option<GameEnd>,
+}
+
+impl Spectators {
+    fn push(&mut self, message: &Message) {
+        self.delayed
+            .push_back((Instant::now() + self.delay, message.clone()));
+        self.release();
+    }
+    /// passes on the messages whose time has come
+    fn release(&mut self) {
+        let now = Instant::now();
+        while self.delayed.front().is_some_and(|(due, _)| *due <= now) {
+            let (_, message) = self.delayed.pop_front().unwrap();
+            match &message {
+                Message::Move { .. } => self.plies += 1,
+                Message::Clock(clock) => self.clock = *clock,
+                Message::GameOver(game_end) => self.game_end = Some(*game_end),
+                _ => {}
+            }
+            // spectators that can not be written to are dropped
+            self.connections
+                .retain_mut(|connection| connection.send(&message).is_ok());
+        }
+    }
+}
+
+struct Seat {
+    user_id: String,
+    /// what the player takes the seat back with , see `Message::Game`
+    token: String,
+    /// None while the player is not connected
+    connection: Option<Connection>,
+    /// when the connection was lost , None while the player is connected
+    left_at: Option<Instant>,
+}
+
+impl Seat {
+    fn leave(&mut self) {
+        self.connection = None;
+        self.left_at = Some(Instant::now());
+    }
+}
+
+/// 128 bits nobody can guess , the hashers of the standard library are keyed at random
+fn new_seat_token() -> String {
+    let random = || RandomState::new().hash_one(Instant::now());
+    format!("{:016x}{:016x}", random(), random())
+}
+
+/// the writing side of a client connection , messages are queued for a thread of its own that
+/// writes them , so no client is waited for while the games are locked
+#[derive(Clone)]
+struct Connection {
+    id: u64,
+    outgoing: mpsc::SyncSender<Vec<u8>>,
+    websocket: bool,
+    /// the one the client joined with
+    encoding: Encoding,
+    /// the version both sides speak , newer messages are not sent
+    version: u32,
+}
+
+impl Connection {
+    /// an error once the client is gone or too far behind
+    fn send(&mut self, message: &Message) -> io::Result<()> {
+        let Some(message) = message.for_version(self.version) else {
+            return Ok(());
+        };
+        let mut bytes = message.encode(self.encoding);
+        if self.websocket {
+            let mut frame = Vec::new();
+            // the server does not mask its frames
+            match self.encoding {
+                Encoding::Json => websocket::write_text(&mut frame, &message.to_json(), None)?,
+                Encoding::Binary => websocket::write_binary(&mut frame, &bytes, None)?,
+            }
+            bytes = frame;
+        } else if self.encoding == Encoding::Json {
+            bytes.push(b'\n');
+        }
+        self.outgoing.try_send(bytes).map_err(|_| {
+            io::Error::new(
+                io::ErrorKind::BrokenPipe,
+                "the client is gone or does not keep up",
+            )
+        })
+    }
+}
+
+/// writes what is queued until the client can not be written to , the connection is shut down
+/// then so its reading side ends too
+fn spawn_writer(mut stream: TcpStream) -> mpsc::SyncSender<Vec<u8>> {
+    let (outgoing, queued) = mpsc::sync_channel::<Vec<u8>>(OUTGOING_QUEUE_LEN);
+    std::thread::spawn(move || {
+        for bytes in queued {
+            if stream
+                .write_all(&bytes)
+                .and_then(|_| stream.flush())
+                .is_err()
+            {
+                let _ = stream.shutdown(Shutdown::Both);
+                break;
+            }
+        }
+    });
+    outgoing
+}
+
+/// listens on the address and serves from a thread of its own , returns the address bound ,
+/// which tells the port when port 0 was asked for , once the server is ready for clients
+pub fn spawn(address: &str, config: ServerConfig) -> io::Result<SocketAddr> {
+    bitboard::build_tables();
+    let listener = TcpListener::bind(address)?;
+    let local_address = listener.local_addr()?;
+    std::thread::spawn(move || serve(listener, config));
+    Ok(local_address)
+}
+
+/// accepts clients until the listener fails
+pub fn serve(listener: TcpListener, config: ServerConfig) -> io::Result<()> {
+    // the first player to join would wait for them otherwise
+    bitboard::build_tables();
+    let games = Games::default();
+    let clock_games = games.clone();
+    std::thread::spawn(move || loop {
+        std::thread::sleep(TICK);
+        check_games(&clock_games, &config);
+    });
+    for stream in listener.incoming() {
+        let stream = stream?;
+        let games = games.clone();
+        std::thread::spawn(move || {
+            // a failing connection only ends its own thread
+            let _ = handle_connection(stream, &games, &config);
+        });
+    }
+    Ok(())
+}
+
+fn handle_connection(stream: TcpStream, games: &Games, config: &ServerConfig) -> io::Result<()> {
+    stream.set_write_timeout(Some(WRITE_TIMEOUT))?;
+    let mut writer = stream.try_clone()?;
+    let mut reader = BufReader::new(stream);
+    // messages start with '{' , an http request with its method
+    let websocket = reader.fill_buf()?.first() == Some(&b'G');
+    if websocket {
+        websocket::server_handshake(&mut reader, &mut writer)?;
+    }
+    let id = NEXT_CONNECTION_ID.fetch_add(1, Ordering::Relaxed);
+    let mut writer = Connection {
+        id,
+        outgoing: spawn_writer(writer),
+        websocket,
+        encoding: Encoding::Json,
+        version: 1,
+    };
+    // pongs are queued like every other frame , the server does not mask them
+    let pong_queue = writer.outgoing.clone();
+    let mut pong = move |payload: &[u8]| {
+        let mut frame = Vec::new();
+        websocket::write_pong(&mut frame, payload, None)?;
+        pong_queue
+            .try_send(frame)
+            .map_err(|_| io::Error::from(io::ErrorKind::BrokenPipe))
+    };
+    let mut framing = if websocket {
+        Framing::WebSocket {
+            role: websocket::Role::Server,
+            pong: &mut pong,
+        }
+    } else {
+        Framing::Plain
+    };
+    // the first message says which game the connection is for , spectators have no color
+    let (game_id, color) = loop {
+        let Some((encoding, bytes)) = protocol::read_message(&mut reader, &mut framing)? else {
+            return Ok(());
+        };
+        // the client is answered the way it spoke
+        writer.encoding = encoding;
+        let message = Message::decode(&bytes, encoding);
+        match &message {
+            Some(Message::ListGames { version }) => {
+                writer.version = (*version).min(PROTOCOL_VERSION);
+                let games = list_games(&games.lock().unwrap());
+                writer.send(&Message::GameList(games))?;
+                continue;
+            }
+            Some(Message::Watch { game_id, version }) => {
+                writer.version = (*version).min(PROTOCOL_VERSION);
+                match games.lock().unwrap().get_mut(game_id) {
+                    Some(game) => game.watch(writer.clone()),
+                    None => {
+                        writer.send(&Message::Error("no such game".to_string()))?;
+                        continue;
+                    }
+                }
+                break (game_id.clone(), None);
+            }
+            _ => {}
+        }
+        let (game_id, user_id, version, seat_token) = match &message {
+            Some(
+                Message::Join {
+                    game_id,
+                    user_id,
+                    version,
+                    seat_token,
+                    ..
+                }
+                | Message::Reconnect {
+                    game_id,
+                    user_id,
+                    version,
+                    seat_token,
+                },
+            ) => (
+                game_id.clone(),
+                user_id.clone(),
+                *version,
+                seat_token.clone(),
+            ),
+            _ => {
+                writer.send(&Message::Error("join a game first".to_string()))?;
+                continue;
+            }
+        };
+        writer.version = version.min(PROTOCOL_VERSION);
+        let mut games = games.lock().unwrap();
+        if !games.contains_key(&game_id) {
+            // only joining creates games
+            let Some(Message::Join {
+                time_control,
+                variant,
+                fen,
+                ..
+            }) = &message
+            else {
+                writer.send(&Message::Error("no such game".to_string()))?;
+                continue;
+            };
+            match ServerGame::new(time_control, *variant, fen.as_deref(), config) {
+                Ok(game) => {
+                    games.insert(game_id.clone(), game);
+                }
+                Err(error) => {
+                    writer.send(&Message::Error(error))?;
+                    continue;
+                }
+            }
+        }
+        let game = games.get_mut(&game_id).unwrap();
+        let reconnecting = matches!(message, Some(Message::Reconnect { .. }));
+        if reconnecting && !game.has_player(&user_id) {
+            writer.send(&Message::Error("not a player of this game".to_string()))?;
+            continue;
+        }
+        match game.seat(user_id, seat_token.as_deref(), writer.clone()) {
+            Ok(color) => break (game_id, Some(color)),
+            Err(error) => writer.send(&Message::Error(error))?,
+        }
+    };
+    let Some(color) = color else {
+        // spectators only listen
+        while protocol::read_message(&mut reader, &mut framing)?.is_some() {
+            writer.send(&Message::Error("spectators can not play".to_string()))?;
+        }
+        if let Some(game) = games.lock().unwrap().get_mut(&game_id) {
+            game.spectators
+                .connections
+                .retain(|connection| connection.id != id);
+        }
+        return Ok(());
+    };
+    while let Some((encoding, bytes)) = protocol::read_message(&mut reader, &mut framing)? {
+        let mut games = games.lock().unwrap();
+        let Some(game) = games.get_mut(&game_id) else {
+            break;
+        };
+        match Message::decode(&bytes, encoding) {
+            Some(Message::Move { ply, uci }) => game.play(color, ply, &uci),
+            Some(Message::DrawOffer) => game.offer_draw(color),
+            Some(Message::DrawDecline) => game.decline_draw(color),
+            Some(Message::Resign) => {
+                if game.game_state.resign(color).is_some() {
+                    game.end();
+                }
+            }
+            Some(Message::Chat { text, .. }) => game.chat(color, &text),
+            Some(_) => game.send_to(color, &Message::Error("unexpected message".to_string())),
+            None => game.send_to(color, &Message::Error("not a message".to_string())),
+        }
+    }
+    if let Some(seat) = games
+        .lock()
+        .unwrap()
+        .get_mut(&game_id)
+        .and_then(|game| game.seats[variant::player_index(color)].as_mut())
+    {
+        // the player may already be back on a new connection
+        if seat
+            .connection
+            .as_ref()
+            .is_some_and(|connection| connection.id == id)
+        {
+            seat.leave();
+        }
+    }
+    Ok(())
+}
+
+/// the games that are not over yet , by game id
+fn list_games(games: &HashMap<String, ServerGame>) -> Vec<GameSummary> {
+    let mut list: Vec<GameSummary> = games
+        .iter()
+        .filter(|(_, game)| !game.game_state.is_game_over())
+        .map(|(game_id, game)| GameSummary {
+            game_id: game_id.clone(),
+            white: game.user_id(PlayerType::White),
+            black: game.user_id(PlayerType::Black),
+            time_control: game.clock.time_control.to_string(),
+            variant: game.game_state.variant(),
+            plies: game.game_state.move_history.len(),
+        })
+        .collect();
+    list.sort_by(|a, b| a.game_id.cmp(&b.game_id));
+    list
+}
+
+/// flags fallen on running clocks , players who stayed away too long and the delayed
+/// messages for spectators , games nobody needs any longer are let go
+fn check_games(games: &Games, config: &ServerConfig) {
+    let mut games = games.lock().unwrap();
+    games.retain(|_, game| !game.is_stale(config.keep_finished));
+    for game in games.values_mut() {
+        game.spectators.release();
+        if game.game_state.is_game_over() {
+            continue;
+        }
+        let active_color = game.game_state.active_color;
+        if game.clock.state == ClockState::Running && game.clock.update_time(active_color).is_none()
+        {
+            game.game_state.flag(active_color);
+            game.end();
+        } else if let Some(absent) = game.absent_player(config.abandon_timeout) {
+            game.game_state.abandon(absent);
+            game.end();
+        }
+    }
+}
+
+impl ServerGame {
+    fn new(
+        time_control: &str,
+        variant: Variant,
+        fen: Option<&str>,
+        config: &ServerConfig,
+    ) -> Result<Self, String> {
+        let time_control =
+            TimeControl::parse(time_control).unwrap_or_else(|| GameType::Blitz.time_control());
+        let fen = fen.unwrap_or(variant.start_fen());
+        let game_state = GameState::from_fen_variant(fen, variant, false)
+            .map_err(|error| format!("bad start position : {}", error))?;
+        let clock = Clock::new(time_control);
+        Ok(Self {
+            game_state,
+            seats: [None, None],
+            spectators: Spectators {
+                connections: Vec::new(),
+                delay: config.spectator_delay,
+                delayed: VecDeque::new(),
+                plies: 0,
+                clock: ClockSync::from_clock(&clock),
+                game_end: None,
+            },
+            clock,
+            ended_at: None,
+        })
+    }
+    /// the first player plays white and the second black , a player whose connection was lost
+    /// gets their seat back with its token , the error says why no seat was given
+    fn seat(
+        &mut self,
+        user_id: String,
+        seat_token: Option<&str>,
+        connection: Connection,
+    ) -> Result<PlayerType, String> {
+        let taken = self
+            .seats
+            .iter()
+            .position(|seat| seat.as_ref().is_some_and(|seat| seat.user_id == user_id));
+        let index = match taken {
+            Some(index) => {
+                let seat = self.seats[index].as_ref().unwrap();
+                if seat_token != Some(seat.token.as_str()) {
+                    return Err("wrong seat token".to_string());
+                }
+                if seat.left_at.is_none() {
+                    return Err("the player is still connected".to_string());
+                }
+                index
+            }
+            None => self
+                .seats
+                .iter()
+                .position(Option::is_none)
+                .ok_or_else(|| "the game is full".to_string())?,
+        };
+        let token = match self.seats[index].take() {
+            Some(seat) => seat.token,
+            None => new_seat_token(),
+        };
+        self.seats[index] = Some(Seat {
+            user_id,
+            token,
+            connection: Some(connection),
+            left_at: None,
+        });
+        // both players and the spectators learn who plays whom
+        for color in [PlayerType::White, PlayerType::Black] {
+            self.send_to(color, &self.game_message(color));
+        }
+        let mut connections = std::mem::take(&mut self.spectators.connections);
+        connections.retain_mut(|connection| {
+            connection
+                .send(&self.watching_message(connection.version))
+                .is_ok()
+        });
+        self.spectators.connections = connections;
+        Ok(if index == 0 {
+            PlayerType::White
+        } else {
+            PlayerType::Black
+        })
+    }
+    fn watch(&mut self, mut connection: Connection) {
+        if connection
+            .send(&self.watching_message(connection.version))
+            .is_ok()
+        {
+            self.spectators.connections.push(connection);
+        }
+    }
+    fn user_id(&self, color: PlayerType) -> Option<String> {
+        self.seats[variant::player_index(color)]
+            .as_ref()
+            .map(|seat| seat.user_id.clone())
+    }
+    fn has_player(&self, user_id: &str) -> bool {
+        self.seats
+            .iter()
+            .flatten()
+            .any(|seat| seat.user_id == user_id)
+    }
+    /// over or left by every player for longer than `keep` , with nothing left for spectators
+    fn is_stale(&self, keep: Duration) -> bool {
+        let ended_long_ago = self
+            .ended_at
+            .is_some_and(|ended_at| ended_at.elapsed() >= keep);
+        let left_long_ago = self.seats.iter().flatten().all(|seat| {
+            seat.left_at
+                .is_some_and(|left_at| left_at.elapsed() >= keep)
+        });
+        (ended_long_ago || left_long_ago) && self.spectators.delayed.is_empty()
+    }
+    /// a player who has been gone for the timeout while the opponent is connected
+    fn absent_player(&self, timeout: Duration) -> Option<PlayerType> {
+        [PlayerType::White, PlayerType::Black]
+            .into_iter()
+            .find(|&color| {
+                let gone_too_long = self.seats[variant::player_index(color)]
+                    .as_ref()
+                    .and_then(|seat| seat.left_at)
+                    .is_some_and(|left_at| left_at.elapsed() >= timeout);
+                let opponent_there = self.seats[variant::player_index(color.opponent())]
+                    .as_ref()
+                    .is_some_and(|seat| seat.connection.is_some());
+                gone_too_long && opponent_there
+            })
+    }
+    /// only sent to the player of the color , it carries their seat token
+    fn game_message(&self, color: PlayerType) -> Message {
+        let seat = self.seats[variant::player_index(color)].as_ref();
+        let version = seat
+            .and_then(|seat| seat.connection.as_ref())
+            .map_or(PROTOCOL_VERSION, |connection| connection.version);
+        let opponent = &self.seats[variant::player_index(color.opponent())];
+        Message::Game {
+            color,
+            opponent: opponent.as_ref().map(|seat| seat.user_id.clone()),
+            time_control: self.clock.time_control.to_string(),
+            variant: self.game_state.variant(),
+            start_fen: self.game_state.move_history.start_fen().to_string(),
+            moves: self.moves(self.game_state.move_history.len()),
+            clock: ClockSync::from_clock(&self.clock),
+            game_end: self.game_state.game_end(),
+            version,
+            seat_token: seat.map(|seat| seat.token.clone()),
+        }
+    }
+    /// the game as far as spectators have been shown it
+    fn watching_message(&self, version: u32) -> Message {
+        Message::Watching {
+            white: self.user_id(PlayerType::White),
+            black: self.user_id(PlayerType::Black),
+            time_control: self.clock.time_control.to_string(),
+            variant: self.game_state.variant(),
+            start_fen: self.game_state.move_history.start_fen().to_string(),
+            moves: self.moves(self.spectators.plies),
+            clock: self.spectators.clock,
+            game_end: self.spectators.game_end,
+            delay_ms: self.spectators.delay.as_millis() as u64,
+            version,
+        }
+    }
+    /// the first moves , in long algebraic notation
+    fn moves(&self, plies: usize) -> Vec<String> {
+        self.game_state.move_history.entries()[..plies]
+            .iter()
+            .map(|entry| entry.chess_move.to_string())
+            .collect()
+    }
+    /// a player whose message can not be written gets it again on joining
+    fn send_to(&mut self, color: PlayerType, message: &Message) {
+        let Some(seat) = self.seats[variant::player_index(color)].as_mut() else {
+            return;
+        };
+        if let Some(connection) = &mut seat.connection {
+            if connection.send(message).is_err() {
+                seat.leave();
+            }
+        }
+    }
+    /// spectators get the moves , clocks and the end of the game too
+    fn broadcast(&mut self, message: &Message) {
+        for color in [PlayerType::White, PlayerType::Black] {
+            self.send_to(color, message);
+        }
+        if matches!(
+            message,
+            Message::Move { .. } | Message::Clock(_) | Message::GameOver(_)
+        ) {
+            self.spectators.push(message);
+        }
+    }
+    /// plays the move if it is legal and the player's turn , otherwise the player is told why
+    /// and gets the game as it stands
+    fn play(&mut self, color: PlayerType, ply: usize, uci_move: &str) {
+        let refusal = if self.game_state.is_game_over() {
+            Some("the game is over")
+        } else if self.seats.iter().any(Option::is_none) {
+            Some("waiting for an opponent")
+        } else if color != self.game_state.active_color || ply != self.game_state.move_history.len()
+        {
+            Some("not your move")
+        } else {
+            None
+        };
+        let chess_move = uci::move_from_uci(&self.game_state, uci_move);
+        let (None, Some(chess_move)) = (refusal, chess_move) else {
+            let error = refusal.unwrap_or("illegal move");
+            self.send_to(color, &Message::Error(error.to_string()));
+            self.send_to(color, &self.game_message(color));
+            return;
+        };
+        self.clock.switch(color);
+        if self.clock.state == ClockState::Flagged(color) {
+            self.game_state.flag(color);
+            self.end();
+            return;
+        }
+        self.game_state.do_move(&chess_move);
+        self.broadcast(&Message::Move {
+            ply,
+            uci: uci_move.to_string(),
+        });
+        if self.game_state.is_game_over() {
+            self.end();
+        } else {
+            self.broadcast(&Message::Clock(ClockSync::from_clock(&self.clock)));
+        }
+    }
+    /// takes a standing offer of the opponent , otherwise passes the offer on
+    fn offer_draw(&mut self, color: PlayerType) {
+        if self.game_state.is_game_over() {
+            self.send_to(color, &Message::Error("the game is over".to_string()));
+        } else if self.game_state.draw_offer == Some(color.opponent()) {
+            self.game_state.accept_draw();
+            self.end();
+        } else {
+            self.game_state.offer_draw(color);
+            self.send_to(color.opponent(), &Message::DrawOffer);
+        }
+    }
+    fn decline_draw(&mut self, color: PlayerType) {
+        if self.game_state.draw_offer == Some(color.opponent()) {
+            self.game_state.decline_draw();
+            self.send_to(color.opponent(), &Message::DrawDecline);
+        }
+    }
+    /// passes the text on to both players , with the name of the sender
+    fn chat(&mut self, color: PlayerType, text: &str) {
+        let Some(seat) = &self.seats[variant::player_index(color)] else {
+            return;
+        };
+        self.broadcast(&Message::Chat {
+            from: seat.user_id.clone(),
+            text: text.chars().take(MAX_CHAT_LEN).collect(),
+        });
+    }
+    /// stops the clock and tells both players how the game ended
+    fn end(&mut self) {
+        self.ended_at = Some(Instant::now());
+        self.clock.pause();
+        self.broadcast(&Message::Clock(ClockSync::from_clock(&self.clock)));
+        if let Some(game_end) = self.game_state.game_end() {
+            self.broadcast(&Message::GameOver(game_end));
+        }
+    }
+}
@@ -0,0 +1,356 @@
+//! the parts of the WebSocket protocol (RFC 6455) the game server and its native clients need ,
+//! the opening handshake and data frames
+use std::io::{self, BufRead, Read, Write};
+
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+
+/// added to the key of the client before hashing it , fixed by the RFC
+const ACCEPT_GUID: &str = "258EAFA5-E914-47DA-95CA-C5AB0DC85B11";
+/// longest message taken in , game messages are far shorter
+const MAX_MESSAGE_LEN: u64 = 1 << 20;
+
+const OP_CONTINUATION: u8 = 0x0;
+const OP_TEXT: u8 = 0x1;
+const OP_BINARY: u8 = 0x2;
+const OP_CLOSE: u8 = 0x8;
+const OP_PING: u8 = 0x9;
+const OP_PONG: u8 = 0xa;
+
+/// which end of the connection reads , clients mask every frame they send and servers none
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Role {
+    Server,
+    Client,
+}
+
+fn invalid_data(what: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, what)
+}
+
+/// what the server answers to the key in `Sec-WebSocket-Accept`
+pub fn accept_key(key: &str) -> String {
+    let digest = sha1_smol::Sha1::from(format!("{}{}", key.trim(), ACCEPT_GUID)).digest();
+    BASE64.encode(digest.bytes())
+}
+
+/// asks the server to speak WebSocket , `key` is 16 random bytes
+pub fn client_handshake(
+    reader: &mut impl BufRead,
+    writer: &mut impl Write,
+    host: &str,
+    path: &str,
+    key: [u8; 16],
+) -> io::Result<()> {
+    let key = BASE64.encode(key);
+    write!(
+        writer,
+        "GET {} HTTP/1.1\r\nHost: {}\r\nUpgrade: websocket\r\nConnection: Upgrade\r\n\
+         Sec-WebSocket-Key: {}\r\nSec-WebSocket-Version: 13\r\n\r\n",
+        path, host, key
+    )?;
+    writer.flush()?;
+    let head = read_head(reader)?;
+    let status_ok = head
+        .first()
+        .is_some_and(|status| status.split_whitespace().nth(1) == Some("101"));
+    if !status_ok {
+        return Err(invalid_data("the server did not switch to websocket"));
+    }
+    if header(&head, "sec-websocket-accept") != Some(&accept_key(&key)) {
+        return Err(invalid_data("the server answered the wrong key"));
+    }
+    Ok(())
+}
+
+/// answers the request of a client , the request line has not been read yet
+pub fn server_handshake(reader: &mut impl BufRead, writer: &mut impl Write) -> io::Result<()> {
+    let head = read_head(reader)?;
+    let Some(key) = header(&head, "sec-websocket-key") else {
+        write!(
+            writer,
+            "HTTP/1.1 400 Bad Request\r\nContent-Length: 0\r\n\r\n"
+        )?;
+        return Err(invalid_data("not a websocket request"));
+    };
+    write!(
+        writer,
+        "HTTP/1.1 101 Switching Protocols\r\nUpgrade: websocket\r\nConnection: Upgrade\r\n\
+         Sec-WebSocket-Accept: {}\r\n\r\n",
+        accept_key(key)
+    )?;
+    writer.flush()
+}
+
+/// the lines up to the empty one ending the head of a request or response
+fn read_head(reader: &mut impl BufRead) -> io::Result<Vec<String>> {
+    let mut head = Vec::new();
+    loop {
+        let mut line = String::new();
+        if reader.read_line(&mut line)? == 0 {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+        let line = line.trim_end();
+        if line.is_empty() {
+            return Ok(head);
+        }
+        head.push(line.to_string());
+    }
+}
+
+/// the value of a header , its name is matched without case
+fn header<'a>(head: &'a [String], name: &str) -> Option<&'a str> {
+    head.iter().find_map(|line| {
+        let (key, value) = line.split_once(':')?;
+        key.trim()
+            .eq_ignore_ascii_case(name)
+            .then_some(value.trim())
+    })
+}
+
+/// a message put together from its frames
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Data {
+    Text(String),
+    Binary(Vec<u8>),
+}
+
+/// sends the text in one frame , clients have to mask what they send and servers must not
+pub fn write_text(writer: &mut impl Write, text: &str, mask: Option<[u8; 4]>) -> io::Result<()> {
+    write_frame(writer, OP_TEXT, text.as_bytes(), mask)
+}
+
+/// same as `write_text` for bytes
+pub fn write_binary(
+    writer: &mut impl Write,
+    bytes: &[u8],
+    mask: Option<[u8; 4]>,
+) -> io::Result<()> {
+    write_frame(writer, OP_BINARY, bytes, mask)
+}
+
+/// tells the other side the connection is done
+pub fn write_close(writer: &mut impl Write, mask: Option<[u8; 4]>) -> io::Result<()> {
+    write_frame(writer, OP_CLOSE, &[], mask)
+}
+
+/// the answer to a ping , with its payload
+pub fn write_pong(
+    writer: &mut impl Write,
+    payload: &[u8],
+    mask: Option<[u8; 4]>,
+) -> io::Result<()> {
+    write_frame(writer, OP_PONG, payload, mask)
+}
+
+fn write_frame(
+    writer: &mut impl Write,
+    opcode: u8,
+    payload: &[u8],
+    mask: Option<[u8; 4]>,
+) -> io::Result<()> {
+    let mut frame = vec![0x80 | opcode];
+    let mask_bit = if mask.is_some() { 0x80 } else { 0 };
+    match payload.len() {
+        len @ 0..=125 => frame.push(mask_bit | len as u8),
+        len @ 126..=0xffff => {
+            frame.push(mask_bit | 126);
+            frame.extend_from_slice(&(len as u16).to_be_bytes());
+        }
+        len => {
+            frame.push(mask_bit | 127);
+            frame.extend_from_slice(&(len as u64).to_be_bytes());
+        }
+    }
+    match mask {
+        Some(mask) => {
+            frame.extend_from_slice(&mask);
+            frame.extend(payload.iter().zip(mask.iter().cycle()).map(|(b, m)| b ^ m));
+        }
+        None => frame.extend_from_slice(payload),
+    }
+    writer.write_all(&frame)?;
+    writer.flush()
+}
+
+/// the next message , None once the other side has closed the connection
+///
+/// pings are answered by `pong` , which gets their payload to send back in a pong frame ,
+/// pongs are skipped , frames masked the wrong way for the role end the connection
+pub fn read_message(
+    reader: &mut impl Read,
+    role: Role,
+    pong: &mut dyn FnMut(&[u8]) -> io::Result<()>,
+) -> io::Result<Option<Data>> {
+    let mut message = Vec::new();
+    // the opcode of the first frame , the others are continuations
+    let mut message_opcode = None;
+    loop {
+        let mut head = [0u8; 2];
+        match reader.read_exact(&mut head) {
+            Err(error) if error.kind() == io::ErrorKind::UnexpectedEof => return Ok(None),
+            result => result?,
+        }
+        let fin = head[0] & 0x80 != 0;
+        let opcode = head[0] & 0x0f;
+        let len = match head[1] & 0x7f {
+            126 => {
+                let mut len = [0u8; 2];
+                reader.read_exact(&mut len)?;
+                u16::from_be_bytes(len) as u64
+            }
+            127 => {
+                let mut len = [0u8; 8];
+                reader.read_exact(&mut len)?;
+                u64::from_be_bytes(len)
+            }
+            len => len as u64,
+        };
+        if message.len() as u64 + len > MAX_MESSAGE_LEN {
+            return Err(invalid_data("websocket message too long"));
+        }
+        if opcode & 0x8 != 0 && (len > 125 || !fin) {
+            return Err(invalid_data("websocket control frame too long or split"));
+        }
+        let masked = head[1] & 0x80 != 0;
+        match (role, masked) {
+            (Role::Server, false) => return Err(invalid_data("unmasked frame from a client")),
+            (Role::Client, true) => return Err(invalid_data("masked frame from a server")),
+            _ => {}
+        }
+        let mut mask = None;
+        if masked {
+            let mut key = [0u8; 4];
+            reader.read_exact(&mut key)?;
+            mask = Some(key);
+        }
+        let mut payload = vec![0u8; len as usize];
+        reader.read_exact(&mut payload)?;
+        if let Some(mask) = mask {
+            for (b, m) in payload.iter_mut().zip(mask.iter().cycle()) {
+                *b ^= m;
+            }
+        }
+        match opcode {
+            OP_CLOSE => return Ok(None),
+            OP_PING => pong(&payload)?,
+            OP_PONG => {}
+            OP_TEXT | OP_BINARY | OP_CONTINUATION => {
+                message_opcode = message_opcode.or(Some(opcode));
+                message.extend_from_slice(&payload);
+                if !fin {
+                    continue;
+                }
+                return match message_opcode {
+                    Some(OP_BINARY) => Ok(Some(Data::Binary(message))),
+                    Some(OP_TEXT) => String::from_utf8(message)
+                        .map(|text| Some(Data::Text(text)))
+                        .map_err(|_| invalid_data("websocket text is not utf-8")),
+                    _ => Err(invalid_data("continuation without a first frame")),
+                };
+            }
+            _ => return Err(invalid_data("unknown websocket opcode")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::BufReader;
+
+    #[test]
+    fn accept_key_is_the_one_of_the_rfc() {
+        assert_eq!(
+            accept_key("dGhlIHNhbXBsZSBub25jZQ=="),
+            "s3pPLMBiTxaQ9kYGzzhZRbK+xOo="
+        );
+    }
+
+    /// what the role reads from the bytes , pings must not come up
+    fn read(bytes: &[u8], role: Role) -> io::Result<Option<Data>> {
+        read_message(&mut &bytes[..], role, &mut |_| panic!("unexpected ping"))
+    }
+
+    #[test]
+    fn frames_round_trip() {
+        let long_text = "x".repeat(70_000);
+        // clients mask their frames , the server reads them
+        for (text, mask, role) in [
+            ("short", None, Role::Client),
+            ("masked ♞", Some([1, 2, 3, 4]), Role::Server),
+            ("a".repeat(300).as_str(), Some([9, 8, 7, 6]), Role::Server),
+            (long_text.as_str(), None, Role::Client),
+        ] {
+            let mut frame = Vec::new();
+            write_text(&mut frame, text, mask).unwrap();
+            assert_eq!(
+                read(&frame, role).unwrap(),
+                Some(Data::Text(text.to_string()))
+            );
+        }
+        let mut frame = Vec::new();
+        write_binary(&mut frame, &[0, 255, 7], Some([5; 4])).unwrap();
+        assert_eq!(
+            read(&frame, Role::Server).unwrap(),
+            Some(Data::Binary(vec![0, 255, 7]))
+        );
+        let mut closed = Vec::new();
+        write_close(&mut closed, None).unwrap();
+        assert_eq!(read(&closed, Role::Client).unwrap(), None);
+    }
+
+    #[test]
+    fn pings_are_answered_with_their_payload() {
+        let mut frames = Vec::new();
+        write_frame(&mut frames, OP_PING, b"still there?", Some([3; 4])).unwrap();
+        write_pong(&mut frames, b"a pong nobody asked for", Some([4; 4])).unwrap();
+        write_text(&mut frames, "e2e4", Some([5; 4])).unwrap();
+        let mut pongs = Vec::new();
+        let data = read_message(&mut frames.as_slice(), Role::Server, &mut |payload| {
+            write_pong(&mut pongs, payload, None)
+        })
+        .unwrap();
+        assert_eq!(data, Some(Data::Text("e2e4".to_string())));
+        let mut expected = Vec::new();
+        write_frame(&mut expected, OP_PONG, b"still there?", None).unwrap();
+        assert_eq!(pongs, expected);
+    }
+
+    #[test]
+    fn frames_masked_the_wrong_way_are_refused() {
+        let mut unmasked = Vec::new();
+        write_text(&mut unmasked, "from a client", None).unwrap();
+        assert!(read(&unmasked, Role::Server).is_err());
+        let mut masked = Vec::new();
+        write_text(&mut masked, "from a server", Some([1; 4])).unwrap();
+        assert!(read(&masked, Role::Client).is_err());
+        // a control frame with more than 125 bytes
+        let mut ping = Vec::new();
+        write_frame(&mut ping, OP_PING, &[0; 126], Some([1; 4])).unwrap();
+        assert!(read_message(&mut ping.as_slice(), Role::Server, &mut |_| Ok(())).is_err());
+    }
+
+    #[test]
+    fn handshake_agrees_on_the_key() {
+        let mut request = Vec::new();
+        // the client fails on the missing answer , what it sent is still there
+        let _ = client_handshake(
+            &mut BufReader::new(&[][..]),
+            &mut request,
+            "localhost",
+            "/",
+            [7; 16],
+        );
+        let mut response = Vec::new();
+        server_handshake(&mut BufReader::new(request.as_slice()), &mut response).unwrap();
+        client_handshake(
+            &mut BufReader::new(response.as_slice()),
+            &mut Vec::new(),
+            "localhost",
+            "/",
+            [7; 16],
+        )
+        .unwrap();
+    }
+}
@@ -0,0 +1,55 @@
+use rusty_chess::common::chess960;
+use rusty_chess::common::logic::GameState;
+use rusty_chess::common::pgn::{self, PgnGame};
+
+fn play(game_state: &mut GameState, moves: &[&str]) {
+    for san in moves {
+        let chess_move = game_state
+            .move_from_san(san)
+            .unwrap_or_else(|| panic!("{} is not legal", san));
+        game_state.do_move(&chess_move);
+    }
+}
+
+#[test]
+fn castling_from_any_start_position() {
+    // position 0 is BBQNNRKR , the king starts on g1 with the rooks on f1 and h1
+    let mut game_state = GameState::from_fen_chess960(&chess960::start_fen(0).unwrap()).unwrap();
+    play(
+        &mut game_state,
+        &["Nc3", "Nc6", "Nf3", "Nf6", "d4", "d5", "Qd2", "Qd7"],
+    );
+    let long = game_state.move_from_san("O-O-O").unwrap();
+    // the move goes to the rook the king castles with
+    assert_eq!(long.to_string(), "g1f1");
+    play(&mut game_state, &["O-O-O"]);
+    assert!(game_state
+        .to_fen()
+        .starts_with("bb3rkr/pppqpppp/2n2n2/3p4/3P4/2N2N2/PPPQPPPP/BBKR3R b kq - "));
+    play(&mut game_state, &["O-O-O"]);
+    assert!(game_state
+        .to_fen()
+        .starts_with("bbkr3r/pppqpppp/2n2n2/3p4/3P4/2N2N2/PPPQPPPP/BBKR3R w - - "));
+    game_state.undo_move();
+    game_state.undo_move();
+    assert!(game_state
+        .to_fen()
+        .starts_with("bb3rkr/pppqpppp/2n2n2/3p4/3P4/2N2N2/PPPQPPPP/BB3RKR w KQkq - "));
+}
+
+#[test]
+fn games_round_trip_through_pgn() {
+    let mut game_state = GameState::from_fen_chess960(&chess960::start_fen(0).unwrap()).unwrap();
+    play(
+        &mut game_state,
+        &[
+            "Nc3", "Nc6", "Nf3", "Nf6", "d4", "d5", "Qd2", "Qd7", "O-O-O",
+        ],
+    );
+    let pgn_text = PgnGame::from_game_state(&game_state, None).to_pgn();
+    let game = &pgn::parse_pgn(&pgn_text)[0];
+    assert_eq!(game.tag("Variant"), Some("Chess960"));
+    let replayed = game.replay().unwrap();
+    assert!(replayed.is_chess960());
+    assert_eq!(replayed.to_fen(), game_state.to_fen());
+}
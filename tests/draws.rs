@@ -0,0 +1,165 @@
+use std::time::{Duration, Instant};
+
+use rusty_chess::client::GameClient;
+use rusty_chess::common::game_modes::GameType;
+use rusty_chess::common::logic::{
+    DrawReason, GameEnd, GameState, MoveResult, PlayerType, START_FEN,
+};
+use rusty_chess::common::pgn::PgnGame;
+use rusty_chess::common::variant::Variant;
+
+fn play(game_state: &mut GameState, moves: &[&str]) -> MoveResult {
+    let mut move_result = MoveResult::Normal;
+    for san in moves {
+        let chess_move = game_state
+            .move_from_san(san)
+            .unwrap_or_else(|| panic!("{} is not legal", san));
+        move_result = game_state.do_move(&chess_move);
+    }
+    move_result
+}
+
+const KNIGHT_SHUFFLE: [&str; 4] = ["Nf3", "Nf6", "Ng1", "Ng8"];
+
+#[test]
+fn halfmove_clock_resets_on_pawn_moves_and_captures() {
+    let mut game_state = GameState::from_fen(START_FEN).unwrap();
+    play(&mut game_state, &["Nf3", "Nc6"]);
+    assert_eq!(game_state.half_move_clock, 2);
+    assert_eq!(game_state.full_move_no, 2);
+    play(&mut game_state, &["e4"]);
+    assert_eq!(game_state.half_move_clock, 0);
+    play(&mut game_state, &["Nd4", "Nxd4"]);
+    assert_eq!(game_state.half_move_clock, 0);
+    assert_eq!(game_state.full_move_no, 3);
+}
+
+#[test]
+fn threefold_repetition_can_be_claimed() {
+    let mut game_state = GameState::from_fen(START_FEN).unwrap();
+    play(&mut game_state, &KNIGHT_SHUFFLE);
+    assert_eq!(game_state.repetition_count(), 2);
+    assert_eq!(game_state.claimable_draw(), None);
+    assert!(matches!(
+        play(&mut game_state, &KNIGHT_SHUFFLE),
+        MoveResult::Normal
+    ));
+    assert_eq!(
+        game_state.claimable_draw(),
+        Some(DrawReason::ThreefoldRepetition)
+    );
+    assert!(matches!(
+        game_state.claim_draw(),
+        Some(MoveResult::GameEnd(GameEnd::Draw(
+            DrawReason::ThreefoldRepetition
+        )))
+    ));
+    assert!(game_state.is_game_over());
+    let pgn_game = PgnGame::from_game_state(&game_state, None);
+    assert_eq!(pgn_game.tag("Result"), Some("1/2-1/2"));
+    let chess_move = game_state.move_from_san("Nf3").unwrap();
+    assert!(matches!(
+        game_state.do_move(&chess_move),
+        MoveResult::InvalidMove
+    ));
+    game_state.undo_move();
+    assert!(!game_state.is_game_over());
+}
+
+#[test]
+fn fivefold_repetition_ends_the_game() {
+    let mut game_state = GameState::from_fen(START_FEN).unwrap();
+    for _ in 0..3 {
+        play(&mut game_state, &KNIGHT_SHUFFLE);
+    }
+    assert!(matches!(
+        play(&mut game_state, &KNIGHT_SHUFFLE),
+        MoveResult::GameEnd(GameEnd::Draw(DrawReason::FivefoldRepetition))
+    ));
+}
+
+#[test]
+fn en_passant_square_only_counts_when_the_capture_is_possible() {
+    // after 1. e4 no black pawn can take en passant , so the position repeats
+    let mut game_state = GameState::from_fen(START_FEN).unwrap();
+    play(&mut game_state, &["e4", "Nf6", "Nf3", "Ng8", "Ng1"]);
+    play(&mut game_state, &["Nf6", "Nf3", "Ng8", "Ng1"]);
+    assert_eq!(game_state.repetition_count(), 3);
+}
+
+#[test]
+fn fifty_and_seventy_five_move_rules() {
+    let mut game_state = GameState::from_fen("4k3/8/8/8/8/8/4P3/R3K3 w - - 99 80").unwrap();
+    assert_eq!(game_state.claimable_draw(), None);
+    play(&mut game_state, &["Ra2"]);
+    assert_eq!(game_state.claimable_draw(), Some(DrawReason::FiftyMoveRule));
+
+    let mut game_state = GameState::from_fen("4k3/8/8/8/8/8/4P3/R3K3 w - - 149 80").unwrap();
+    assert!(matches!(
+        play(&mut game_state, &["Ra2"]),
+        MoveResult::GameEnd(GameEnd::Draw(DrawReason::SeventyFiveMoveRule))
+    ));
+}
+
+#[test]
+fn insufficient_material() {
+    let dead = [
+        "4k3/8/8/8/8/8/8/4K3 w - - 0 1",
+        "4k3/8/8/8/8/8/8/4KN2 w - - 0 1",
+        "4k3/8/8/8/8/8/8/4KB2 w - - 0 1",
+        "4kb2/8/8/8/8/8/8/2B1K3 w - - 0 1",
+        "4k3/8/8/8/8/8/8/B1B1K3 w - - 0 1",
+    ];
+    for fen in dead {
+        let game_state = GameState::from_fen(fen).unwrap();
+        assert!(game_state.is_insufficient_material(), "{}", fen);
+        assert_eq!(
+            game_state.game_end(),
+            Some(GameEnd::Draw(DrawReason::InsufficientMaterial)),
+            "{}",
+            fen
+        );
+    }
+    let alive = [
+        "4k3/8/8/8/8/8/5P2/4K3 w - - 0 1",
+        "4kb2/8/8/8/8/8/8/3BK3 w - - 0 1",
+        "4k3/8/8/8/8/8/8/3NKN2 w - - 0 1",
+        "4kn2/8/8/8/8/8/8/4KB2 w - - 0 1",
+    ];
+    for fen in alive {
+        let game_state = GameState::from_fen(fen).unwrap();
+        assert!(!game_state.is_insufficient_material(), "{}", fen);
+    }
+}
+
+#[test]
+fn capturing_into_a_dead_position_ends_the_game() {
+    let mut game_state = GameState::from_fen("4k3/8/8/8/8/8/4r3/4KB2 w - - 0 1").unwrap();
+    assert!(matches!(
+        play(&mut game_state, &["Kxe2"]),
+        MoveResult::GameEnd(GameEnd::Draw(DrawReason::InsufficientMaterial))
+    ));
+}
+
+#[test]
+fn computer_player_repeats_a_position_when_behind() {
+    let mut client = GameClient::new_vs_computer(GameType::Blitz, PlayerType::Black, 3, None);
+    client
+        .update_fen(
+            "1n2k3/8/8/8/2Q5/8/8/R3K3 b - - 0 1",
+            Variant::Standard,
+            false,
+        )
+        .unwrap();
+    play(&mut client.game_state, &["Nc6", "Kd1", "Nb8", "Ke1"]);
+    let start = Instant::now();
+    while client.update_computer().is_none() {
+        assert!(start.elapsed() < Duration::from_secs(10), "no move");
+        std::thread::sleep(Duration::from_millis(10));
+    }
+    // Nc6 hangs the knight but brings back the position after the first move , which the
+    // search scores as a draw
+    let entries = client.game_state.move_history.entries();
+    assert_eq!(entries.last().unwrap().notation, "Nc6");
+    assert_eq!(client.game_state.repetition_count(), 2);
+}
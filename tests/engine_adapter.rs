@@ -0,0 +1,97 @@
+#![cfg(unix)]
+
+use std::time::{Duration, Instant};
+
+use rusty_chess::client::GameClient;
+use rusty_chess::common::game_modes::GameType;
+use rusty_chess::common::logic::{GameState, PlayerType};
+use rusty_chess::common::uci::EngineScore;
+use rusty_chess::engine_adapter::{Analysis, ExternalEngine};
+
+const STUB_ENGINE: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/stub_uci_engine.sh");
+
+/// calls `poll` until it gives something , the engines answer on their own threads
+fn wait_for<T>(mut poll: impl FnMut() -> Option<T>) -> T {
+    let start = Instant::now();
+    loop {
+        if let Some(value) = poll() {
+            return value;
+        }
+        assert!(start.elapsed() < Duration::from_secs(10), "no answer");
+        std::thread::sleep(Duration::from_millis(10));
+    }
+}
+
+#[test]
+fn handshake_reads_the_engine_name() {
+    let engine = ExternalEngine::spawn(STUB_ENGINE).unwrap();
+    assert_eq!(engine.name, "stub engine");
+}
+
+#[test]
+fn missing_program_is_an_error() {
+    assert!(ExternalEngine::spawn("/no/such/engine").is_err());
+    let starting = ExternalEngine::start("/no/such/engine");
+    wait_for(|| starting.poll().err());
+}
+
+#[test]
+fn starting_does_not_wait_for_the_engine() {
+    // cat never answers uci
+    let start = Instant::now();
+    let starting = ExternalEngine::start("cat");
+    assert!(matches!(starting.poll(), Ok(None)));
+    assert!(start.elapsed() < Duration::from_secs(1));
+    let starting = ExternalEngine::start(STUB_ENGINE);
+    let engine = wait_for(|| starting.poll().unwrap());
+    assert_eq!(engine.name, "stub engine");
+}
+
+#[test]
+fn analysis_shows_score_and_pv_in_san() {
+    let game_state = GameState::default();
+    let mut analysis = Analysis::start(STUB_ENGINE);
+    let info = wait_for(|| {
+        analysis.update(&game_state).unwrap();
+        analysis.info.clone()
+    });
+    assert_eq!(info.depth, Some(3));
+    assert_eq!(info.score, Some(EngineScore::Mate(3)));
+    assert_eq!(analysis.pv_san, vec!["e4", "e5"]);
+}
+
+#[test]
+fn external_engine_plays_as_opponent() {
+    let mut client = GameClient::new_vs_computer(
+        GameType::Blitz,
+        PlayerType::Black,
+        1,
+        Some(STUB_ENGINE.to_string()),
+    );
+    assert!(client.update_computer().is_none());
+    let chess_move = client.game_state.move_from_san("e4").unwrap();
+    client.game_state.do_move(&chess_move);
+    wait_for(|| client.update_computer());
+    let computer = client.computer.as_ref().unwrap();
+    assert_eq!(computer.engine_error, None);
+    let entries = client.game_state.move_history.entries();
+    assert_eq!(entries.last().unwrap().notation, "e5");
+    assert_eq!(client.game_state.active_color, PlayerType::White);
+}
+
+#[test]
+fn built_in_engine_plays_when_the_external_one_quits() {
+    let mut client = GameClient::new_vs_computer(
+        GameType::Blitz,
+        PlayerType::Black,
+        1,
+        Some(STUB_ENGINE.to_string()),
+    );
+    let chess_move = client.game_state.move_from_san("d4").unwrap();
+    client.game_state.do_move(&chess_move);
+    wait_for(|| client.update_computer());
+    let computer = client.computer.as_ref().unwrap();
+    assert_eq!(computer.engine_error.as_deref(), Some("the engine quit"));
+    assert!(!computer.is_thinking());
+    assert_eq!(client.game_state.move_history.len(), 2);
+}
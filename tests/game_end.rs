@@ -0,0 +1,167 @@
+use rusty_chess::common::logic::{
+    DrawReason, GameEnd, GameState, MoveResult, PlayerType, WinReason, START_FEN,
+};
+use rusty_chess::common::pgn::PgnGame;
+
+fn play(game_state: &mut GameState, moves: &[&str]) -> MoveResult {
+    let mut move_result = MoveResult::Normal;
+    for san in moves {
+        let chess_move = game_state
+            .move_from_san(san)
+            .unwrap_or_else(|| panic!("{} is not legal", san));
+        move_result = game_state.do_move(&chess_move);
+    }
+    move_result
+}
+
+fn result_tag(game_state: &GameState) -> String {
+    PgnGame::from_game_state(game_state, None)
+        .tag("Result")
+        .unwrap()
+        .to_string()
+}
+
+#[test]
+fn checkmate_names_the_winner() {
+    let mut game_state = GameState::from_fen(START_FEN).unwrap();
+    let move_result = play(&mut game_state, &["f3", "e5", "g4", "Qh4#"]);
+    let MoveResult::GameEnd(game_end) = move_result else {
+        panic!("{:?}", move_result);
+    };
+    assert_eq!(
+        game_end,
+        GameEnd::Win {
+            winner: PlayerType::Black,
+            reason: WinReason::Checkmate
+        }
+    );
+    assert_eq!(game_end.to_string(), "Black wins by checkmate");
+    assert_eq!(result_tag(&game_state), "0-1");
+}
+
+#[test]
+fn stalemate_is_a_draw() {
+    let game_state = GameState::from_fen("7k/5Q2/6K1/8/8/8/8/8 b - - 0 1").unwrap();
+    assert_eq!(
+        game_state.game_end(),
+        Some(GameEnd::Draw(DrawReason::Stalemate))
+    );
+    assert_eq!(result_tag(&game_state), "1/2-1/2");
+}
+
+#[test]
+fn resignation() {
+    let mut game_state = GameState::from_fen(START_FEN).unwrap();
+    play(&mut game_state, &["e4"]);
+    assert!(matches!(
+        game_state.resign(PlayerType::White),
+        Some(MoveResult::GameEnd(GameEnd::Win {
+            winner: PlayerType::Black,
+            reason: WinReason::Resignation
+        }))
+    ));
+    assert_eq!(result_tag(&game_state), "0-1");
+    // a finished game can not end again
+    assert!(game_state.resign(PlayerType::Black).is_none());
+}
+
+#[test]
+fn timeout() {
+    let mut game_state = GameState::from_fen(START_FEN).unwrap();
+    assert!(matches!(
+        game_state.flag(PlayerType::Black),
+        Some(MoveResult::GameEnd(GameEnd::Win {
+            winner: PlayerType::White,
+            reason: WinReason::Timeout
+        }))
+    ));
+    assert_eq!(result_tag(&game_state), "1-0");
+}
+
+#[test]
+fn timeout_against_insufficient_material() {
+    // a lone king can never mate , so white running out of time draws
+    let mut game_state = GameState::from_fen("4k3/8/8/8/8/8/4P3/4K3 w - - 0 1").unwrap();
+    assert!(matches!(
+        game_state.flag(PlayerType::White),
+        Some(MoveResult::GameEnd(GameEnd::Draw(
+            DrawReason::TimeoutVsInsufficientMaterial
+        )))
+    ));
+    // a knight can mate when the pawn blocks the king in
+    let mut game_state = GameState::from_fen("4k3/8/8/8/8/8/4P3/4K1n1 w - - 0 1").unwrap();
+    assert!(matches!(
+        game_state.flag(PlayerType::White),
+        Some(MoveResult::GameEnd(GameEnd::Win {
+            winner: PlayerType::Black,
+            ..
+        }))
+    ));
+}
+
+#[test]
+fn abandonment() {
+    let mut game_state = GameState::from_fen(START_FEN).unwrap();
+    assert!(matches!(
+        game_state.abandon(PlayerType::White),
+        Some(MoveResult::GameEnd(GameEnd::Win {
+            winner: PlayerType::Black,
+            reason: WinReason::Abandonment
+        }))
+    ));
+    assert_eq!(result_tag(&game_state), "0-1");
+    let mut game_state = GameState::from_fen("4k3/8/8/8/8/8/4P3/4K3 w - - 0 1").unwrap();
+    assert!(matches!(
+        game_state.abandon(PlayerType::White),
+        Some(MoveResult::GameEnd(GameEnd::Draw(
+            DrawReason::AbandonmentVsInsufficientMaterial
+        )))
+    ));
+}
+
+#[test]
+fn mating_material() {
+    let cases = [
+        ("4k3/8/8/8/8/8/8/4KB2 w - - 0 1", false),
+        ("4k3/8/8/8/8/8/8/3NKN2 w - - 0 1", true),
+        ("4kb2/8/8/8/8/8/8/2B1K3 w - - 0 1", false),
+        ("4kb2/8/8/8/8/8/8/3BK3 w - - 0 1", true),
+        ("4k3/4p3/8/8/8/8/8/4KB2 w - - 0 1", true),
+        ("4k3/8/8/8/8/8/8/4KR2 w - - 0 1", true),
+    ];
+    for (fen, expected) in cases {
+        let game_state = GameState::from_fen(fen).unwrap();
+        assert_eq!(
+            game_state.has_mating_material(PlayerType::White),
+            expected,
+            "{}",
+            fen
+        );
+    }
+}
+
+#[test]
+fn draw_offers() {
+    let mut game_state = GameState::from_fen(START_FEN).unwrap();
+    // moving turns the offer down
+    game_state.offer_draw(PlayerType::White);
+    play(&mut game_state, &["e4"]);
+    assert_eq!(game_state.draw_offer, Some(PlayerType::White));
+    play(&mut game_state, &["e5"]);
+    assert_eq!(game_state.draw_offer, None);
+    assert!(game_state.accept_draw().is_none());
+
+    game_state.offer_draw(PlayerType::White);
+    game_state.decline_draw();
+    assert!(game_state.accept_draw().is_none());
+
+    game_state.offer_draw(PlayerType::White);
+    assert!(matches!(
+        game_state.accept_draw(),
+        Some(MoveResult::GameEnd(GameEnd::Draw(DrawReason::Agreement)))
+    ));
+    assert_eq!(
+        game_state.game_end().unwrap().to_string(),
+        "Draw by agreement"
+    );
+}
@@ -0,0 +1,705 @@
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{Shutdown, TcpStream};
+use std::time::{Duration, Instant};
+
+use rusty_chess::client::{transport, GameClient, GameListing};
+use rusty_chess::common::binary;
+use rusty_chess::common::logic::{DrawReason, GameEnd, PlayerType, WinReason};
+use rusty_chess::common::protocol::{Encoding, GameSummary, Message, PROTOCOL_VERSION};
+use rusty_chess::common::time_control::TimeControl;
+use rusty_chess::common::uci;
+use rusty_chess::common::variant::Variant;
+use rusty_chess::server::{self, ServerConfig};
+use rusty_chess::websocket;
+
+fn join(address: &str, game_id: &str, user_id: &str, time_control: &str) -> GameClient {
+    GameClient::new_online(
+        address,
+        game_id,
+        user_id,
+        &TimeControl::parse(time_control).unwrap(),
+        Variant::Standard,
+        None,
+        Encoding::Binary,
+    )
+    .unwrap()
+}
+
+/// updates the clients until the condition holds , panics after a few seconds
+fn wait_for(clients: &mut [&mut GameClient], condition: impl Fn(&[&mut GameClient]) -> bool) {
+    let start = Instant::now();
+    while !condition(clients) {
+        assert!(start.elapsed() < Duration::from_secs(5), "timed out");
+        for client in clients.iter_mut() {
+            client.update_online();
+        }
+        std::thread::sleep(Duration::from_millis(10));
+    }
+}
+
+fn play(client: &mut GameClient, uci_move: &str) {
+    let chess_move = uci::move_from_uci(&client.game_state, uci_move).unwrap();
+    client.game_state.do_move(&chess_move);
+}
+
+#[test]
+fn moves_reach_both_players() {
+    let address = server::spawn("127.0.0.1:0", ServerConfig::default())
+        .unwrap()
+        .to_string();
+    let mut white = join(&address, "game", "alice", "300+2");
+    let mut black = join(&address, "game", "bob", "60");
+    wait_for(&mut [&mut white, &mut black], |clients| {
+        clients
+            .iter()
+            .all(|client| client.online.as_ref().unwrap().opponent.is_some())
+    });
+    assert_eq!(
+        white.online.as_ref().unwrap().color,
+        Some(PlayerType::White)
+    );
+    assert_eq!(
+        black.online.as_ref().unwrap().color,
+        Some(PlayerType::Black)
+    );
+    // the game was made with the time control of the first player
+    assert_eq!(black.clock.time_control.to_string(), "300+2");
+    assert!(!black.is_human_turn());
+
+    play(&mut white, "e2e4");
+    wait_for(&mut [&mut white, &mut black], |clients| {
+        clients[1].game_state.move_history.len() == 1
+    });
+    assert!(black.is_human_turn());
+    play(&mut black, "e7e5");
+    wait_for(&mut [&mut white, &mut black], |clients| {
+        clients[0].game_state.move_history.len() == 2
+    });
+    assert_eq!(white.game_state.to_fen(), black.game_state.to_fen());
+    // the increment of the first move was given on the server
+    assert!(white.clock.white_time > Duration::from_secs(300));
+}
+
+#[test]
+fn server_refuses_illegal_moves_and_a_third_player() {
+    let address = server::spawn("127.0.0.1:0", ServerConfig::default())
+        .unwrap()
+        .to_string();
+    let mut white = join(&address, "game", "alice", "300");
+    let mut black = join(&address, "game", "bob", "300");
+    wait_for(&mut [&mut white, &mut black], |clients| {
+        clients[0].online.as_ref().unwrap().opponent.is_some()
+    });
+
+    let stream = TcpStream::connect(&address).unwrap();
+    let mut lines = BufReader::new(stream.try_clone().unwrap()).lines();
+    let send = |message: &Message| writeln!(&stream, "{}", message.to_json()).unwrap();
+    let mut next_message = || Message::from_json(&lines.next().unwrap().unwrap()).unwrap();
+    send(&Message::Move {
+        ply: 0,
+        uci: "e2e4".to_string(),
+    });
+    assert_eq!(
+        next_message(),
+        Message::Error("join a game first".to_string())
+    );
+    send(&Message::Join {
+        game_id: "game".to_string(),
+        user_id: "carol".to_string(),
+        time_control: "300".to_string(),
+        variant: Variant::Standard,
+        fen: None,
+        version: PROTOCOL_VERSION,
+        seat_token: None,
+    });
+    assert_eq!(
+        next_message(),
+        Message::Error("the game is full".to_string())
+    );
+    send(&Message::Reconnect {
+        game_id: "game".to_string(),
+        user_id: "carol".to_string(),
+        version: PROTOCOL_VERSION,
+        seat_token: None,
+    });
+    assert_eq!(
+        next_message(),
+        Message::Error("not a player of this game".to_string())
+    );
+    send(&Message::Reconnect {
+        game_id: "other game".to_string(),
+        user_id: "alice".to_string(),
+        version: PROTOCOL_VERSION,
+        seat_token: None,
+    });
+    assert_eq!(next_message(), Message::Error("no such game".to_string()));
+
+    // white tries a move that is not legal , the server sends the game back
+    white
+        .online
+        .as_mut()
+        .unwrap()
+        .send(&Message::Move {
+            ply: 0,
+            uci: "e2e5".to_string(),
+        })
+        .unwrap();
+    wait_for(&mut [&mut white, &mut black], |clients| {
+        clients[0].online.as_ref().unwrap().error.is_some()
+    });
+    assert_eq!(
+        white.online.as_ref().unwrap().error.as_deref(),
+        Some("illegal move")
+    );
+    assert!(white.game_state.move_history.is_empty());
+    assert!(black.game_state.move_history.is_empty());
+}
+
+#[test]
+fn server_clock_flags_the_player_to_move() {
+    let address = server::spawn("127.0.0.1:0", ServerConfig::default())
+        .unwrap()
+        .to_string();
+    let mut white = join(&address, "game", "alice", "1");
+    let mut black = join(&address, "game", "bob", "1");
+    wait_for(&mut [&mut white, &mut black], |clients| {
+        clients[0].online.as_ref().unwrap().opponent.is_some()
+    });
+    play(&mut white, "e2e4");
+    // black never answers
+    wait_for(&mut [&mut white, &mut black], |clients| {
+        clients
+            .iter()
+            .all(|client| client.game_state.is_game_over())
+    });
+    let timeout = GameEnd::Win {
+        winner: PlayerType::White,
+        reason: WinReason::Timeout,
+    };
+    assert_eq!(white.game_state.game_end(), Some(timeout));
+    assert_eq!(black.game_state.game_end(), Some(timeout));
+    assert_eq!(black.clock.black_time, Duration::ZERO);
+}
+
+#[test]
+fn websocket_and_tcp_players_share_a_game() {
+    let address = server::spawn("127.0.0.1:0", ServerConfig::default())
+        .unwrap()
+        .to_string();
+    let mut white = join(&format!("ws://{}/", address), "game", "alice", "300");
+    let mut black = join(&address, "game", "bob", "300");
+    wait_for(&mut [&mut white, &mut black], |clients| {
+        clients[0].online.as_ref().unwrap().opponent.is_some()
+    });
+    play(&mut white, "d2d4");
+    wait_for(&mut [&mut white, &mut black], |clients| {
+        clients[1].game_state.move_history.len() == 1
+    });
+    play(&mut black, "g8f6");
+    wait_for(&mut [&mut white, &mut black], |clients| {
+        clients[0].game_state.move_history.len() == 2
+    });
+    assert_eq!(white.game_state.to_fen(), black.game_state.to_fen());
+    assert!(white.online.as_ref().unwrap().error.is_none());
+}
+
+#[test]
+fn websocket_pings_are_answered_and_unmasked_frames_refused() {
+    let address = server::spawn("127.0.0.1:0", ServerConfig::default())
+        .unwrap()
+        .to_string();
+    let mut stream = TcpStream::connect(&address).unwrap();
+    stream
+        .set_read_timeout(Some(Duration::from_secs(5)))
+        .unwrap();
+    let mut reader = BufReader::new(stream.try_clone().unwrap());
+    websocket::client_handshake(&mut reader, &mut stream, &address, "/", [7; 16]).unwrap();
+    // a ping masked with zeros , its payload is sent as is
+    stream
+        .write_all(&[0x89, 0x80 | 4, 0, 0, 0, 0, b'p', b'i', b'n', b'g'])
+        .unwrap();
+    let mut pong = [0; 6];
+    reader.read_exact(&mut pong).unwrap();
+    assert_eq!(pong, [0x8a, 4, b'p', b'i', b'n', b'g']);
+    // a text frame without a mask ends the connection
+    stream.write_all(&[0x81, 2, b'{', b'}']).unwrap();
+    let mut rest = Vec::new();
+    let _ = reader.read_to_end(&mut rest);
+    assert!(rest.is_empty() || rest[0] == 0x88, "{:?}", rest);
+}
+
+#[test]
+fn transports_speak_the_same_messages() {
+    let address = server::spawn("127.0.0.1:0", ServerConfig::default())
+        .unwrap()
+        .to_string();
+    for (user_id, transport_address, encoding) in [
+        ("alice", address.clone(), Encoding::Binary),
+        ("bob", format!("ws://{}", address), Encoding::Json),
+    ] {
+        let mut transport = transport::connect(&transport_address, encoding).unwrap();
+        transport
+            .send(&Message::Join {
+                game_id: "game".to_string(),
+                user_id: user_id.to_string(),
+                time_control: "300".to_string(),
+                variant: Variant::Standard,
+                fen: None,
+                version: PROTOCOL_VERSION,
+                seat_token: None,
+            })
+            .unwrap();
+        let start = Instant::now();
+        let message = loop {
+            if let Some(message) = transport.try_recv().unwrap() {
+                break message;
+            }
+            assert!(start.elapsed() < Duration::from_secs(5), "timed out");
+            std::thread::sleep(Duration::from_millis(10));
+        };
+        assert!(
+            matches!(&message, Message::Game { moves, .. } if moves.is_empty()),
+            "{:?}",
+            message
+        );
+    }
+}
+
+#[test]
+fn draw_offers_resignation_and_chat_reach_the_opponent() {
+    let address = server::spawn("127.0.0.1:0", ServerConfig::default())
+        .unwrap()
+        .to_string();
+    let mut white = GameClient::new_online(
+        &address,
+        "game",
+        "alice",
+        &TimeControl::parse("300").unwrap(),
+        Variant::Standard,
+        None,
+        Encoding::Json,
+    )
+    .unwrap();
+    let mut black = join(&address, "game", "bob", "300");
+    wait_for(&mut [&mut white, &mut black], |clients| {
+        clients[0].online.as_ref().unwrap().opponent.is_some()
+    });
+    assert_eq!(white.online.as_ref().unwrap().version, PROTOCOL_VERSION);
+
+    white.send_chat("good luck");
+    wait_for(&mut [&mut white, &mut black], |clients| {
+        clients
+            .iter()
+            .all(|client| client.online.as_ref().unwrap().chat.len() == 1)
+    });
+    assert_eq!(
+        black.online.as_ref().unwrap().chat[0],
+        ("alice".to_string(), "good luck".to_string())
+    );
+
+    white.offer_draw_online();
+    wait_for(&mut [&mut white, &mut black], |clients| {
+        clients[1].game_state.draw_offer == Some(PlayerType::White)
+    });
+    black.decline_draw_online();
+    wait_for(&mut [&mut white, &mut black], |clients| {
+        clients[0].game_state.draw_offer.is_none()
+    });
+    black.offer_draw_online();
+    wait_for(&mut [&mut white, &mut black], |clients| {
+        clients[0].game_state.draw_offer == Some(PlayerType::Black)
+    });
+    // offering back takes the offer
+    white.offer_draw_online();
+    wait_for(&mut [&mut white, &mut black], |clients| {
+        clients
+            .iter()
+            .all(|client| client.game_state.is_game_over())
+    });
+    assert_eq!(
+        black.game_state.game_end(),
+        Some(GameEnd::Draw(DrawReason::Agreement))
+    );
+
+    let mut white = join(&address, "second game", "alice", "300");
+    let mut black = join(&address, "second game", "bob", "300");
+    wait_for(&mut [&mut white, &mut black], |clients| {
+        clients[1].online.as_ref().unwrap().opponent.is_some()
+    });
+    black.resign_online();
+    wait_for(&mut [&mut white, &mut black], |clients| {
+        clients
+            .iter()
+            .all(|client| client.game_state.is_game_over())
+    });
+    assert_eq!(
+        white.game_state.game_end(),
+        Some(GameEnd::Win {
+            winner: PlayerType::White,
+            reason: WinReason::Resignation
+        })
+    );
+}
+
+#[test]
+fn version_1_clients_play_against_current_clients() {
+    let address = server::spawn("127.0.0.1:0", ServerConfig::default())
+        .unwrap()
+        .to_string();
+    // what a client of version 1 sends , JSON lines without a version
+    let stream = TcpStream::connect(&address).unwrap();
+    let mut lines = BufReader::new(stream.try_clone().unwrap()).lines();
+    writeln!(
+        &stream,
+        r#"{{"Join":{{"game_id":"game","user_id":"alice","time_control":"300","variant":"Standard","fen":null}}}}"#
+    )
+    .unwrap();
+    let mut next_message = || Message::from_json(&lines.next().unwrap().unwrap()).unwrap();
+    assert!(matches!(
+        next_message(),
+        Message::Game {
+            opponent: None,
+            version: 1,
+            ..
+        }
+    ));
+
+    let mut black = join(&address, "game", "bob", "300");
+    wait_for(&mut [&mut black], |clients| {
+        clients[0].online.as_ref().unwrap().opponent.is_some()
+    });
+    assert_eq!(black.online.as_ref().unwrap().version, PROTOCOL_VERSION);
+    // neither reaches the older client
+    black.send_chat("hello");
+    black.offer_draw_online();
+    wait_for(&mut [&mut black], |clients| {
+        clients[0].online.as_ref().unwrap().chat.len() == 1
+    });
+
+    writeln!(&stream, r#"{{"Move":{{"ply":0,"uci":"e2e4"}}}}"#).unwrap();
+    assert!(matches!(
+        next_message(),
+        Message::Game {
+            opponent: Some(_),
+            ..
+        }
+    ));
+    assert_eq!(
+        next_message(),
+        Message::Move {
+            ply: 0,
+            uci: "e2e4".to_string()
+        }
+    );
+    assert!(matches!(next_message(), Message::Clock(_)));
+    wait_for(&mut [&mut black], |clients| {
+        clients[0].game_state.move_history.len() == 1
+    });
+    assert!(black.is_human_turn());
+}
+
+#[test]
+fn players_reconnect_after_a_restart_and_get_the_missed_moves() {
+    let address = server::spawn("127.0.0.1:0", ServerConfig::default())
+        .unwrap()
+        .to_string();
+    let mut white = join(&address, "game", "alice", "300");
+    let mut black = join(&address, "game", "bob", "300");
+    wait_for(&mut [&mut white, &mut black], |clients| {
+        clients[1].online.as_ref().unwrap().opponent.is_some()
+    });
+    // the app keeps the game when it is closed , the connection goes with it
+    let saved = binary::to_bytes(&black).unwrap();
+    drop(black);
+    play(&mut white, "e2e4");
+
+    let mut black: GameClient = binary::from_bytes(&saved).unwrap();
+    assert!(!black.online.as_ref().unwrap().is_connected());
+    wait_for(&mut [&mut white, &mut black], |clients| {
+        clients[1].is_human_turn()
+    });
+    assert_eq!(black.game_state.move_history.len(), 1);
+    assert_eq!(
+        black.online.as_ref().unwrap().color,
+        Some(PlayerType::Black)
+    );
+    play(&mut black, "e7e5");
+    wait_for(&mut [&mut white, &mut black], |clients| {
+        clients[0].game_state.move_history.len() == 2
+    });
+    assert_eq!(white.game_state.to_fen(), black.game_state.to_fen());
+}
+
+#[test]
+fn seats_are_only_taken_back_with_their_token() {
+    let address = server::spawn("127.0.0.1:0", ServerConfig::default())
+        .unwrap()
+        .to_string();
+    let connect = || {
+        let stream = TcpStream::connect(&address).unwrap();
+        let lines = BufReader::new(stream.try_clone().unwrap()).lines();
+        (stream, lines)
+    };
+    let reconnect = |seat_token: Option<&str>| Message::Reconnect {
+        game_id: "game".to_string(),
+        user_id: "alice".to_string(),
+        version: PROTOCOL_VERSION,
+        seat_token: seat_token.map(str::to_string),
+    };
+    let (white, mut white_lines) = connect();
+    writeln!(
+        &white,
+        "{}",
+        Message::Join {
+            game_id: "game".to_string(),
+            user_id: "alice".to_string(),
+            time_control: "300".to_string(),
+            variant: Variant::Standard,
+            fen: None,
+            version: PROTOCOL_VERSION,
+            seat_token: None,
+        }
+        .to_json()
+    )
+    .unwrap();
+    let Some(Message::Game {
+        seat_token: Some(seat_token),
+        ..
+    }) = Message::from_json(&white_lines.next().unwrap().unwrap())
+    else {
+        panic!("no seat token");
+    };
+
+    let (other, mut other_lines) = connect();
+    let mut ask = |message: Message| {
+        writeln!(&other, "{}", message.to_json()).unwrap();
+        Message::from_json(&other_lines.next().unwrap().unwrap()).unwrap()
+    };
+    let wrong_token = Message::Error("wrong seat token".to_string());
+    assert_eq!(ask(reconnect(None)), wrong_token);
+    assert_eq!(ask(reconnect(Some("0123456789abcdef"))), wrong_token);
+    // a name alone does not take the seat either
+    let join_as_white = Message::Join {
+        game_id: "game".to_string(),
+        user_id: "alice".to_string(),
+        time_control: "300".to_string(),
+        variant: Variant::Standard,
+        fen: None,
+        version: PROTOCOL_VERSION,
+        seat_token: None,
+    };
+    assert_eq!(ask(join_as_white), wrong_token);
+    assert_eq!(
+        ask(reconnect(Some(&seat_token))),
+        Message::Error("the player is still connected".to_string())
+    );
+
+    // once the connection is lost the token takes the seat back
+    white.shutdown(Shutdown::Both).unwrap();
+    let start = Instant::now();
+    loop {
+        match ask(reconnect(Some(&seat_token))) {
+            Message::Game {
+                color,
+                seat_token: kept,
+                ..
+            } => {
+                assert_eq!(color, PlayerType::White);
+                assert_eq!(kept, Some(seat_token));
+                break;
+            }
+            message => assert_eq!(
+                message,
+                Message::Error("the player is still connected".to_string())
+            ),
+        }
+        assert!(start.elapsed() < Duration::from_secs(5), "timed out");
+        std::thread::sleep(Duration::from_millis(10));
+    }
+}
+
+#[test]
+fn players_who_stay_away_lose_by_abandonment() {
+    let config = ServerConfig {
+        abandon_timeout: Duration::from_millis(300),
+        ..ServerConfig::default()
+    };
+    let address = server::spawn("127.0.0.1:0", config).unwrap().to_string();
+    let mut white = join(&address, "game", "alice", "300");
+    let mut black = join(&address, "game", "bob", "300");
+    wait_for(&mut [&mut white, &mut black], |clients| {
+        clients[0].online.as_ref().unwrap().opponent.is_some()
+    });
+    drop(black);
+    wait_for(&mut [&mut white], |clients| {
+        clients[0].game_state.is_game_over()
+    });
+    assert_eq!(
+        white.game_state.game_end(),
+        Some(GameEnd::Win {
+            winner: PlayerType::White,
+            reason: WinReason::Abandonment
+        })
+    );
+}
+
+#[test]
+fn finished_and_deserted_games_are_let_go() {
+    let config = ServerConfig {
+        keep_finished: Duration::from_millis(200),
+        ..ServerConfig::default()
+    };
+    let address = server::spawn("127.0.0.1:0", config).unwrap().to_string();
+    let mut resigned = join(&address, "over", "alice", "300");
+    let mut deserted = join(&address, "deserted", "bob", "300");
+    let mut playing = join(&address, "playing", "carol", "300");
+    wait_for(
+        &mut [&mut resigned, &mut deserted, &mut playing],
+        |clients| {
+            clients
+                .iter()
+                .all(|client| client.online.as_ref().unwrap().color.is_some())
+        },
+    );
+    resigned.resign_online();
+    wait_for(&mut [&mut resigned], |clients| {
+        clients[0].game_state.is_game_over()
+    });
+    drop(deserted);
+    std::thread::sleep(Duration::from_millis(600));
+    playing.update_online();
+
+    for (game_id, kept) in [("over", false), ("deserted", false), ("playing", true)] {
+        let mut transport = transport::connect(&address, Encoding::Binary).unwrap();
+        transport
+            .send(&Message::Watch {
+                game_id: game_id.to_string(),
+                version: PROTOCOL_VERSION,
+            })
+            .unwrap();
+        let start = Instant::now();
+        let message = loop {
+            if let Some(message) = transport.try_recv().unwrap() {
+                break message;
+            }
+            assert!(start.elapsed() < Duration::from_secs(5), "timed out");
+            std::thread::sleep(Duration::from_millis(10));
+        };
+        assert_eq!(
+            matches!(message, Message::Watching { .. }),
+            kept,
+            "{}: {:?}",
+            game_id,
+            message
+        );
+    }
+}
+
+#[test]
+fn spectators_see_the_game_late_and_from_white() {
+    let config = ServerConfig {
+        spectator_delay: Duration::from_millis(1000),
+        ..ServerConfig::default()
+    };
+    let address = server::spawn("127.0.0.1:0", config).unwrap().to_string();
+    let mut white = join(&address, "game", "alice", "300");
+    let mut black = join(&address, "game", "bob", "300");
+    let mut spectator = GameClient::new_spectator(&address, "game", Encoding::Json).unwrap();
+    wait_for(&mut [&mut white, &mut black, &mut spectator], |clients| {
+        clients[2].online.as_ref().unwrap().players[1].is_some()
+    });
+    let online = spectator.online.as_ref().unwrap();
+    assert_eq!(online.players[0].as_deref(), Some("alice"));
+    assert_eq!(online.spectator_delay, Duration::from_millis(1000));
+
+    play(&mut white, "e2e4");
+    wait_for(&mut [&mut white, &mut black, &mut spectator], |clients| {
+        clients[1].game_state.move_history.len() == 1
+    });
+    spectator.update_online();
+    assert!(spectator.game_state.move_history.is_empty());
+    wait_for(&mut [&mut white, &mut black, &mut spectator], |clients| {
+        clients[2].game_state.move_history.len() == 1
+    });
+    assert_eq!(spectator.game_state.to_fen(), white.game_state.to_fen());
+    // black is to move , the board stays the way round it was and can not be played on
+    assert_eq!(
+        spectator.perspective(&spectator.game_state),
+        PlayerType::White
+    );
+    assert!(!spectator.is_human_turn());
+}
+
+#[test]
+fn games_going_on_are_listed() {
+    let address = server::spawn("127.0.0.1:0", ServerConfig::default())
+        .unwrap()
+        .to_string();
+    let mut white = join(&address, "game", "alice", "180+2");
+    let mut black = join(&address, "game", "bob", "300");
+    let mut resigned = join(&address, "over", "carol", "300");
+    wait_for(&mut [&mut white, &mut black, &mut resigned], |clients| {
+        clients[0].online.as_ref().unwrap().opponent.is_some()
+            && clients[2].online.as_ref().unwrap().color.is_some()
+    });
+    resigned.resign_online();
+    wait_for(&mut [&mut resigned], |clients| {
+        clients[0].game_state.is_game_over()
+    });
+    play(&mut white, "e2e4");
+    wait_for(&mut [&mut white, &mut black], |clients| {
+        clients[1].game_state.move_history.len() == 1
+    });
+
+    let mut game_listing = GameListing::request(&address, Encoding::Binary).unwrap();
+    let start = Instant::now();
+    while game_listing.games.is_none() {
+        assert!(start.elapsed() < Duration::from_secs(5), "timed out");
+        game_listing.update();
+        std::thread::sleep(Duration::from_millis(10));
+    }
+    assert_eq!(
+        game_listing.games.unwrap(),
+        [GameSummary {
+            game_id: "game".to_string(),
+            white: Some("alice".to_string()),
+            black: Some("bob".to_string()),
+            time_control: "180+2".to_string(),
+            variant: Variant::Standard,
+            plies: 1,
+        }]
+    );
+    // the names listed do not take the seats of the players
+    let mut impostor = transport::connect(&address, Encoding::Binary).unwrap();
+    impostor
+        .send(&Message::Reconnect {
+            game_id: "game".to_string(),
+            user_id: "alice".to_string(),
+            version: PROTOCOL_VERSION,
+            seat_token: None,
+        })
+        .unwrap();
+    let start = Instant::now();
+    let refusal = loop {
+        if let Some(message) = impostor.try_recv().unwrap() {
+            break message;
+        }
+        assert!(start.elapsed() < Duration::from_secs(5), "timed out");
+        std::thread::sleep(Duration::from_millis(10));
+    };
+    assert_eq!(refusal, Message::Error("wrong seat token".to_string()));
+    play(&mut black, "e7e5");
+    wait_for(&mut [&mut white, &mut black], |clients| {
+        clients[0].game_state.move_history.len() == 2
+    });
+    assert!(white.online.as_ref().unwrap().is_connected());
+
+    let mut spectator = GameClient::new_spectator(&address, "no game", Encoding::Binary).unwrap();
+    wait_for(&mut [&mut spectator], |clients| {
+        clients[0].online.as_ref().unwrap().error.is_some()
+    });
+    assert_eq!(
+        spectator.online.unwrap().error.as_deref(),
+        Some("no such game")
+    );
+}
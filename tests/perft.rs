@@ -0,0 +1,138 @@
+use rusty_chess::common::logic::GameState;
+use rusty_chess::common::variant::Variant;
+
+fn assert_perft(fen: &str, expected: &[u64]) {
+    let game_state = GameState::from_fen(fen).unwrap();
+    for (depth, nodes) in expected.iter().enumerate() {
+        assert_eq!(
+            game_state.perft(depth as u32 + 1),
+            *nodes,
+            "perft({}) of {}",
+            depth + 1,
+            fen
+        );
+    }
+}
+
+#[test]
+fn initial_position() {
+    assert_perft(
+        "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+        &[20, 400, 8902, 197281, 4865609],
+    );
+}
+
+#[test]
+fn kiwipete() {
+    assert_perft(
+        "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+        &[48, 2039, 97862, 4085603],
+    );
+}
+
+#[test]
+fn position_3() {
+    assert_perft(
+        "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+        &[14, 191, 2812, 43238, 674624],
+    );
+}
+
+#[test]
+fn position_4() {
+    assert_perft(
+        "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+        &[6, 264, 9467, 422333],
+    );
+}
+
+#[test]
+fn position_4_mirrored() {
+    assert_perft(
+        "r2q1rk1/pP1p2pp/Q4n2/bbp1p3/Np6/1B3NBn/pPPP1PPP/R3K2R b KQ - 0 1",
+        &[6, 264, 9467, 422333],
+    );
+}
+
+#[test]
+fn position_5() {
+    assert_perft(
+        "rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8",
+        &[44, 1486, 62379, 2103487],
+    );
+}
+
+#[test]
+fn position_6() {
+    assert_perft(
+        "r4rk1/1pp1qppp/p1np1n2/2b1p1B1/2B1P1b1/P1NP1N2/1PP1QPPP/R4RK1 w - - 0 10",
+        &[46, 2079, 89890, 3894594],
+    );
+}
+
+#[test]
+fn divide_adds_up_to_perft() {
+    let game_state =
+        GameState::from_fen("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1")
+            .unwrap();
+    let divided = game_state.divide(2);
+    assert_eq!(divided.len(), 48);
+    assert_eq!(divided.iter().map(|(_, nodes)| nodes).sum::<u64>(), 2039);
+}
+
+fn assert_perft_chess960(fen: &str, expected: &[u64]) {
+    let game_state = GameState::from_fen_chess960(fen).unwrap();
+    for (depth, nodes) in expected.iter().enumerate() {
+        assert_eq!(
+            game_state.perft(depth as u32 + 1),
+            *nodes,
+            "perft({}) of {}",
+            depth + 1,
+            fen
+        );
+    }
+}
+
+#[test]
+fn chess960_positions() {
+    assert_perft_chess960(
+        "bqnb1rkr/pp3ppp/3ppn2/2p5/5P2/P2P4/NPP1P1PP/BQ1BNRKR w HFhf - 2 9",
+        &[21, 528, 12189, 326672],
+    );
+    assert_perft_chess960(
+        "2nnrbkr/p1qppppp/8/1ppb4/6PP/3PP3/PPP2P2/BQNNRBKR w HEhe - 1 9",
+        &[21, 807, 18002, 667366],
+    );
+    assert_perft_chess960(
+        "b1q1rrkb/pppppppp/3nn3/8/P7/1PPP4/4PPPP/BQNNRKRB w GE - 1 9",
+        &[20, 479, 10471, 273318],
+    );
+}
+
+fn assert_perft_variant(variant: Variant, fen: &str, expected: &[u64]) {
+    let game_state = GameState::from_fen_variant(fen, variant, false).unwrap();
+    for (depth, nodes) in expected.iter().enumerate() {
+        assert_eq!(
+            game_state.perft(depth as u32 + 1),
+            *nodes,
+            "{} perft({}) of {}",
+            variant.name(),
+            depth + 1,
+            fen
+        );
+    }
+}
+
+#[test]
+fn variant_positions() {
+    assert_perft_variant(
+        Variant::Antichess,
+        Variant::Antichess.start_fen(),
+        &[20, 400, 8067, 153299],
+    );
+    assert_perft_variant(
+        Variant::Atomic,
+        Variant::Atomic.start_fen(),
+        &[20, 400, 8902, 197326],
+    );
+}
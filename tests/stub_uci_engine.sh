@@ -0,0 +1,29 @@
+#!/bin/sh
+# a tiny uci engine for the adapter tests , it plays e2e4 from the start position
+# and e7e5 from any other one , it crashes when asked to search after d2d4
+position=""
+while read -r line; do
+    case "$line" in
+        uci)
+            echo "id name stub engine"
+            echo "id author rusty chess tests"
+            echo "uciok"
+            ;;
+        isready) echo "readyok" ;;
+        position*) position="$line" ;;
+        go*)
+            case "$position" in
+                *d2d4*) exit 1 ;;
+                *moves*)
+                    echo "info depth 1 score cp -20 nodes 1 pv e7e5"
+                    echo "bestmove e7e5"
+                    ;;
+                *)
+                    echo "info depth 3 score mate 3 nodes 12 pv e2e4 e7e5"
+                    echo "bestmove e2e4 ponder e7e5"
+                    ;;
+            esac
+            ;;
+        quit) exit 0 ;;
+    esac
+done
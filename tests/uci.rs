@@ -0,0 +1,108 @@
+use std::io::{BufRead, BufReader, Write};
+use std::process::{Command, Stdio};
+
+/// sends the commands to the uci binary and collects what it prints up to the first line
+/// starting with `last_line` , the engine is told to quit after that
+fn run_engine(commands: &[&str], last_line: &str) -> Vec<String> {
+    let mut engine = Command::new(env!("CARGO_BIN_EXE_rusty_chess_uci"))
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+    let mut stdin = engine.stdin.take().unwrap();
+    let stdout = BufReader::new(engine.stdout.take().unwrap());
+    for command in commands {
+        writeln!(stdin, "{}", command).unwrap();
+    }
+    let mut lines = vec![];
+    for line in stdout.lines() {
+        let line = line.unwrap();
+        let done = line.starts_with(last_line);
+        lines.push(line);
+        if done {
+            break;
+        }
+    }
+    writeln!(stdin, "quit").unwrap();
+    engine.wait().unwrap();
+    lines
+}
+
+#[test]
+fn handshake() {
+    let lines = run_engine(&["uci", "isready"], "readyok");
+    assert!(lines[0].starts_with("id name"));
+    assert!(lines.contains(&"uciok".to_string()));
+    assert_eq!(lines.last().unwrap(), "readyok");
+}
+
+#[test]
+fn searches_to_a_depth_and_reports() {
+    let lines = run_engine(
+        &["position startpos moves e2e4 e7e5", "go depth 2"],
+        "bestmove",
+    );
+    assert!(lines
+        .iter()
+        .any(|line| line.starts_with("info depth 2 score cp")));
+    let best_move = lines.last().unwrap().strip_prefix("bestmove ").unwrap();
+    assert_eq!(best_move.len(), 4);
+}
+
+#[test]
+fn finds_mate_from_a_fen() {
+    let lines = run_engine(
+        &[
+            "position fen 6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1",
+            "go depth 3",
+        ],
+        "bestmove",
+    );
+    assert!(lines.iter().any(|line| line.contains("score mate 1")));
+    assert_eq!(lines.last().unwrap(), "bestmove a1a8");
+}
+
+#[test]
+fn repetitions_in_the_moves_are_draws() {
+    // Nc6 hangs the knight but brings back a position of the game
+    let lines = run_engine(
+        &[
+            "position fen 1n2k3/8/8/8/2Q5/8/8/R3K3 b - - 0 1 moves b8c6 e1d1 c6b8 d1e1",
+            "go depth 3",
+        ],
+        "bestmove",
+    );
+    assert!(lines
+        .iter()
+        .any(|line| line.starts_with("info depth 3 score cp 0 ")));
+    assert_eq!(lines.last().unwrap(), "bestmove b8c6");
+}
+
+#[test]
+fn stop_ends_an_infinite_search() {
+    let lines = run_engine(&["position startpos", "go infinite", "stop"], "bestmove");
+    assert!(lines.last().unwrap().starts_with("bestmove "));
+}
+
+#[test]
+fn mated_side_has_no_move() {
+    let lines = run_engine(
+        &["position startpos moves f2f3 e7e5 g2g4 d8h4", "go depth 1"],
+        "bestmove",
+    );
+    assert_eq!(lines.last().unwrap(), "bestmove 0000");
+}
+
+#[test]
+fn plays_the_variant_it_is_told() {
+    // in antichess the only legal move is taking the pawn
+    let lines = run_engine(
+        &[
+            "setoption name UCI_Variant value antichess",
+            "position startpos moves e2e4 d7d5",
+            "go depth 2",
+        ],
+        "bestmove",
+    );
+    assert_eq!(lines.last().unwrap(), "bestmove e4d5");
+}
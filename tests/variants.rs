@@ -0,0 +1,98 @@
+use rusty_chess::common::logic::{GameEnd, GameState, PlayerType, WinReason};
+use rusty_chess::common::pgn::{self, PgnGame};
+use rusty_chess::common::variant::{Variant, VARIANTS};
+
+fn play(game_state: &mut GameState, moves: &[&str]) {
+    for san in moves {
+        let chess_move = game_state
+            .move_from_san(san)
+            .unwrap_or_else(|| panic!("{} is not legal", san));
+        game_state.do_move(&chess_move);
+    }
+}
+
+#[test]
+fn variant_tag_round_trips() {
+    for variant in VARIANTS {
+        let mut game_state =
+            GameState::from_fen_variant(variant.start_fen(), variant, false).unwrap();
+        play(&mut game_state, &["e4", "e5", "Nf3", "Nc6"]);
+        let pgn_text = PgnGame::from_game_state(&game_state, None).to_pgn();
+        let game = &pgn::parse_pgn(&pgn_text)[0];
+        match variant {
+            Variant::Standard => assert_eq!(game.tag("Variant"), None),
+            _ => assert_eq!(game.tag("Variant"), Some(variant.name())),
+        }
+        // games from the start position of the variant need no FEN tag
+        assert_eq!(game.tag("FEN"), None);
+        let replayed = game.replay().unwrap();
+        assert_eq!(replayed.variant(), variant);
+        assert_eq!(replayed.to_fen(), game_state.to_fen());
+    }
+}
+
+#[test]
+fn variant_games_from_a_position_round_trip() {
+    // white has given two checks already
+    let fen = "4k3/8/8/8/8/8/8/R3K3 w - - 1+3 0 1";
+    let mut game_state = GameState::from_fen_variant(fen, Variant::ThreeCheck, false).unwrap();
+    play(&mut game_state, &["Ra8+"]);
+    assert!(game_state.is_game_over());
+    let pgn_text = PgnGame::from_game_state(&game_state, None).to_pgn();
+    assert!(pgn_text.contains("[Variant \"Three-check\"]"));
+    assert!(pgn_text.contains("[FEN \"4k3/8/8/8/8/8/8/R3K3 w - - 1+3 0 1\"]"));
+    assert!(pgn_text.contains("1. Ra8+ 1-0"));
+    let replayed = pgn::parse_pgn(&pgn_text)[0].replay().unwrap();
+    assert_eq!(replayed.game_end(), game_state.game_end());
+}
+
+#[test]
+fn atomic_game_ends_without_a_king() {
+    let mut game_state =
+        GameState::from_fen_variant(Variant::Atomic.start_fen(), Variant::Atomic, false).unwrap();
+    play(&mut game_state, &["Nf3", "d5", "Ne5", "e6", "Nxf7"]);
+    assert_eq!(
+        game_state.game_end().unwrap().winner(),
+        Some(game_state.active_color.opponent())
+    );
+    assert!(game_state.generate_legal_moves().is_empty());
+    // the position with the king blown up can still be looked at again
+    let last = game_state.move_history.len();
+    assert_eq!(
+        game_state.position_at_ply(last).unwrap().to_fen(),
+        game_state.to_fen()
+    );
+    let pgn_text = PgnGame::from_game_state(&game_state, None).to_pgn();
+    assert!(pgn_text.contains("3. Nxf7 1-0"));
+}
+
+#[test]
+fn three_check_positions_differ_in_the_checks_given() {
+    let shuffle = ["Qh5+", "Kd8", "Qh1", "Ke8"];
+    let fen = "4k3/8/8/8/8/8/8/4K2Q w - - 0 1";
+    let mut standard = GameState::from_fen_variant(fen, Variant::Standard, false).unwrap();
+    let mut three_check = GameState::from_fen_variant(fen, Variant::ThreeCheck, false).unwrap();
+    for _ in 0..2 {
+        play(&mut standard, &shuffle);
+        play(&mut three_check, &shuffle);
+    }
+    assert_eq!(standard.repetition_count(), 3);
+    assert!(standard.claimable_draw().is_some());
+    // the pieces stand where they started , but with two checks given
+    assert_eq!(three_check.repetition_count(), 1);
+    assert_eq!(three_check.claimable_draw(), None);
+    assert_ne!(
+        three_check.position_key(),
+        GameState::from_fen_variant(fen, Variant::ThreeCheck, false)
+            .unwrap()
+            .position_key()
+    );
+    play(&mut three_check, &shuffle[..1]);
+    assert_eq!(
+        three_check.game_end(),
+        Some(GameEnd::Win {
+            winner: PlayerType::White,
+            reason: WinReason::ThreeChecks
+        })
+    );
+}